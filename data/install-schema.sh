@@ -0,0 +1,12 @@
+#!/bin/sh
+# Install and compile the GSettings schema, e.g. to /usr/share or
+# ~/.local/share. Without it, the persisted state is kept in a keyfile.
+#
+#   data/install-schema.sh [DATADIR]
+set -e
+cd "$(dirname "$0")"
+schemas="${1:-${XDG_DATA_HOME:-$HOME/.local/share}}/glib-2.0/schemas"
+
+mkdir -p "$schemas"
+cp dev.rouge.Software.gschema.xml "$schemas/"
+glib-compile-schemas "$schemas"
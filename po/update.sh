@@ -0,0 +1,30 @@
+#!/bin/sh
+# Maintain the translations of the languages listed in LINGUAS.
+#
+#   po/update.sh             extract the strings of the files listed in
+#                            POTFILES.in to rouge_software.pot, and merge
+#                            them into the existing translations
+#   po/update.sh install DIR compile the translations to DIR, which the
+#                            application looks in when built with
+#                            LOCALEDIR=DIR
+set -e
+cd "$(dirname "$0")"
+domain=rouge_software
+
+if [ "$1" = install ]; then
+  for lang in $(cat LINGUAS); do
+    mkdir -p "$2/$lang/LC_MESSAGES"
+    msgfmt --check --output-file="$2/$lang/LC_MESSAGES/$domain.mo" "$lang.po"
+  done
+  exit
+fi
+
+# tr(msgid), ntr(singular, plural, n) and tr_ctx(context, msgid), see
+# src/reactive/i18n.rs.
+xgettext --from-code=UTF-8 --language=C \
+  --keyword=tr --keyword=ntr:1,2 --keyword=tr_ctx:1c,2 \
+  --add-comments=Translators --directory=.. --files-from=POTFILES.in \
+  --package-name=$domain --output=$domain.pot
+for lang in $(cat LINGUAS); do
+  msgmerge --quiet --update "$lang.po" $domain.pot
+done
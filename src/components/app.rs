@@ -1,14 +1,52 @@
-use adw::{Application, HeaderBar, Window};
-use gtk4::prelude::{BoxExt, ButtonExt, GtkWindowExt, OrientableExt, WidgetExt};
-use gtk4::{Box, Button, Label, Orientation, ScrolledWindow};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use adw::gio::{
+  prelude::{ApplicationExt, ApplicationExtManual, CancellableExt, FileExt, ListModelExtManual},
+  ApplicationFlags, Cancellable, File, ListStore, Menu, SimpleAction,
+};
+use adw::glib::{object::CastNone, Error};
+use adw::prelude::{AdwDialogExt, AlertDialogExt};
+use adw::{AlertDialog, Application, Dialog, HeaderBar, ResponseAppearance, Window};
+use gtk4::prelude::{
+  ActionableExt, BoxExt, ButtonExt, GtkWindowExt, OrientableExt, ToggleButtonExt, WidgetExt,
+};
+use gtk4::{
+  gdk::Key, Align, Box, Button, FileDialog, FileFilter, Label, ListView, MenuButton, Orientation,
+  ProgressBar, ScrolledWindow, SearchEntry, Switch, ToggleButton,
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
 
 use crate::components::counter::Counter;
 use crate::reactive::component::{UpdateAction, ViewContext};
+use crate::reactive::helpers::action_ext::ReactiveActionExt;
+use crate::reactive::helpers::dialog_ext::ReactiveAlertDialogExt;
 use crate::reactive::helpers::widget_ext::ReactiveWidgetExt;
+use crate::reactive::i18n::{fill, format_number, format_size, ntr, tr, tr_ctx};
+use crate::reactive::persist::{self, Persistence};
+use crate::reactive::replay;
+use crate::reactive::scope::Scope;
+use crate::reactive::vnode::vaccessible::VAccessible;
+use crate::reactive::vnode::vaction::VActionBuilder;
+use crate::reactive::vnode::vanimation::{VAnimation, VTransition};
 use crate::reactive::vnode::vcomponent::VComponentBuilder;
+use crate::reactive::vnode::vcontrolled::VControlledBuilder;
+use crate::reactive::vnode::vcontroller::VController;
+use crate::reactive::vnode::vlist::VListBuilder;
+use crate::reactive::vnode::vmenu::{VMenuBuilder, VMenuItem};
 use crate::reactive::vnode::vobject::VObjectBuilder;
+use crate::reactive::worker::{self, Worker};
 use crate::reactive::{component::Component, vnode::VNode};
-use crate::services::flatpak::{self, FpRef};
+use crate::services::catalog::{self, Catalog, Component as CatalogComponent};
+use crate::services::flatpak::file::{self, RefFile};
+use crate::services::flatpak::permissions::{
+  self, AppPermissions, OverrideScope, Permission, Permissions,
+};
+use crate::services::flatpak::remote;
+use crate::services::flatpak::transaction::{self, Operation, OperationKind, TransactionEvent};
+use crate::services::flatpak::{self, FpRef, InstallationId, PendingUpdate, RefKind};
+use crate::services::search::{Query, SearchIndex};
 
 //
 // State.
@@ -17,14 +55,386 @@ use crate::services::flatpak::{self, FpRef};
 #[derive(Clone, Debug, Default)]
 pub struct App {
   count: u8,
-  refs: Vec<FpRef>,
+  confirm_reset: bool,
+  filter: String,
+  refs: Option<Result<Vec<FpRef>, String>>,
+  window_width: i32,
+  window_height: i32,
+  sort: SortOrder,
+  page: Page,
+  /// Checked when the updates page is first shown.
+  updates: Option<Result<Vec<PendingUpdate>, String>>,
+  /// Loaded when the explore page is first shown.
+  catalog: Option<Result<Rc<Catalog>, String>>,
+  /// Of the catalog and the installed refs, rebuilt when either changes.
+  search: Rc<SearchIndex>,
+  transaction: Option<RunningTransaction>,
+  /// Why the last transaction failed, until the user dismisses it.
+  transaction_error: Option<String>,
+  confirm_uninstall: Option<FpRef>,
+  /// The installation to look for unused runtimes in once the transaction
+  /// is over.
+  cleanup: Option<InstallationId>,
+  /// Runtimes left unused by an uninstall, offered for removal.
+  unused: Vec<FpRef>,
+  /// Files opened by the user, each offered for installation in turn.
+  opened: Vec<(PathBuf, Result<RefFile, String>)>,
+  /// The app whose permissions are shown.
+  permissions_of: Option<FpRef>,
+  /// Its permissions once read, edited by the user until saved.
+  permissions: Option<Result<AppPermissions, String>>,
 }
 
+/// The transaction being run, if any.
 #[derive(Clone, Debug)]
+struct RunningTransaction {
+  operations: Vec<Operation>,
+  done: usize,
+  current: Option<Operation>,
+  status: Option<String>,
+  progress: u32,
+  cancellable: Cancellable,
+}
+
+impl RunningTransaction {
+  fn new(cancellable: Cancellable) -> Self {
+    RunningTransaction {
+      operations: vec![],
+      done: 0,
+      current: None,
+      status: None,
+      progress: 0,
+      cancellable,
+    }
+  }
+
+  fn fraction(&self) -> f64 {
+    if self.operations.is_empty() {
+      return 0.0;
+    }
+    let done = self.done as f64 + f64::from(self.progress) / 100.0;
+    (done / self.operations.len() as f64).min(1.0)
+  }
+
+  fn download_size(&self) -> u64 {
+    self.operations.iter().map(|o| o.download_size).sum()
+  }
+
+  fn apply(&mut self, event: TransactionEvent) {
+    match event {
+      // Updates of several installations run one transaction each.
+      TransactionEvent::Ready(operations) => self.operations.extend(operations),
+      TransactionEvent::Started(operation) => {
+        self.current = Some(operation);
+        self.status = None;
+        self.progress = 0;
+      }
+      TransactionEvent::Progress {
+        status, progress, ..
+      } => {
+        self.status = status;
+        self.progress = progress;
+      }
+      TransactionEvent::Done { .. } => {
+        self.done += 1;
+        self.progress = 0;
+      }
+      TransactionEvent::Failed { message, .. } => self.status = Some(message),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Page {
+  Explore,
+  #[default]
+  Installed,
+  Updates,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortOrder {
+  #[default]
+  Ascending,
+  Descending,
+}
+
+/// What's kept across restarts, see `data/dev.rouge.Software.gschema.xml`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct AppSettings {
+  window_width: i32,
+  window_height: i32,
+  sort_order: SortOrder,
+  page: Page,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum AppMessage {
   Increment,
   Decrement,
   Add(i8),
+  AskReset,
+  Reset(bool),
+  Filter(String),
+  Refs(Result<Vec<FpRef>, String>),
+  WindowSize(i32, i32),
+  Sort(SortOrder),
+  Install {
+    installation: InstallationId,
+    remote: String,
+    ref_: String,
+  },
+  Transaction(TransactionEvent),
+  TransactionDone(Result<(), String>),
+  CancelTransaction,
+  DismissTransactionError,
+  AskUninstall(FpRef),
+  Uninstall {
+    confirmed: bool,
+    remove_data: bool,
+  },
+  Unused(Vec<FpRef>),
+  RemoveUnused(bool),
+  Page(Page),
+  CheckUpdates,
+  Updates(Result<Vec<PendingUpdate>, String>),
+  /// Apply some of the pending updates, or all of them.
+  Update(Vec<PendingUpdate>),
+  OpenFiles(Vec<PathBuf>),
+  Opened(Vec<(PathBuf, Result<RefFile, String>)>),
+  /// Install the first opened file in an installation, or skip it.
+  InstallFile(Option<InstallationId>),
+  Catalog(Result<Catalog, String>),
+  ShowPermissions(FpRef),
+  Permissions(Result<std::boxed::Box<AppPermissions>, String>),
+  SetPermission(Permission, bool),
+  /// Close the permissions, saving the overrides of the user, or removing
+  /// them all.
+  SavePermissions {
+    confirmed: bool,
+    reset: bool,
+  },
+  PermissionsSaved(FpRef, Result<(), String>),
+}
+
+fn list_refs() -> AppMessage {
+  AppMessage::Refs(flatpak::list().map_err(|e| e.to_string()))
+}
+
+/// List installed apps.
+fn refresh() -> UpdateAction<App> {
+  worker::spawn_blocking(|_| list_refs())
+}
+
+fn list_updates() -> AppMessage {
+  AppMessage::Updates(flatpak::updates().map_err(|e| e.to_string()))
+}
+
+/// Load the catalog from the cache, then again once refreshed from the
+/// remotes.
+fn load_catalog() -> UpdateAction<App> {
+  worker::spawn_blocking(refresh_catalog)
+}
+
+fn refresh_catalog(w: &Worker<AppMessage>) -> AppMessage {
+  let load = || AppMessage::Catalog(catalog::load().map_err(|e| e.to_string()));
+  w.progress(load());
+  let remotes = remote::list().unwrap_or_else(|error| {
+    warn!("Unable to list remotes: {}", error);
+    vec![]
+  });
+  for remote in remotes.iter().filter(|remote| remote.enabled) {
+    if w.is_cancelled() {
+      break;
+    }
+    if let Err(error) = catalog::refresh(&remote.installation, &remote.name) {
+      warn!(
+        "Unable to refresh the catalog of {}: {}",
+        remote.name, error
+      );
+    }
+  }
+  load()
+}
+
+/// Let the user pick `.flatpakref` and `.flatpak` files to install.
+fn choose_files(parent: &Button, scope: Scope<App>) {
+  let filter = FileFilter::new();
+  filter.set_name(Some(&tr("Flatpak Files")));
+  filter.add_suffix("flatpakref");
+  filter.add_suffix("flatpak");
+  let filters = ListStore::new::<FileFilter>();
+  filters.append(&filter);
+
+  let dialog = FileDialog::builder()
+    .title(tr("Install from File"))
+    .filters(&filters)
+    .modal(true)
+    .build();
+  let window = parent.root().and_downcast::<gtk4::Window>();
+  dialog.open_multiple(window.as_ref(), None::<&Cancellable>, move |files| {
+    // Dismissing the dialog is an error too.
+    let Ok(files) = files else {
+      return;
+    };
+    let paths = files
+      .iter::<File>()
+      .filter_map(|file| file.ok()?.path())
+      .collect();
+    scope.send_message(AppMessage::OpenFiles(paths));
+  });
+}
+
+/// The first characters of a commit, enough to tell it apart.
+fn short_commit(commit: &str) -> &str {
+  commit.get(..10).unwrap_or(commit)
+}
+
+/// The section and the name of `permission` in the permissions dialog.
+fn describe_permission(permission: &Permission) -> (String, &str) {
+  match permission {
+    Permission::Shared(name) => (tr("Share"), name),
+    Permission::Socket(name) => (tr("Sockets"), name),
+    Permission::Device(name) => (tr("Devices"), name),
+    Permission::Feature(name) => (tr("Features"), name),
+    Permission::Filesystem(path) => (tr("Filesystems"), path),
+    Permission::SessionBus(name) => (tr("Session Bus"), name),
+    Permission::SystemBus(name) => (tr("System Bus"), name),
+  }
+}
+
+/// A switch for each permission of an app, by section, then the variables
+/// and directories it gets, which can't be changed here.
+fn permission_rows(permissions: &AppPermissions) -> VNode<'_, App> {
+  let effective = permissions.effective();
+  let heading = |label: String| {
+    Label::c(move |w| {
+      w.set_label(&label);
+      w.set_xalign(0.0);
+      w.set_margin_top(10);
+    })
+    .classes(&["heading"])
+  };
+
+  let mut rows: Vec<VNode<App>> = vec![];
+  let mut section = None;
+  for permission in permissions.permissions() {
+    let (kind, name) = describe_permission(&permission);
+    if section.as_ref() != Some(&kind) {
+      rows.push(heading(kind.clone()));
+      section = Some(kind);
+    }
+    let name = name.to_string();
+    let tooltip = permission.to_string();
+    let allowed = effective.allows(&permission);
+    let changed = permissions.is_overridden(OverrideScope::User, &permission);
+    rows.push(
+      Box::c(|w| {
+        w.set_orientation(Orientation::Horizontal);
+        w.set_spacing(10);
+      })
+      .children(vec![
+        //
+        Label::c({
+          let name = name.clone();
+          move |w| {
+            w.set_label(&name);
+            w.set_tooltip_text(Some(&tooltip));
+            w.set_xalign(0.0);
+            w.set_hexpand(true);
+          }
+        }),
+        Label::c(move |w| {
+          w.set_label(&tr("Changed"));
+          w.set_visible(changed);
+        })
+        .classes(&["dim-label"]),
+        Switch::ce(move |w, c| {
+          w.set_active(allowed);
+          let permission = permission.clone();
+          vec![w.connect_active_notify(
+            c.d(move |w: &Switch| AppMessage::SetPermission(permission.clone(), w.is_active())),
+          )]
+        })
+        .accessible(vec![VAccessible::Label(name)]),
+      ]),
+    );
+  }
+
+  let fixed = [
+    (
+      tr("Environment"),
+      effective
+        .environment
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>(),
+    ),
+    (
+      tr("Persistent Directories"),
+      effective.persistent.iter().cloned().collect(),
+    ),
+  ];
+  for (kind, values) in fixed {
+    if values.is_empty() {
+      continue;
+    }
+    rows.push(heading(kind));
+    for value in values {
+      rows.push(
+        Label::c(move |w| {
+          w.set_label(&value);
+          w.set_xalign(0.0);
+          w.set_selectable(true);
+        })
+        .classes(&["dim-label"]),
+      );
+    }
+  }
+
+  Box::c(|w| {
+    w.set_orientation(Orientation::Vertical);
+    w.set_spacing(5);
+    w.set_margin_all(10);
+  })
+  .children(rows)
+}
+
+impl App {
+  /// Index the catalog and the installed refs loaded so far.
+  fn reindex(&mut self) {
+    let empty = Catalog::default();
+    let catalog = match &self.catalog {
+      Some(Ok(catalog)) => catalog.as_ref(),
+      _ => &empty,
+    };
+    let refs = match &self.refs {
+      Some(Ok(refs)) => refs.as_slice(),
+      _ => &[],
+    };
+    self.search = Rc::new(SearchIndex::new(catalog, refs));
+  }
+
+  /// Run the transaction started by `run` in the background, unless one is
+  /// already running.
+  fn start_transaction<F>(&mut self, run: F) -> UpdateAction<Self>
+  where
+    F: 'static + Send + FnOnce(Worker<AppMessage>, &Cancellable) -> Result<(), Error>,
+  {
+    if self.transaction.is_some() {
+      return UpdateAction::None;
+    }
+    let cancellable = Cancellable::new();
+    self.transaction = Some(RunningTransaction::new(cancellable.clone()));
+    worker::spawn_blocking(move |w| {
+      w.link_cancellable(&cancellable);
+      let result = run(w.clone(), &cancellable);
+      AppMessage::TransactionDone(result.map_err(|e| e.to_string()))
+    })
+  }
 }
 
 //
@@ -36,19 +446,71 @@ impl Component for App {
   type Props = ();
 
   fn create(_: Self::Props) -> Self {
-    // List installed apps.
-    let refs = flatpak::list().expect("Error listing flatpaks.");
+    persist::restored(App {
+      count: 0,
+      confirm_reset: false,
+      filter: String::new(),
+      refs: None,
+      window_width: 300,
+      window_height: 100,
+      sort: SortOrder::Ascending,
+      page: Page::Installed,
+      updates: None,
+      catalog: None,
+      search: Rc::default(),
+      transaction: None,
+      transaction_error: None,
+      confirm_uninstall: None,
+      cleanup: None,
+      unused: vec![],
+      opened: vec![],
+      permissions_of: None,
+      permissions: None,
+    })
+  }
 
-    // let refs: Vec<FpRef> = vec![FpRef {
-    //   name: "Hello".to_string(),
-    //   summary: "Hello".to_string(),
-    //   version: "Hello".to_string(),
-    // }];
+  fn persistence() -> Option<Persistence<Self>> {
+    Some(Persistence::new(
+      "dev.rouge.Software",
+      |app: &App| AppSettings {
+        window_width: app.window_width,
+        window_height: app.window_height,
+        sort_order: app.sort,
+        page: app.page,
+      },
+      |app, settings| {
+        app.window_width = settings.window_width;
+        app.window_height = settings.window_height;
+        app.sort = settings.sort_order;
+        app.page = settings.page;
+      },
+    ))
+  }
 
-    App { count: 0, refs }
+  fn mounted(&mut self) -> UpdateAction<Self> {
+    // The page may be restored from the last run.
+    match self.page {
+      Page::Explore => worker::spawn_blocking(|w| {
+        w.progress(list_refs());
+        refresh_catalog(w)
+      }),
+      Page::Installed => refresh(),
+      Page::Updates => worker::spawn_blocking(|w| {
+        w.progress(list_refs());
+        list_updates()
+      }),
+    }
   }
 
-  fn update(&mut self, message: Self::Message) -> UpdateAction {
+  fn encode_message(message: &AppMessage) -> Option<serde_json::Value> {
+    replay::encode(message)
+  }
+
+  fn decode_message(message: serde_json::Value) -> Option<AppMessage> {
+    replay::decode(message)
+  }
+
+  fn update(&mut self, message: Self::Message) -> UpdateAction<Self> {
     match message {
       AppMessage::Increment => {
         self.count = self.count.saturating_add(1);
@@ -62,79 +524,1074 @@ impl Component for App {
         self.count = self.count.saturating_add_signed(delta);
         UpdateAction::Render
       }
+      AppMessage::AskReset => {
+        self.confirm_reset = true;
+        UpdateAction::Render
+      }
+      AppMessage::Reset(confirmed) => {
+        if confirmed {
+          self.count = 0;
+        }
+        self.confirm_reset = false;
+        UpdateAction::Render
+      }
+      AppMessage::Filter(filter) => {
+        self.filter = filter;
+        UpdateAction::Render
+      }
+      AppMessage::Refs(refs) => {
+        self.refs = Some(refs);
+        self.reindex();
+        UpdateAction::Render
+      }
+      AppMessage::WindowSize(width, height) => {
+        self.window_width = width;
+        self.window_height = height;
+        // The window already has this size.
+        UpdateAction::None
+      }
+      AppMessage::Sort(sort) => {
+        self.sort = sort;
+        UpdateAction::Render
+      }
+      AppMessage::Install {
+        installation,
+        remote,
+        ref_,
+      } => self.start_transaction(move |worker, cancellable| {
+        transaction::install(
+          &installation,
+          &remote,
+          &ref_,
+          move |event| worker.progress(AppMessage::Transaction(event)),
+          cancellable,
+        )
+      }),
+      AppMessage::Transaction(event) => {
+        if let Some(transaction) = &mut self.transaction {
+          transaction.apply(event);
+        }
+        UpdateAction::Render
+      }
+      AppMessage::TransactionDone(result) => {
+        let transaction = self.transaction.take();
+        let cleanup = self.cleanup.take();
+        // Cancelling is no failure.
+        let cancelled = transaction.is_some_and(|t| t.cancellable.is_cancelled());
+        if let Err(error) = result {
+          if !cancelled {
+            self.transaction_error = Some(error);
+          }
+          return refresh();
+        }
+        let check_updates = self.updates.is_some();
+        match cleanup {
+          Some(installation) => worker::spawn_blocking(move |w| {
+            w.progress(list_refs());
+            if check_updates {
+              w.progress(list_updates());
+            }
+            AppMessage::Unused(flatpak::unused_in(&installation).unwrap_or_else(|error| {
+              warn!("Unable to list unused refs of {}: {}", installation, error);
+              vec![]
+            }))
+          }),
+          None if check_updates => worker::spawn_blocking(|w| {
+            w.progress(list_refs());
+            list_updates()
+          }),
+          None => refresh(),
+        }
+      }
+      AppMessage::CancelTransaction => {
+        if let Some(transaction) = &self.transaction {
+          transaction.cancellable.cancel();
+        }
+        UpdateAction::None
+      }
+      AppMessage::DismissTransactionError => {
+        self.transaction_error = None;
+        UpdateAction::Render
+      }
+      AppMessage::AskUninstall(ref_) => {
+        self.confirm_uninstall = Some(ref_);
+        UpdateAction::Render
+      }
+      AppMessage::Uninstall {
+        confirmed,
+        remove_data,
+      } => match self.confirm_uninstall.take() {
+        Some(ref_) if confirmed && self.transaction.is_none() => {
+          self.cleanup = Some(ref_.installation.clone());
+          self.start_transaction(move |worker, cancellable| {
+            transaction::uninstall(
+              &ref_,
+              remove_data,
+              move |event| worker.progress(AppMessage::Transaction(event)),
+              cancellable,
+            )
+          })
+        }
+        _ => UpdateAction::Render,
+      },
+      AppMessage::Unused(refs) => {
+        self.unused = refs;
+        UpdateAction::Render
+      }
+      AppMessage::RemoveUnused(confirmed) => {
+        let unused = std::mem::take(&mut self.unused);
+        let Some(installation) = unused.first().map(|r| r.installation.clone()) else {
+          return UpdateAction::Render;
+        };
+        if !confirmed {
+          return UpdateAction::Render;
+        }
+        let refs: Vec<String> = unused.iter().map(FpRef::format_ref).collect();
+        self.start_transaction(move |worker, cancellable| {
+          transaction::uninstall_refs(
+            &installation,
+            &refs,
+            move |event| worker.progress(AppMessage::Transaction(event)),
+            cancellable,
+          )
+        })
+      }
+      AppMessage::Page(page) => {
+        self.page = page;
+        match page {
+          Page::Updates if self.updates.is_none() => worker::spawn_blocking(|_| list_updates()),
+          Page::Explore if self.catalog.is_none() => load_catalog(),
+          _ => UpdateAction::Render,
+        }
+      }
+      AppMessage::CheckUpdates => {
+        self.updates = None;
+        worker::spawn_blocking(|_| list_updates())
+      }
+      AppMessage::Updates(updates) => {
+        self.updates = Some(updates);
+        UpdateAction::Render
+      }
+      AppMessage::Update(updates) => self.start_transaction(move |worker, cancellable| {
+        transaction::update(
+          &updates,
+          move |event| worker.progress(AppMessage::Transaction(event)),
+          cancellable,
+        )
+      }),
+      AppMessage::OpenFiles(paths) => worker::spawn_blocking(move |_| {
+        AppMessage::Opened(
+          paths
+            .into_iter()
+            .map(|path| {
+              let file = file::read(&path).map_err(|e| e.to_string());
+              (path, file)
+            })
+            .collect(),
+        )
+      }),
+      AppMessage::Catalog(catalog) => {
+        self.catalog = Some(catalog.map(Rc::new));
+        self.reindex();
+        UpdateAction::Render
+      }
+      AppMessage::ShowPermissions(app) => {
+        self.permissions_of = Some(app.clone());
+        self.permissions = None;
+        worker::spawn_blocking(move |_| {
+          AppMessage::Permissions(
+            permissions::read(&app)
+              .map(std::boxed::Box::new)
+              .map_err(|e| e.to_string()),
+          )
+        })
+      }
+      AppMessage::Permissions(result) => {
+        // Unless they were closed meanwhile.
+        let Some(app) = &self.permissions_of else {
+          return UpdateAction::None;
+        };
+        if result.as_ref().is_ok_and(|read| read.app_id != app.id) {
+          return UpdateAction::None;
+        }
+        self.permissions = Some(
+          result
+            .map(|read| *read)
+            .map_err(|error| fill(&tr("Unable to read the permissions: {}"), &[&error])),
+        );
+        UpdateAction::Render
+      }
+      AppMessage::SetPermission(permission, allowed) => {
+        if let Some(Ok(permissions)) = &mut self.permissions {
+          permissions.set(OverrideScope::User, &permission, allowed);
+        }
+        UpdateAction::Render
+      }
+      AppMessage::SavePermissions { confirmed, reset } => {
+        let app = self.permissions_of.take();
+        match (app, self.permissions.take()) {
+          // The dialog stays open until they're saved.
+          (Some(app), Some(Ok(edited))) if confirmed => {
+            let overrides = if reset {
+              Permissions::default()
+            } else {
+              edited.user
+            };
+            worker::spawn_blocking(move |_| {
+              let result =
+                permissions::write_overrides(OverrideScope::User, &edited.app_id, &overrides);
+              AppMessage::PermissionsSaved(app, result.map_err(|e| e.to_string()))
+            })
+          }
+          _ => UpdateAction::Render,
+        }
+      }
+      AppMessage::PermissionsSaved(app, result) => match result {
+        Ok(()) => UpdateAction::Render,
+        Err(error) => {
+          self.permissions_of = Some(app);
+          self.permissions = Some(Err(fill(
+            &tr("Unable to save the permissions: {}"),
+            &[&error],
+          )));
+          UpdateAction::Render
+        }
+      },
+      AppMessage::Opened(files) => {
+        self.opened.extend(files);
+        UpdateAction::Render
+      }
+      AppMessage::InstallFile(target) => {
+        // Keep the file until the running transaction is over.
+        if self.opened.is_empty() || (target.is_some() && self.transaction.is_some()) {
+          return UpdateAction::None;
+        }
+        match (self.opened.remove(0), target) {
+          ((_, Ok(file)), Some(installation)) => {
+            self.start_transaction(move |worker, cancellable| {
+              transaction::install_file(
+                &installation,
+                &file,
+                move |event| worker.progress(AppMessage::Transaction(event)),
+                cancellable,
+              )
+            })
+          }
+          _ => UpdateAction::Render,
+        }
+      }
     }
   }
 
   fn view(&self, c: &ViewContext<Self>) -> VNode<App> {
-    let items: Vec<VNode<Self>> = self
-      .refs
-      .iter()
-      .map(|r| {
-        Box::c(|w| {
-          w.set_orientation(Orientation::Horizontal);
-          w.set_spacing(10);
-          w.set_margin_all(10);
-        })
-        .children(vec![
-          //
-          Label::c(|w| {
-            w.set_label(&r.name);
-            if self.count % 2 == 0 {
-              w.add_css_class("accent");
-            }
-          }),
-          Label::c(|w| {
-            w.set_label(&r.summary);
-          }),
-          Label::c(|w| {
-            w.set_label(&r.version);
-          }),
-        ])
+    let refs: VNode<Self> = match &self.refs {
+      None => Label::c(|w| {
+        w.set_label(&tr("Loading installed apps…"));
+        w.set_margin_all(10);
+      }),
+      Some(Err(error)) => Label::c(move |w| {
+        w.set_label(&fill(&tr("Error listing flatpaks: {}"), &[error]));
+        w.set_margin_all(10);
       })
-      .collect();
+      .transition(VTransition::fade(VAnimation::timed(200))),
+      Some(Ok(refs)) => ListView::list(
+        {
+          // Positions in the whole list are stable when filtering and sorting.
+          let hits = self.search.search(&Query {
+            text: self.filter.clone(),
+            installed: Some(true),
+            ..Default::default()
+          });
+          let mut items: Vec<(String, FpRef)> = hits
+            .iter()
+            .filter_map(|hit| hit.installed)
+            .filter_map(|index| Some((index.to_string(), refs.get(index)?.clone())))
+            .collect();
+          // Search results are ranked, the whole list is sorted by name.
+          if self.filter.trim().is_empty() {
+            items.sort_by_cached_key(|(_, r)| r.display_name().to_lowercase());
+            if self.sort == SortOrder::Descending {
+              items.reverse();
+            }
+          }
+          items
+        },
+        |_, _| vec![],
+        |r| {
+          Box::c(|w| {
+            w.set_orientation(Orientation::Horizontal);
+            w.set_spacing(10);
+            w.set_margin_all(10);
+          })
+          .children(vec![
+            //
+            Label::c(|w| {
+              w.set_label(r.display_name());
+              w.set_tooltip_text(Some(&format!("{} ({})", r.format_ref(), r.installation)));
+            })
+            .classes(&["heading"]),
+            Label::c(|w| {
+              w.set_label(r.summary.as_deref().unwrap_or_default());
+            })
+            .classes(&["dim-label"]),
+            Label::c(|w| {
+              // Runtimes have no appdata, but their branch is their version.
+              w.set_label(r.version.as_deref().unwrap_or(&r.branch));
+            }),
+            Label::c(|w| {
+              w.set_label(&format_size(r.installed_size));
+            })
+            .classes(&["dim-label"]),
+            Button::ce(|w, c| {
+              w.set_icon_name("security-high-symbolic");
+              w.set_tooltip_text(Some(&tr("Permissions")));
+              // Runtimes have no sandbox of their own.
+              w.set_visible(r.kind == RefKind::App);
+              let target = r.clone();
+              vec![w.connect_clicked(c.d(move |_| AppMessage::ShowPermissions(target.clone())))]
+            })
+            .classes(&["flat"])
+            .accessible(vec![VAccessible::Label(tr("Permissions"))]),
+            Button::ce(|w, c| {
+              w.set_icon_name("user-trash-symbolic");
+              w.set_tooltip_text(Some(&tr("Uninstall")));
+              let target = r.clone();
+              vec![w.connect_clicked(c.d(move |_| AppMessage::AskUninstall(target.clone())))]
+            })
+            .classes(&["flat"])
+            .accessible(vec![VAccessible::Label(tr("Uninstall"))]),
+          ])
+        },
+      ),
+    };
+
+    let updates: VNode<Self> = match &self.updates {
+      None => Label::c(|w| {
+        w.set_label(&tr("Checking for updates…"));
+        w.set_margin_all(10);
+      }),
+      Some(Err(error)) => Label::c(|w| {
+        w.set_label(&fill(&tr("Error checking for updates: {}"), &[error]));
+        w.set_margin_all(10);
+      }),
+      Some(Ok(updates)) if updates.is_empty() => Label::c(|w| {
+        w.set_label(&tr("Everything is up to date."));
+        w.set_margin_all(10);
+      }),
+      Some(Ok(updates)) => ListView::list(
+        updates
+          .iter()
+          .map(|u| {
+            let key = format!("{} {}", u.installed.installation, u.installed.format_ref());
+            (key, u.clone())
+          })
+          .collect(),
+        |_, _| vec![],
+        |u: &PendingUpdate| {
+          let r = &u.installed;
+          Box::c(|w| {
+            w.set_orientation(Orientation::Horizontal);
+            w.set_spacing(10);
+            w.set_margin_all(10);
+          })
+          .children(vec![
+            //
+            Label::c(|w| {
+              w.set_label(r.display_name());
+              w.set_tooltip_text(Some(&format!("{} ({})", r.format_ref(), r.installation)));
+            })
+            .classes(&["heading"]),
+            Label::c(|w| {
+              let current = r
+                .version
+                .as_deref()
+                .or(r.commit.as_deref().map(short_commit))
+                .unwrap_or_default();
+              let new = u
+                .new_version
+                .as_deref()
+                .or(u.commit.as_deref().map(short_commit))
+                .unwrap_or_default();
+              w.set_label(&format!("{} → {}", current, new));
+            })
+            .classes(&["dim-label"]),
+            Label::c(|w| {
+              w.set_label(&format_size(u.download_size));
+            })
+            .classes(&["dim-label"]),
+            Button::ce(|w, c| {
+              w.set_label(&tr("Update"));
+              let update = u.clone();
+              vec![w.connect_clicked(c.d(move |_| AppMessage::Update(vec![update.clone()])))]
+            }),
+          ])
+        },
+      ),
+    };
+    let explore: VNode<Self> = match &self.catalog {
+      None => Label::c(|w| {
+        w.set_label(&tr("Loading available apps…"));
+        w.set_margin_all(10);
+      }),
+      Some(Err(error)) => Label::c(|w| {
+        w.set_label(&fill(&tr("Error loading available apps: {}"), &[error]));
+        w.set_margin_all(10);
+      }),
+      Some(Ok(catalog)) => ListView::list(
+        {
+          // Ranked by relevance, or by name without a search.
+          let hits = self.search.search(&Query {
+            text: self.filter.clone(),
+            ..Default::default()
+          });
+          hits
+            .iter()
+            .filter_map(|hit| {
+              let a = catalog.components.get(hit.component?)?;
+              let key = format!("{:?} {} {}", a.installation, a.remote, a.id);
+              Some((key, (a.clone(), hit.installed.is_some())))
+            })
+            .collect::<Vec<(String, (CatalogComponent, bool))>>()
+        },
+        |_, _| vec![],
+        |(a, is_installed): &(CatalogComponent, bool)| {
+          let is_installed = *is_installed;
+          Box::c(|w| {
+            w.set_orientation(Orientation::Horizontal);
+            w.set_spacing(10);
+            w.set_margin_all(10);
+          })
+          .children(vec![
+            //
+            Label::c(|w| {
+              w.set_label(a.display_name());
+              w.set_tooltip_text(a.bundle.as_deref());
+            })
+            .classes(&["heading"]),
+            Label::c(|w| {
+              w.set_label(a.summary.as_deref().unwrap_or_default());
+            })
+            .classes(&["dim-label"]),
+            Label::c(|w| {
+              w.set_label(a.version().unwrap_or_default());
+            }),
+            Label::c(|w| {
+              w.set_label(&a.remote);
+            })
+            .classes(&["dim-label"]),
+            Button::ce(move |w, c| {
+              w.set_label(&if is_installed {
+                tr("Installed")
+              } else {
+                tr("Install")
+              });
+              let target = a.installation.clone().zip(a.bundle.clone());
+              w.set_sensitive(target.is_some() && !is_installed);
+              let remote = a.remote.clone();
+              match target {
+                Some((installation, ref_)) => {
+                  vec![w.connect_clicked(c.d(move |_| AppMessage::Install {
+                    installation: installation.clone(),
+                    remote: remote.clone(),
+                    ref_: ref_.clone(),
+                  }))]
+                }
+                None => vec![],
+              }
+            }),
+          ])
+        },
+      ),
+    };
+    let pending: Vec<PendingUpdate> = match &self.updates {
+      Some(Ok(updates)) => updates.clone(),
+      _ => vec![],
+    };
 
-    Application::cs().children(vec![
+    let mut window_children = vec![
       //
-      Window::c(|w| {
-        w.set_default_width(300);
-        w.set_default_height(100);
+      Box::c(|w| {
+        w.set_orientation(Orientation::Vertical);
       })
       .children(vec![
         //
+        HeaderBar::c(|w| {
+          w.set_title_widget(Some(&Label::new(Some(&tr("My Adwaita App")))));
+        })
+        .children(vec![
+          //
+          Box::c(|w| {
+            w.set_orientation(Orientation::Horizontal);
+          })
+          .classes(&["linked"])
+          .children(vec![
+            ToggleButton::ce(|w, c| {
+              w.set_label(&tr("Explore"));
+              w.set_active(self.page == Page::Explore);
+              vec![w.connect_toggled(c.d(|_| AppMessage::Page(Page::Explore)))]
+            }),
+            ToggleButton::ce(|w, c| {
+              w.set_label(&tr("Installed"));
+              w.set_active(self.page == Page::Installed);
+              vec![w.connect_toggled(c.d(|_| AppMessage::Page(Page::Installed)))]
+            }),
+            ToggleButton::ce(|w, c| {
+              w.set_label(&match &self.updates {
+                Some(Ok(updates)) if !updates.is_empty() => {
+                  fill(&tr("Updates ({})"), &[&format_number(updates.len() as i64)])
+                }
+                _ => tr("Updates"),
+              });
+              w.set_active(self.page == Page::Updates);
+              vec![w.connect_toggled(c.d(|_| AppMessage::Page(Page::Updates)))]
+            }),
+          ]),
+          Button::ce(|w, c| {
+            w.set_icon_name("document-open-symbolic");
+            w.set_tooltip_text(Some(&tr("Install from File…")));
+            let scope = c.scope();
+            vec![w.connect_clicked(move |w| choose_files(w, scope.clone()))]
+          })
+          .accessible(vec![VAccessible::Label(tr("Install from File…"))]),
+          MenuButton::c(|w| {
+            w.set_icon_name("open-menu-symbolic");
+            w.set_primary(true);
+          })
+          .accessible(vec![VAccessible::Label(tr("Main Menu"))])
+          .children(vec![Menu::menu(vec![
+            VMenuItem::section(vec![
+              VMenuItem::item(&tr("Increment"), "app.increment"),
+              VMenuItem::item(&tr("Decrement"), "app.decrement"),
+            ]),
+            VMenuItem::section(vec![VMenuItem::item(&tr("Reset…"), "app.reset")]),
+          ])]),
+        ]),
+        // Always there, so that showing it doesn't rebuild what follows.
         Box::c(|w| {
           w.set_orientation(Orientation::Vertical);
+          w.set_spacing(5);
+          w.set_margin_all(5);
+          w.set_visible(self.transaction.is_some());
         })
         .children(vec![
           //
-          HeaderBar::c(|w| {
-            w.set_title_widget(Some(&Label::new(Some("My Adwaita App"))));
+          Label::c(|w| {
+            let label = match &self.transaction {
+              Some(RunningTransaction {
+                current: Some(current),
+                ..
+              }) => {
+                let template = match current.kind {
+                  OperationKind::Install | OperationKind::InstallBundle => tr("Installing {}…"),
+                  OperationKind::Update => tr("Updating {}…"),
+                  OperationKind::Uninstall => tr("Uninstalling {}…"),
+                  OperationKind::RemoveData => tr("Deleting the data of {}…"),
+                };
+                fill(&template, &[&current.ref_])
+              }
+              Some(transaction) if transaction.download_size() > 0 => fill(
+                &tr("Preparing to download {}…"),
+                &[&format_size(transaction.download_size())],
+              ),
+              _ => tr("Preparing…"),
+            };
+            w.set_label(&label);
+            w.set_xalign(0.0);
           }),
+          Label::c(|w| {
+            let status = self.transaction.as_ref().and_then(|t| t.status.as_deref());
+            w.set_label(status.unwrap_or_default());
+            w.set_xalign(0.0);
+          })
+          .classes(&["dim-label"]),
+          ProgressBar::cs().animate(
+            "fraction",
+            self.transaction.as_ref().map_or(0.0, |t| t.fraction()),
+            VAnimation::timed(250),
+          ),
           Button::ce(|w, c| {
-            w.set_label("Add");
-            vec![w.connect_clicked(c.d(|_| AppMessage::Increment))]
+            w.set_label(&tr("Cancel"));
+            vec![w.connect_clicked(c.d(|_| AppMessage::CancelTransaction))]
           }),
+        ]),
+        Button::ce(|w, c| {
+          w.set_label(&tr("Add"));
+          vec![w.connect_clicked(c.d(|_| AppMessage::Increment))]
+        }),
+        Button::ce(|w, c| {
+          w.set_label(&tr("Remove"));
+          vec![w.connect_clicked(c.d(|_| AppMessage::Decrement))]
+        }),
+        Button::c(|w| {
+          w.set_label(&tr_ctx("action", "Reset"));
+          w.set_action_name(Some("app.reset"));
+        }),
+        Label::c(|w| {
+          w.set_label(&fill(
+            &tr("Count: {}"),
+            &[&format_number(self.count.into())],
+          ));
+        }),
+        Counter::cp(Counter {
+          name: tr("Counter"),
+          count: self.count,
+          on_changed: c.callback(AppMessage::Add),
+        }),
+        ProgressBar::c(|w| {
+          w.set_margin_all(5);
+        })
+        .animate(
+          "fraction",
+          f64::from(self.count.min(10)) / 10.0,
+          VAnimation::timed(250),
+        ),
+        ToggleButton::ce(|w, c| {
+          w.set_icon_name("view-sort-descending-symbolic");
+          w.set_tooltip_text(Some(&tr("Reverse Order")));
+          w.set_active(self.sort == SortOrder::Descending);
+          w.set_margin_all(5);
+          vec![w.connect_toggled(c.d(|w: &ToggleButton| {
+            AppMessage::Sort(if w.is_active() {
+              SortOrder::Descending
+            } else {
+              SortOrder::Ascending
+            })
+          }))]
+        })
+        .accessible(vec![VAccessible::Label(tr("Reverse Order"))]),
+        SearchEntry::controlled(
+          self.filter.clone(),
+          |w| {
+            w.set_placeholder_text(Some(&tr("Filter installed apps")));
+            w.set_margin_all(5);
+          },
+          AppMessage::Filter,
+        )
+        .accessible(vec![VAccessible::Label(tr("Filter installed apps"))]),
+        // Always there as well, shown on the updates page.
+        Box::c(|w| {
+          w.set_orientation(Orientation::Horizontal);
+          w.set_spacing(5);
+          w.set_margin_all(5);
+          w.set_visible(self.page == Page::Updates);
+        })
+        .children(vec![
+          //
+          Button::ce(move |w, c| {
+            w.set_label(&tr("Update All"));
+            w.set_sensitive(!pending.is_empty() && self.transaction.is_none());
+            let pending = pending.clone();
+            vec![w.connect_clicked(c.d(move |_| AppMessage::Update(pending.clone())))]
+          })
+          .classes(&["suggested-action"]),
           Button::ce(|w, c| {
-            w.set_label("Remove");
-            vec![w.connect_clicked(c.d(|_| AppMessage::Decrement))]
+            w.set_icon_name("view-refresh-symbolic");
+            w.set_tooltip_text(Some(&tr("Check for Updates")));
+            w.set_sensitive(self.updates.is_some());
+            vec![w.connect_clicked(c.d(|_| AppMessage::CheckUpdates))]
+          })
+          .accessible(vec![VAccessible::Label(tr("Check for Updates"))]),
+        ]),
+        //
+        ScrolledWindow::c(|w| {
+          w.set_vexpand(true);
+        })
+        .children(vec![match self.page {
+          Page::Explore => explore,
+          Page::Installed => refs,
+          Page::Updates => updates,
+        }]),
+      ]),
+    ];
+
+    // Dialogs go last, so that showing or hiding them doesn't rebuild the
+    // window content.
+    if self.confirm_reset {
+      window_children.push(AlertDialog::ce(|d, c| {
+        d.set_heading(Some(&tr("Reset the count?")));
+        d.set_body(&fill(
+          &ntr(
+            "The count is currently {} click.",
+            "The count is currently {} clicks.",
+            self.count.into(),
+          ),
+          &[&format_number(self.count.into())],
+        ));
+        d.set_responses(&[
+          ("cancel", tr("Cancel").as_str()),
+          ("reset", tr_ctx("action", "Reset").as_str()),
+        ]);
+        d.set_response_appearance("reset", ResponseAppearance::Destructive);
+        d.set_close_response("cancel");
+        vec![d.connect_response(
+          None,
+          c.dv(|_, response: &str| AppMessage::Reset(response == "reset")),
+        )]
+      }));
+    }
+    if let Some(error) = &self.transaction_error {
+      window_children.push(AlertDialog::ce(|d, c| {
+        d.set_heading(Some(&tr("Operation Failed")));
+        d.set_body(error);
+        d.set_responses(&[("close", tr("Close").as_str())]);
+        d.set_close_response("close");
+        vec![d.connect_response(None, c.dv(|_, _: &str| AppMessage::DismissTransactionError))]
+      }));
+    }
+
+    if let Some(ref_) = &self.confirm_uninstall {
+      window_children.push(AlertDialog::ce(|d, c| {
+        d.set_heading(Some(&fill(&tr("Uninstall {}?"), &[&ref_.display_name()])));
+        d.set_body(&tr(
+          "Its translations and debug info are removed too. Its data can be kept in case it's installed again.",
+        ));
+        d.set_responses(&[
+          ("cancel", tr("Cancel").as_str()),
+          ("uninstall-data", tr("Uninstall and Delete Data").as_str()),
+          ("uninstall", tr("Uninstall").as_str()),
+        ]);
+        d.set_response_appearance("uninstall-data", ResponseAppearance::Destructive);
+        d.set_response_appearance("uninstall", ResponseAppearance::Destructive);
+        d.set_close_response("cancel");
+        vec![d.connect_response(
+          None,
+          c.dv(|_, response: &str| AppMessage::Uninstall {
+            confirmed: response != "cancel",
+            remove_data: response == "uninstall-data",
+          }),
+        )]
+      }));
+    } else if !self.unused.is_empty() {
+      window_children.push(AlertDialog::ce(|d, c| {
+        d.set_heading(Some(&tr("Remove unused runtimes?")));
+        let names: Vec<&str> = self.unused.iter().map(FpRef::display_name).collect();
+        d.set_body(&fill(
+          &ntr(
+            "{} runtime or extension isn't used by any app anymore: {}",
+            "{} runtimes or extensions aren't used by any app anymore: {}",
+            self.unused.len() as u64,
+          ),
+          &[&format_number(self.unused.len() as i64), &names.join(", ")],
+        ));
+        d.set_responses(&[
+          ("keep", tr("Keep").as_str()),
+          ("remove", tr_ctx("action", "Remove").as_str()),
+        ]);
+        d.set_response_appearance("remove", ResponseAppearance::Destructive);
+        d.set_close_response("keep");
+        vec![d.connect_response(
+          None,
+          c.dv(|_, response: &str| AppMessage::RemoveUnused(response == "remove")),
+        )]
+      }));
+    } else if let Some((path, opened)) = self.opened.first() {
+      window_children.push(
+        AlertDialog::ce(|d, c| {
+          match opened {
+            Ok(file) => {
+              d.set_heading(Some(&fill(&tr("Install {}?"), &[&file.display_name()])));
+              let unknown = tr("Unknown");
+              let permissions = match &file.permissions {
+                Some(permissions) => match permissions.granted() {
+                  granted if granted.is_empty() => tr("None"),
+                  granted => granted.join("\n"),
+                },
+                None => unknown.clone(),
+              };
+              d.set_body(&fill(
+                &tr("Remote: {}\nRuntime: {}\nSize: {}\n\nPermissions:\n{}"),
+                &[
+                  &file
+                    .remote
+                    .as_deref()
+                    .or(file.url.as_deref())
+                    .unwrap_or(&unknown),
+                  &file.runtime.as_deref().unwrap_or(&unknown),
+                  &if file.installed_size > 0 {
+                    format_size(file.installed_size)
+                  } else {
+                    unknown.clone()
+                  },
+                  &permissions,
+                ],
+              ));
+              d.set_responses(&[
+                ("cancel", tr("Cancel").as_str()),
+                ("install-system", tr("Install for All Users").as_str()),
+                ("install", tr("Install for Me").as_str()),
+              ]);
+              d.set_response_appearance("install", ResponseAppearance::Suggested);
+              // Only one transaction runs at a time.
+              d.set_response_enabled("install", self.transaction.is_none());
+              d.set_response_enabled("install-system", self.transaction.is_none());
+            }
+            Err(error) => {
+              d.set_heading(Some(&tr("Unable to open the file")));
+              d.set_body(&format!("{}: {}", path.display(), error));
+              d.set_responses(&[("cancel", tr("Close").as_str())]);
+            }
+          }
+          d.set_close_response("cancel");
+          vec![d.connect_response(
+            None,
+            c.dv(|_, response: &str| {
+              AppMessage::InstallFile(match response {
+                "install" => Some(InstallationId::User),
+                "install-system" => Some(InstallationId::default_system()),
+                _ => None,
+              })
+            }),
+          )]
+        })
+        // Each file gets its own dialog, the previous one closed itself.
+        .key(path.display()),
+      );
+    } else if let Some(app) = &self.permissions_of {
+      let (content, buttons): (VNode<Self>, Vec<VNode<Self>>) = match &self.permissions {
+        None => (
+          Label::c(|w| {
+            w.set_label(&tr("Loading permissions…"));
+            w.set_margin_all(10);
           }),
+          vec![],
+        ),
+        Some(Err(error)) => (
           Label::c(|w| {
-            w.set_label(&format!("Count: {}", self.count));
+            w.set_label(error);
+            w.set_wrap(true);
+            w.set_margin_all(10);
           }),
+          vec![],
+        ),
+        Some(Ok(permissions)) => (
+          permission_rows(permissions),
+          vec![
+            Button::ce(|w, c| {
+              w.set_label(&tr_ctx("action", "Reset"));
+              w.set_tooltip_text(Some(&tr("Remove your changes to the permissions")));
+              w.set_sensitive(!permissions.user.is_empty());
+              vec![w.connect_clicked(c.d(|_| AppMessage::SavePermissions {
+                confirmed: true,
+                reset: true,
+              }))]
+            })
+            .classes(&["destructive-action"]),
+            Button::ce(|w, c| {
+              w.set_label(&tr("Save"));
+              vec![w.connect_clicked(c.d(|_| AppMessage::SavePermissions {
+                confirmed: true,
+                reset: false,
+              }))]
+            })
+            .classes(&["suggested-action"]),
+          ],
+        ),
+      };
+      window_children.push(
+        Dialog::ce(|d, c| {
+          d.set_title(&fill(&tr("Permissions of {}"), &[&app.display_name()]));
+          d.set_content_width(420);
+          d.set_content_height(560);
+          vec![d.connect_closed(c.d(|_| AppMessage::SavePermissions {
+            confirmed: false,
+            reset: false,
+          }))]
+        })
+        .children(vec![
           //
-          ScrolledWindow::c(|w| {
-            w.set_vexpand(true);
+          Box::c(|w| {
+            w.set_orientation(Orientation::Vertical);
           })
           .children(vec![
             //
+            HeaderBar::cs(),
+            ScrolledWindow::c(|w| {
+              w.set_vexpand(true);
+            })
+            .children(vec![content]),
             Box::c(|w| {
-              w.set_orientation(Orientation::Vertical);
-              w.set_spacing(5);
-              w.set_margin_all(5);
+              w.set_orientation(Orientation::Horizontal);
+              w.set_spacing(10);
+              w.set_margin_all(10);
+              w.set_halign(Align::End);
             })
-            .children(items),
+            .children(buttons),
           ]),
         ]),
-      ]),
+      );
+    }
+
+    Application::ce(|a, c| {
+      // Only taken into account before the application registers.
+      a.set_flags(a.flags() | ApplicationFlags::HANDLES_OPEN);
+      let scope = c.scope();
+      vec![a.connect_open(move |_, files, _| {
+        let paths = files
+          .iter()
+          .filter_map(|file| file.path())
+          .filter(|path| file::is_ref_file(path))
+          .collect();
+        scope.send_message(AppMessage::OpenFiles(paths));
+      })]
+    })
+    .children(vec![
+      //
+      Window::ce(|w, c| {
+        w.set_default_size(self.window_width, self.window_height);
+        let size = |w: &Window| AppMessage::WindowSize(w.default_width(), w.default_height());
+        vec![
+          w.connect_default_width_notify(c.d(size)),
+          w.connect_default_height_notify(c.d(size)),
+        ]
+      })
+      // Escape clears the filter, wherever the focus is.
+      .controller(VController::key(|key, _| {
+        (key == Key::Escape).then(|| AppMessage::Filter(String::new()))
+      }))
+      .children(window_children),
+      SimpleAction::action(
+        "increment",
+        |a| a.set_accels(&["<Control>plus"]),
+        || AppMessage::Increment,
+      ),
+      SimpleAction::action(
+        "decrement",
+        |a| a.set_accels(&["<Control>minus"]),
+        || AppMessage::Decrement,
+      ),
+      SimpleAction::action(
+        "reset",
+        |a| {
+          a.set_enabled(self.count > 0 && !self.confirm_reset);
+          a.set_accels(&["<Control>r"]);
+        },
+        || AppMessage::AskReset,
+      ),
     ])
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use adw::glib::object::ObjectExt;
+  use futures::channel::mpsc::unbounded;
+
+  use super::*;
+  use crate::reactive::{
+    scope::Scope, testing::with_gtk, vnode::vaccessible::unnamed_widgets, vstate::VState,
+  };
+  use crate::services::flatpak::{InstallationId, RefKind};
+
+  fn installed() -> FpRef {
+    FpRef {
+      id: "org.gnome.Maps".to_string(),
+      installation: InstallationId::User,
+      kind: RefKind::App,
+      arch: "x86_64".to_string(),
+      branch: "stable".to_string(),
+      origin: Some("flathub".to_string()),
+      commit: Some("0123abcd".to_string()),
+      latest_commit: Some("4567cdef".to_string()),
+      installed_size: 12_000_000,
+      deploy_dir: None,
+      end_of_life: None,
+      end_of_life_rebase: None,
+      name: Some("Maps".to_string()),
+      summary: Some("Find places around the world".to_string()),
+      version: Some("47.0".to_string()),
+      license: None,
+      content_rating: None,
+    }
+  }
+
+  fn catalog() -> Rc<Catalog> {
+    Rc::new(Catalog {
+      components: vec![CatalogComponent {
+        id: "org.gnome.Weather".to_string(),
+        kind: "desktop-application".to_string(),
+        remote: "flathub".to_string(),
+        bundle: Some("app/org.gnome.Weather/x86_64/stable".to_string()),
+        name: Some("Weather".to_string()),
+        summary: Some("Show weather conditions and forecast".to_string()),
+        ..Default::default()
+      }],
+    })
+  }
+
+  /// The widgets of the window of `app` without an accessible name.
+  fn unnamed(app: &App) -> Vec<String> {
+    let (sender, _receiver) = unbounded();
+    let scope = Scope::new("App", sender);
+    let view = app.view(&ViewContext::new(scope.clone()));
+    let VNode::Object(application) = &view else {
+      unreachable!("the root of the view is the application");
+    };
+    let window = VState::build(&application.children[0], None, &scope);
+    let unnamed = unnamed_widgets(window.widget().unwrap())
+      .iter()
+      .map(|widget| format!("{} {:?}", widget.type_(), widget.css_classes()))
+      .collect();
+    window.unmount();
+    unnamed
+  }
+
+  #[test]
+  fn main_views_name_their_widgets() {
+    with_gtk(|| {
+      let app = App {
+        refs: Some(Ok(vec![installed()])),
+        ..Default::default()
+      };
+      assert_eq!(unnamed(&app), Vec::<String>::new());
+    });
+  }
+
+  #[test]
+  fn updates_page_names_its_widgets() {
+    with_gtk(|| {
+      let app = App {
+        refs: Some(Ok(vec![installed()])),
+        page: Page::Updates,
+        updates: Some(Ok(vec![PendingUpdate {
+          installed: installed(),
+          commit: Some("89abef01".to_string()),
+          new_version: Some("48.0".to_string()),
+          download_size: 2_000_000,
+          installed_size: 12_500_000,
+        }])),
+        ..Default::default()
+      };
+      assert_eq!(unnamed(&app), Vec::<String>::new());
+    });
+  }
+
+  #[test]
+  fn explore_page_names_its_widgets() {
+    with_gtk(|| {
+      let app = App {
+        refs: Some(Ok(vec![installed()])),
+        page: Page::Explore,
+        catalog: Some(Ok(catalog())),
+        ..Default::default()
+      };
+      assert_eq!(unnamed(&app), Vec::<String>::new());
+    });
+  }
+
+  #[test]
+  fn search_results_name_their_widgets() {
+    with_gtk(|| {
+      for page in [Page::Explore, Page::Installed] {
+        let mut app = App {
+          refs: Some(Ok(vec![installed()])),
+          page,
+          catalog: Some(Ok(catalog())),
+          filter: "weather maps".to_string(),
+          ..Default::default()
+        };
+        app.reindex();
+        assert_eq!(unnamed(&app), Vec::<String>::new());
+      }
+    });
+  }
+}
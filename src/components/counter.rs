@@ -1,16 +1,20 @@
 use crate::reactive::callback::Callback;
 use crate::reactive::component::{UpdateAction, ViewContext};
 use crate::reactive::helpers::widget_ext::ReactiveWidgetExt;
+use crate::reactive::i18n::{fill, format_number, tr};
+use crate::reactive::replay;
+use crate::reactive::style::Stylesheet;
 use crate::reactive::vnode::vobject::VObjectBuilder;
 use crate::reactive::{component::Component, vnode::VNode};
 use gtk4::prelude::{BoxExt, ButtonExt, OrientableExt};
 use gtk4::{Box, Button, Label, Orientation};
+use serde::{Deserialize, Serialize};
 
 //
 // State.
 //
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Counter {
   pub name: String,
   pub count: u8,
@@ -30,7 +34,7 @@ pub struct Counter {
 //   }
 // }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum CounterMessage {
   Increment,
   Decrement,
@@ -52,18 +56,21 @@ impl Component for Counter {
     // }
   }
 
-  fn change(&mut self, props: Self::Props) -> UpdateAction {
+  fn change(&mut self, props: Self::Props) -> UpdateAction<Self> {
     // if self.name == props.name {
     //   UpdateAction::None
     // } else {
     //   self.name = props.name;
     //   UpdateAction::Render
     // }
+    if *self == props {
+      return UpdateAction::None;
+    }
     *self = props;
     UpdateAction::Render
   }
 
-  fn update(&mut self, message: Self::Message) -> UpdateAction {
+  fn update(&mut self, message: Self::Message) -> UpdateAction<Self> {
     match message {
       CounterMessage::Increment => {
         // self.count = self.count.saturating_add(1);
@@ -79,6 +86,18 @@ impl Component for Counter {
     UpdateAction::None
   }
 
+  fn encode_message(message: &CounterMessage) -> Option<serde_json::Value> {
+    replay::encode(message)
+  }
+
+  fn decode_message(message: serde_json::Value) -> Option<CounterMessage> {
+    replay::decode(message)
+  }
+
+  fn stylesheet() -> Option<Stylesheet> {
+    Some(Stylesheet::scoped("& > label { font-weight: bold; }"))
+  }
+
   fn view(&self, _: &ViewContext<Self>) -> VNode<Self> {
     Box::c(|w| {
       w.set_orientation(Orientation::Vertical);
@@ -88,15 +107,19 @@ impl Component for Counter {
     .children(vec![
       //
       Button::ce(|w, c| {
-        w.set_label("Increment");
+        w.set_label(&tr("Increment"));
         vec![w.connect_clicked(c.d(|_| CounterMessage::Increment))]
       }),
       Button::ce(|w, c| {
-        w.set_label("Decrement");
+        w.set_label(&tr("Decrement"));
         vec![w.connect_clicked(c.d(|_| CounterMessage::Decrement))]
       }),
       Label::c(|w| {
-        w.set_label(&format!("{}: {}", self.name, self.count));
+        w.set_label(&fill(
+          // Translators: the name of a counter, then its value.
+          &tr("{} is at {}"),
+          &[&self.name, &format_number(self.count.into())],
+        ));
         w.set_margin_all(5);
       }),
     ])
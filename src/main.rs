@@ -1,7 +1,36 @@
 use adw::glib;
-use rouge_software::{components::app::App, reactive};
-use std::process;
+use rouge_software::{
+  components::app::App,
+  reactive::{self, replay},
+};
+use std::{env, process};
 
 fn main() -> glib::ExitCode {
+  // Record the session to a file, to reproduce a bug later on.
+  if let Ok(path) = env::var("ROUGE_RECORD") {
+    return replay::run_recording::<App>(&path).unwrap_or_else(|error| {
+      eprintln!("Unable to record to {}: {}", path, error);
+      process::exit(1);
+    });
+  }
+
+  // Replay a recorded session.
+  if let Ok(path) = env::var("ROUGE_REPLAY") {
+    let mode = env::var("ROUGE_REPLAY_MODE")
+      .ok()
+      .map(|mode| mode.parse())
+      .transpose()
+      .unwrap_or_else(|error: String| {
+        eprintln!("{}", error);
+        process::exit(1);
+      })
+      .unwrap_or_default();
+
+    return replay::run_replay::<App>(&path, mode).unwrap_or_else(|error| {
+      eprintln!("Unable to replay {}: {}", path, error);
+      process::exit(1);
+    });
+  }
+
   reactive::run::<App>()
 }
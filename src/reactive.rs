@@ -1,9 +1,17 @@
 pub mod callback;
 pub mod component;
+pub mod error_boundary;
 pub mod helpers;
+pub mod i18n;
+pub mod persist;
+pub mod replay;
 pub mod scope;
+pub mod style;
+#[cfg(test)]
+pub(crate) mod testing;
 pub mod vnode;
 pub mod vstate;
+pub mod worker;
 
 use adw::{
   gio::{
@@ -18,9 +26,11 @@ use adw::{
 };
 use colored::Colorize;
 use component::{Component, ComponentMessage, PartialComponentTask};
+use futures::channel::mpsc::UnboundedSender;
 use log::debug;
 use scope::Scope;
 use std::env;
+use std::rc::Rc;
 
 enum AndThen {
   Panic,
@@ -45,7 +55,15 @@ fn once<A, F: FnOnce(A)>(and_then: AndThen, f: F) -> impl Fn(A) {
 }
 
 pub fn start<C: 'static + Component>() -> (Application, Scope<C>) {
+  let (app, scope, _) = start_with_channel::<C>();
+  (app, scope)
+}
+
+/// Same as [`start`], but also returns the system channel of the root task.
+pub(crate) fn start_with_channel<C: 'static + Component>(
+) -> (Application, Scope<C>, UnboundedSender<ComponentMessage<C>>) {
   gtk4::init().expect("GTK failed to initialize.");
+  i18n::init(i18n::GETTEXT_PACKAGE, i18n::LOCALEDIR);
   let partial_task = PartialComponentTask::<C, C>::new(Default::default(), None, None);
 
   let app: Application = partial_task.object().downcast().unwrap_or_else(|_| {
@@ -60,6 +78,7 @@ pub fn start<C: 'static + Component>() -> (Application, Scope<C>) {
     .expect("Unable to register Application.");
 
   let scope = partial_task.scope();
+  let system_channel = partial_task.channel();
   let const_app = app.clone();
 
   let constructor = once(AndThen::DoNothing, move |_| {
@@ -68,15 +87,27 @@ pub fn start<C: 'static + Component>() -> (Application, Scope<C>) {
     channel.unbounded_send(ComponentMessage::Mounted).unwrap();
     const_app.connect_shutdown(move |_| {
       channel.unbounded_send(ComponentMessage::Unmounted).unwrap();
+      // The main loop is over: the delayed writes would never happen.
+      persist::flush();
     });
   });
 
-  app.connect_activate(move |_| {
-    debug!("{}", "Application has activated.".bright_blue());
+  let constructor = Rc::new(constructor);
+  {
+    let constructor = constructor.clone();
+    app.connect_activate(move |_| {
+      debug!("{}", "Application has activated.".bright_blue());
+      constructor(());
+    });
+  }
+  // Applications with the HANDLES_OPEN flag are opened instead of activated
+  // when launched with files, which they get through their own handler.
+  app.connect_open(move |_, _, _| {
+    debug!("{}", "Application has opened files.".bright_blue());
     constructor(());
   });
 
-  (app, scope)
+  (app, scope, system_channel)
 }
 
 pub fn run<C: 'static + Component>() -> ExitCode {
@@ -1,6 +1,12 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::fmt::{Debug, Error, Formatter};
+use std::panic::Location;
 use std::rc::Rc;
 
+use adw::glib::MainContext;
+
 pub struct Callback<A>(pub Option<Rc<dyn Fn(A)>>);
 
 impl<A> Callback<A> {
@@ -15,6 +21,67 @@ impl<A> Callback<A> {
   }
 }
 
+impl<A: 'static> Callback<A> {
+  /// A callback spawning the future returned by `func` on the main context,
+  /// e.g. to send the result of an async call to another callback.
+  pub fn from_async<Fut, F>(func: F) -> Self
+  where
+    Fut: 'static + Future<Output = ()>,
+    F: 'static + Fn(A) -> Fut,
+  {
+    Callback::from(move |value| {
+      MainContext::ref_thread_default().spawn_local(func(value));
+    })
+  }
+
+  /// A callback taking `B` values, converted to `A` by `func` before they
+  /// are sent to this one. An empty callback stays empty.
+  pub fn reform<B, F: 'static + Fn(B) -> A>(&self, func: F) -> Callback<B> {
+    match &self.0 {
+      Some(callback) => {
+        let callback = callback.clone();
+        Callback::from(move |value| callback(func(value)))
+      }
+      None => Callback(None),
+    }
+  }
+
+  /// A callback sending the values transformed by `func`.
+  pub fn map<F: 'static + Fn(A) -> A>(&self, func: F) -> Callback<A> {
+    self.reform(func)
+  }
+
+  /// A callback sending only the values matching `predicate`.
+  pub fn filter<F: 'static + Fn(&A) -> bool>(&self, predicate: F) -> Callback<A> {
+    match &self.0 {
+      Some(callback) => {
+        let callback = callback.clone();
+        Callback::from(move |value| {
+          if predicate(&value) {
+            callback(value)
+          }
+        })
+      }
+      None => Callback(None),
+    }
+  }
+
+  /// A callback taking `B` values, sending those `func` turns into `A`.
+  pub fn filter_reform<B, F: 'static + Fn(B) -> Option<A>>(&self, func: F) -> Callback<B> {
+    match &self.0 {
+      Some(callback) => {
+        let callback = callback.clone();
+        Callback::from(move |value| {
+          if let Some(value) = func(value) {
+            callback(value)
+          }
+        })
+      }
+      None => Callback(None),
+    }
+  }
+}
+
 impl<A> Default for Callback<A> {
   fn default() -> Self {
     Callback(None)
@@ -27,6 +94,18 @@ impl<A> Clone for Callback<A> {
   }
 }
 
+/// Callbacks are equal when they're both empty or are the same function,
+/// i.e. clones of each other.
+impl<A> PartialEq for Callback<A> {
+  fn eq(&self, other: &Self) -> bool {
+    match (&self.0, &other.0) {
+      (Some(a), Some(b)) => std::ptr::addr_eq(Rc::as_ptr(a), Rc::as_ptr(b)),
+      (None, None) => true,
+      _ => false,
+    }
+  }
+}
+
 impl<A> Debug for Callback<A> {
   fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
     write!(f, "Callback()")
@@ -38,3 +117,164 @@ impl<A, F: Fn(A) + 'static> From<F> for Callback<A> {
     Callback(Some(Rc::new(func)))
   }
 }
+
+/// Where a stable callback was created: its call site, an optional key for
+/// call sites run several times per render, and the type of its argument.
+type StableKey = (&'static Location<'static>, Option<String>, TypeId);
+
+struct StableEntry {
+  /// The callback, and the slot holding the function it calls.
+  callback: Box<dyn Any>,
+  generation: u64,
+}
+
+/// Callbacks of a component which keep their identity across renders, see
+/// `ViewContext::callback`.
+#[derive(Default)]
+pub(crate) struct StableCallbacks {
+  entries: RefCell<HashMap<StableKey, StableEntry>>,
+  generation: Cell<u64>,
+}
+
+impl StableCallbacks {
+  /// The callback created at `location`, now calling `func`.
+  pub(crate) fn get<A: 'static>(
+    &self,
+    location: &'static Location<'static>,
+    key: Option<String>,
+    func: Rc<dyn Fn(A)>,
+  ) -> Callback<A> {
+    type Current<A> = Rc<RefCell<Rc<dyn Fn(A)>>>;
+    type Slot<A> = (Callback<A>, Current<A>);
+
+    let generation = self.generation.get();
+    let mut entries = self.entries.borrow_mut();
+    let entry = entries
+      .entry((location, key, TypeId::of::<A>()))
+      .or_insert_with(|| {
+        let slot: Current<A> = Rc::new(RefCell::new(Rc::new(|_| {})));
+        let current = slot.clone();
+        let callback = Callback::from(move |value| {
+          // Don't hold the borrow while calling, the function may render.
+          let func = current.borrow().clone();
+          func(value)
+        });
+        StableEntry {
+          callback: Box::new((callback, slot) as Slot<A>),
+          generation,
+        }
+      });
+    entry.generation = generation;
+    let (callback, slot) = entry
+      .callback
+      .downcast_ref::<Slot<A>>()
+      .expect("Stable callbacks are keyed by their type.");
+    *slot.borrow_mut() = func;
+    callback.clone()
+  }
+
+  /// Forget the callbacks which weren't created by the last render.
+  pub(crate) fn prune(&self) {
+    let generation = self.generation.get();
+    self
+      .entries
+      .borrow_mut()
+      .retain(|_, entry| entry.generation == generation);
+    self.generation.set(generation + 1);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// A callback recording the values it's sent in `sent`.
+  fn recorder<A: 'static>(sent: &Rc<RefCell<Vec<A>>>) -> Callback<A> {
+    let sent = sent.clone();
+    Callback::from(move |value| sent.borrow_mut().push(value))
+  }
+
+  #[test]
+  fn clones_are_equal() {
+    let sent = Rc::new(RefCell::new(vec![]));
+    let callback = recorder::<u8>(&sent);
+    assert_eq!(callback, callback.clone());
+    assert_ne!(callback, recorder(&sent));
+    assert_ne!(callback, Callback::default());
+    assert_eq!(Callback::<u8>::default(), Callback::default());
+  }
+
+  #[test]
+  fn combinators_transform_and_filter_values() {
+    let sent = Rc::new(RefCell::new(vec![]));
+    let callback = recorder::<u8>(&sent);
+    callback.reform(|text: &str| text.len() as u8).send("four");
+    callback.map(|value| value * 2).send(3);
+    callback.filter(|value| value % 2 == 0).send(7);
+    callback.filter(|value| value % 2 == 0).send(8);
+    callback
+      .filter_reform(|text: &str| text.parse().ok())
+      .send("x");
+    callback
+      .filter_reform(|text: &str| text.parse().ok())
+      .send("9");
+    assert_eq!(*sent.borrow(), vec![4, 6, 8, 9]);
+  }
+
+  #[test]
+  fn combinators_keep_empty_callbacks_empty() {
+    let callback = Callback::<u8>::default();
+    assert!(callback.reform(|value: u16| value as u8).is_empty());
+    assert!(callback.filter(|_| true).is_empty());
+    assert!(callback
+      .filter_reform(|value: u16| Some(value as u8))
+      .is_empty());
+  }
+
+  #[test]
+  fn stable_callbacks_keep_their_identity_and_call_the_last_function() {
+    let callbacks = StableCallbacks::default();
+    let location = Location::caller();
+    let sent = Rc::new(RefCell::new(vec![]));
+
+    let render = |offset: u8| {
+      let sent = sent.clone();
+      let func: Rc<dyn Fn(u8)> = Rc::new(move |value| sent.borrow_mut().push(value + offset));
+      let callback = callbacks.get(location, None, func);
+      callbacks.prune();
+      callback
+    };
+    let first = render(0);
+    let second = render(10);
+    assert_eq!(first, second);
+    first.send(1);
+    assert_eq!(*sent.borrow(), vec![11]);
+  }
+
+  #[test]
+  fn stable_callbacks_are_told_apart_by_key_and_type() {
+    let callbacks = StableCallbacks::default();
+    let location = Location::caller();
+    let a = callbacks.get::<u8>(location, Some("a".into()), Rc::new(|_| {}));
+    let b = callbacks.get::<u8>(location, Some("b".into()), Rc::new(|_| {}));
+    assert_ne!(a, b);
+    let text = callbacks.get::<&str>(location, Some("a".into()), Rc::new(|_| {}));
+    assert!(!text.is_empty());
+    assert_eq!(
+      a,
+      callbacks.get::<u8>(location, Some("a".into()), Rc::new(|_| {}))
+    );
+  }
+
+  #[test]
+  fn forgets_callbacks_left_out_of_the_last_render() {
+    let callbacks = StableCallbacks::default();
+    let location = Location::caller();
+    let first = callbacks.get::<u8>(location, None, Rc::new(|_| {}));
+    callbacks.prune();
+    // A render without the callback.
+    callbacks.prune();
+    let second = callbacks.get::<u8>(location, None, Rc::new(|_| {}));
+    assert_ne!(first, second);
+  }
+}
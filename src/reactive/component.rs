@@ -1,36 +1,51 @@
 use std::fmt::{Debug, Error, Formatter};
+use std::panic::{self, AssertUnwindSafe, Location};
 use std::pin::Pin;
-use std::sync::RwLock;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
 use std::task::{Context, Poll};
 
 use adw::glib::clone::Downgrade;
 use adw::glib::object::IsA;
-use adw::glib::{Object, WeakRef};
+use adw::glib::{MainContext, Object, WeakRef};
 use colored::Colorize;
 use futures::channel::mpsc::{unbounded, UnboundedSender};
 use futures::stream::{select, Stream};
 use futures::StreamExt;
-use log::{debug, trace};
+use log::{debug, error, trace};
 
 use crate::reactive::scope::Scope;
 use crate::reactive::vnode::VNode;
 
-use super::callback::Callback;
+use super::callback::{Callback, StableCallbacks};
+use super::error_boundary::ComponentError;
+use super::i18n;
+use super::persist::{Persistence, Saver};
+use super::replay;
 use super::scope::AnyScope;
+use super::style::{style_root, Stylesheet};
 use super::vstate::VState;
+use super::worker::{deliver, Job, RunningJob};
 
-pub enum UpdateAction {
+pub enum UpdateAction<C: Component> {
   None,
   Render,
+  /// Run a job in the background, and deliver the messages it produces.
+  Defer(Job<C>),
 }
 
 pub struct ViewContext<C: Component> {
   scope: Scope<C>,
+  callbacks: Rc<StableCallbacks>,
 }
 
 impl<C: Component> ViewContext<C> {
   pub fn new(scope: Scope<C>) -> ViewContext<C> {
-    ViewContext { scope }
+    ViewContext::with_callbacks(scope, Default::default())
+  }
+
+  pub(crate) fn with_callbacks(scope: Scope<C>, callbacks: Rc<StableCallbacks>) -> ViewContext<C> {
+    ViewContext { scope, callbacks }
   }
 }
 
@@ -42,6 +57,60 @@ impl<C: 'static + Component> ViewContext<C> {
       scope_clone.send_message(message);
     })
   }
+
+  /// Same as [`ViewContext::d`], for a message built by a future, e.g. the
+  /// result of an async call. The future runs on the main context.
+  pub fn da<R, Fut, MB>(&self, message_builder: MB) -> Callback<R>
+  where
+    R: 'static,
+    Fut: 'static + Future<Output = C::Message>,
+    MB: 'static + Fn(R) -> Fut,
+  {
+    let scope = self.scope.clone();
+    Callback::from_async(move |o| {
+      let message = message_builder(o);
+      let scope = scope.clone();
+      async move { scope.send_message(message.await) }
+    })
+  }
+
+  /// Same as [`ViewContext::d`], but the callback is the same from a render
+  /// to the next, so that children comparing their props don't see it
+  /// change. It always sends the message built by the last render.
+  ///
+  /// Callbacks are told apart by their call site: use
+  /// [`ViewContext::keyed_callback`] in loops.
+  #[track_caller]
+  pub fn callback<R: 'static, MB: 'static + Fn(R) -> C::Message>(
+    &self,
+    message_builder: MB,
+  ) -> Callback<R> {
+    self.stable_callback(None, message_builder)
+  }
+
+  /// Same as [`ViewContext::callback`], told apart by `key` as well.
+  #[track_caller]
+  pub fn keyed_callback<R: 'static, MB: 'static + Fn(R) -> C::Message>(
+    &self,
+    key: impl ToString,
+    message_builder: MB,
+  ) -> Callback<R> {
+    self.stable_callback(Some(key.to_string()), message_builder)
+  }
+
+  #[track_caller]
+  fn stable_callback<R: 'static, MB: 'static + Fn(R) -> C::Message>(
+    &self,
+    key: Option<String>,
+    message_builder: MB,
+  ) -> Callback<R> {
+    let scope = self.scope.clone();
+    self.callbacks.get(
+      Location::caller(),
+      key,
+      Rc::new(move |o| scope.send_message(message_builder(o))),
+    )
+  }
 }
 
 pub trait Component: Default + Unpin + Clone {
@@ -52,17 +121,52 @@ pub trait Component: Default + Unpin + Clone {
     Default::default()
   }
 
-  fn update(&mut self, _message: Self::Message) -> UpdateAction {
+  fn update(&mut self, _message: Self::Message) -> UpdateAction<Self> {
     UpdateAction::None
   }
 
-  fn change(&mut self, _props: Self::Props) -> UpdateAction {
+  fn change(&mut self, _props: Self::Props) -> UpdateAction<Self> {
     unimplemented!("add a Component::change() implementation");
   }
 
-  fn mounted(&self) {}
+  fn mounted(&mut self) -> UpdateAction<Self> {
+    UpdateAction::None
+  }
+
   fn unmounted(&self) {}
 
+  /// Make this component an error boundary: the panics of the components
+  /// below it are delivered as the returned message rather than taking the
+  /// application down. Read once, when the component is created. See
+  /// [`ErrorBoundary`](super::error_boundary::ErrorBoundary).
+  fn catch_panics() -> Option<fn(ComponentError) -> Self::Message> {
+    None
+  }
+
+  /// CSS of the component, loaded for the display along with its first
+  /// instance. See [`Stylesheet`].
+  fn stylesheet() -> Option<Stylesheet> {
+    None
+  }
+
+  /// Parts of the state saved when they change and when the component
+  /// unmounts. They are restored by calling
+  /// [`restored`](super::persist::restored) from `create`.
+  fn persistence() -> Option<Persistence<Self>> {
+    None
+  }
+
+  /// Encode a message in a recording, see [`replay`]. The messages of
+  /// components which don't are left out of recordings.
+  fn encode_message(_message: &Self::Message) -> Option<serde_json::Value> {
+    None
+  }
+
+  /// Decode a message encoded by [`Component::encode_message`].
+  fn decode_message(_message: serde_json::Value) -> Option<Self::Message> {
+    None
+  }
+
   fn view(&self, context: &ViewContext<Self>) -> VNode<Self>;
 }
 
@@ -71,6 +175,11 @@ pub enum ComponentMessage<C: Component> {
   Props(C::Props),
   Mounted,
   Unmounted,
+  /// Go back to the state the component was created with, and rebuild its
+  /// children from scratch. Used by the replayer to travel back in time.
+  Reset,
+  /// Render again without changing the state, e.g. after the locale changed.
+  Render,
 }
 
 impl<C: Component> Debug for ComponentMessage<C> {
@@ -88,6 +197,8 @@ impl<C: Component> Debug for ComponentMessage<C> {
       ComponentMessage::Props(_) => write!(f, "{}", "ComponentMessage::Props(...)".green()),
       ComponentMessage::Mounted => write!(f, "{}", "ComponentMessage::Mounted".green()),
       ComponentMessage::Unmounted => write!(f, "{}", "ComponentMessage::Unmounted".green()),
+      ComponentMessage::Reset => write!(f, "{}", "ComponentMessage::Reset".green()),
+      ComponentMessage::Render => write!(f, "{}", "ComponentMessage::Render".green()),
     }
   }
 }
@@ -99,9 +210,14 @@ where
 {
   scope: Scope<C>,
   parent_scope: Option<Scope<P>>,
+  /// The state right after its creation, to go back to on reset.
+  initial: C,
   state: C,
   ui_state: Option<VState<C>>,
   channel: Pin<Box<dyn Stream<Item = ComponentMessage<C>>>>,
+  jobs: Vec<RunningJob>,
+  saver: Option<Saver<C>>,
+  callbacks: Rc<StableCallbacks>,
 }
 
 impl<C, P> ComponentTask<C, P>
@@ -131,9 +247,9 @@ where
           ComponentMessage::Update(msg) => {
             let result = self.state.update(msg);
             match result {
-              // UpdateAction::Defer(job) => {
-              //   self.run_job(job);
-              // }
+              UpdateAction::Defer(job) => {
+                self.run_job(job);
+              }
               UpdateAction::Render => {
                 render = true;
               }
@@ -141,9 +257,9 @@ where
             }
           }
           ComponentMessage::Props(props) => match self.state.change(props) {
-            // UpdateAction::Defer(job) => {
-            //   self.run_job(job);
-            // }
+            UpdateAction::Defer(job) => {
+              self.run_job(job);
+            }
             UpdateAction::Render => {
               render = true;
             }
@@ -155,12 +271,27 @@ where
               "Component mounted:".bright_blue(),
               self.scope.name().magenta().bold()
             );
-            self.state.mounted();
+            match self.state.mounted() {
+              UpdateAction::Defer(job) => {
+                self.run_job(job);
+              }
+              UpdateAction::Render => {
+                render = true;
+              }
+              UpdateAction::None => {}
+            }
           }
           ComponentMessage::Unmounted => {
+            for job in self.jobs.drain(..) {
+              job.cancel();
+            }
             if let Some(state) = self.ui_state.take() {
               state.unmount();
             }
+            self.save();
+            if let Some(saver) = &self.saver {
+              saver.flush();
+            }
             self.state.unmounted();
             debug!(
               "{} {}",
@@ -169,20 +300,44 @@ where
             );
             return Poll::Ready(());
           }
+          ComponentMessage::Reset => {
+            debug!(
+              "{} {}",
+              "Component reset:".bright_yellow(),
+              self.scope.name().magenta().bold()
+            );
+            for job in self.jobs.drain(..) {
+              job.cancel();
+            }
+            self.state = self.initial.clone();
+            self.scope.reset_children();
+            if let Some(ref mut ui_state) = self.ui_state {
+              let context = ViewContext::with_callbacks(self.scope.clone(), self.callbacks.clone());
+              let view = self.state.view(&context);
+              self.callbacks.prune();
+              ui_state.rebuild_children(&view, &self.scope);
+            }
+            render = true;
+          }
+          ComponentMessage::Render => {
+            render = true;
+          }
         },
         Poll::Pending if render => {
+          self.save();
           if let Some(ref mut ui_state) = self.ui_state {
             // we patch
-            let context = ViewContext::new(self.scope.clone());
+            let context = ViewContext::with_callbacks(self.scope.clone(), self.callbacks.clone());
             let new_view = self.state.view(&context);
-            self.scope.mute();
+            self.callbacks.prune();
+            let _muted = self.scope.muted();
             if !ui_state.patch(&new_view, None, &self.scope) {
               unimplemented!(
                 "{}: don't know how to propagate failed patch",
                 self.scope.name()
               );
             }
-            self.scope.unmute();
+            style_root::<C>(ui_state.object());
             return Poll::Pending;
           } else {
             debug!(
@@ -201,11 +356,55 @@ where
           );
           return Poll::Ready(());
         }
-        Poll::Pending => return Poll::Pending,
+        Poll::Pending => {
+          // The state may change without a render, e.g. the window size.
+          self.save();
+          return Poll::Pending;
+        }
       }
     }
   }
 
+  /// Save the persisted parts of the state, if they changed.
+  fn save(&self) {
+    if let Some(saver) = &self.saver {
+      saver.save(&self.state);
+    }
+  }
+
+  /// Tear down the subtree of a component which panicked, and report the
+  /// panic to the nearest error boundary. Without one, the panic resumes.
+  fn fail(&mut self, payload: Box<dyn std::any::Any + Send>) {
+    let error = ComponentError::from_panic(self.scope.name(), payload.as_ref());
+    error!("{}", error);
+
+    for job in self.jobs.drain(..) {
+      job.cancel();
+    }
+    if let Some(state) = self.ui_state.take() {
+      // The state may be half patched; unmount what can be unmounted.
+      let _ = panic::catch_unwind(AssertUnwindSafe(|| state.unmount()));
+    }
+
+    match self.scope.boundary() {
+      Some(boundary) => boundary(error),
+      None => panic::resume_unwind(payload),
+    }
+  }
+
+  /// Spawn a job on the main context, delivering its messages to the scope
+  /// until the component unmounts.
+  fn run_job(&mut self, job: Job<C>) {
+    self.jobs.retain(|job| !job.is_finished());
+
+    let (messages, cancellable) = job.into_parts();
+    let scope = self.scope.clone();
+    let handle = MainContext::ref_thread_default().spawn_local(deliver(messages, move |message| {
+      scope.send_message(message);
+    }));
+    self.jobs.push(RunningJob::new(cancellable, handle));
+  }
+
   pub fn object(&self) -> Option<Object> {
     self.ui_state.as_ref().map(|state| state.object().clone())
   }
@@ -249,19 +448,35 @@ where
       Some(ref p) => p.inherit(type_name, user_send),
       None => Scope::new(type_name, user_send),
     };
+    if let Some(message_builder) = C::catch_panics() {
+      let boundary = scope.clone();
+      scope.catch_panics(Arc::new(move |error| {
+        boundary.post(message_builder(error));
+      }));
+    }
     let state = C::create(props);
+    let saver = C::persistence().map(|persistence| Saver::new(persistence, &state));
+    replay::register::<C>(scope.path(), sys_send.clone());
     let cloned_state = state.clone();
-    let context = ViewContext::new(scope.clone());
+    let callbacks = Rc::new(StableCallbacks::default());
+    let context = ViewContext::with_callbacks(scope.clone(), callbacks.clone());
     let initial_view = cloned_state.view(&context);
+    callbacks.prune();
     let ui_state = VState::build_root(&initial_view, parent, &scope);
+    style_root::<C>(ui_state.object());
+    i18n::subscribe(sys_send.clone());
 
     PartialComponentTask {
       task: ComponentTask {
         scope,
         parent_scope: parent_scope.cloned(),
+        initial: state.clone(),
         state,
         ui_state: Some(ui_state),
         channel,
+        jobs: Vec::new(),
+        saver,
+        callbacks,
       },
       // view: initial_view,
       sender: sys_send,
@@ -271,9 +486,10 @@ where
   /// Finalise the partially constructed `ComponentTask` by constructing its
   /// children.
   pub fn finalise(mut self) -> (UnboundedSender<ComponentMessage<C>>, ComponentTask<C, P>) {
-    let context: ViewContext<_> = ViewContext::new(self.scope());
+    let context = ViewContext::with_callbacks(self.scope(), self.task.callbacks.clone());
     if let Some(ref mut ui_state) = self.task.ui_state {
       let view = &self.task.state.view(&context);
+      self.task.callbacks.prune();
       ui_state.build_children(view, &self.task.scope);
     }
 
@@ -287,6 +503,12 @@ where
   pub fn scope(&self) -> Scope<C> {
     self.task.scope.clone()
   }
+
+  /// The system channel of the task, usable before it has been finalised.
+  /// Messages sent on it are queued until the task is spawned.
+  pub fn channel(&self) -> UnboundedSender<ComponentMessage<C>> {
+    self.sender.clone()
+  }
 }
 
 #[derive(Default)]
@@ -316,10 +538,16 @@ where
           .map(|state| state.object().downgrade()),
       };
     });
-    let polled = self.get_mut().process(ctx);
+    let task = self.get_mut();
+    // Messages sent while processing follow from the processed ones.
+    let _processing = replay::processing();
+    let polled = panic::catch_unwind(AssertUnwindSafe(|| task.process(ctx)));
     LOCAL_CONTEXT.with(|key| {
       *key.write().unwrap() = Default::default();
     });
-    polled
+    polled.unwrap_or_else(|payload| {
+      task.fail(payload);
+      Poll::Ready(())
+    })
   }
 }
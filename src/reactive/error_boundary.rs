@@ -0,0 +1,163 @@
+use std::{
+  any::Any,
+  fmt::{Display, Error, Formatter},
+  marker::PhantomData,
+};
+
+use adw::Bin;
+use gtk4::{
+  prelude::{BoxExt, ButtonExt, OrientableExt, WidgetExt},
+  Align, Box, Button, Label, Orientation,
+};
+
+use crate::reactive::{
+  component::{Component, UpdateAction, ViewContext},
+  helpers::widget_ext::ReactiveWidgetExt,
+  vnode::{vcomponent::VComponentBuilder, vobject::VObjectBuilder, VNode},
+};
+
+/// A panic caught in a component, see [`ErrorBoundary`].
+#[derive(Clone, Debug)]
+pub struct ComponentError {
+  /// Type name of the component which panicked.
+  pub component: &'static str,
+  pub message: String,
+}
+
+impl ComponentError {
+  pub(crate) fn from_panic(component: &'static str, payload: &(dyn Any + Send)) -> Self {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+      message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+      message.clone()
+    } else {
+      "unknown panic".to_string()
+    };
+    ComponentError { component, message }
+  }
+}
+
+impl Display for ComponentError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    write!(f, "{} panicked: {}", self.component, self.message)
+  }
+}
+
+#[derive(Clone, Debug)]
+pub enum ErrorBoundaryMessage {
+  Failed(ComponentError),
+  /// Remount the subtree after a failure.
+  Retry,
+}
+
+/// Hosts a `Child` component, taking its props, and catches the panics of
+/// `Child` and of the components below it.
+///
+/// A panic tears the failed subtree down and replaces it with a fallback
+/// showing the error, with a button sending [`ErrorBoundaryMessage::Retry`],
+/// which remounts `Child` from its last props. Panics of the first failed
+/// component only are shown.
+pub struct ErrorBoundary<Child: Component> {
+  child: PhantomData<Child>,
+  props: Child::Props,
+  error: Option<ComponentError>,
+}
+
+impl<Child: Component> Default for ErrorBoundary<Child> {
+  fn default() -> Self {
+    ErrorBoundary {
+      child: PhantomData,
+      props: Default::default(),
+      error: None,
+    }
+  }
+}
+
+impl<Child: Component> Clone for ErrorBoundary<Child> {
+  fn clone(&self) -> Self {
+    ErrorBoundary {
+      child: PhantomData,
+      props: self.props.clone(),
+      error: self.error.clone(),
+    }
+  }
+}
+
+impl<Child> Component for ErrorBoundary<Child>
+where
+  Child: 'static + Component,
+  Child::Props: Unpin,
+{
+  type Message = ErrorBoundaryMessage;
+  type Props = Child::Props;
+
+  fn create(props: Self::Props) -> Self {
+    ErrorBoundary {
+      props,
+      ..Default::default()
+    }
+  }
+
+  fn change(&mut self, props: Self::Props) -> UpdateAction<Self> {
+    self.props = props;
+    UpdateAction::Render
+  }
+
+  fn update(&mut self, message: Self::Message) -> UpdateAction<Self> {
+    match message {
+      ErrorBoundaryMessage::Failed(error) => {
+        // Components failing while the subtree is torn down would hide the
+        // original error.
+        if self.error.is_some() {
+          return UpdateAction::None;
+        }
+        self.error = Some(error);
+      }
+      ErrorBoundaryMessage::Retry => {
+        self.error = None;
+      }
+    }
+    UpdateAction::Render
+  }
+
+  fn catch_panics() -> Option<fn(ComponentError) -> Self::Message> {
+    Some(ErrorBoundaryMessage::Failed)
+  }
+
+  fn view(&self, _: &ViewContext<Self>) -> VNode<'_, Self> {
+    let child = match &self.error {
+      None => Child::cp(self.props.clone()),
+      Some(error) => fallback(error),
+    };
+    Bin::cs().children(vec![child])
+  }
+}
+
+fn fallback<'a, C>(error: &ComponentError) -> VNode<'a, C>
+where
+  C: 'static + Component<Message = ErrorBoundaryMessage>,
+{
+  let error = error.clone();
+  Box::c(|w| {
+    w.set_orientation(Orientation::Vertical);
+    w.set_spacing(5);
+    w.set_margin_all(5);
+    w.set_valign(Align::Center);
+  })
+  .children(vec![
+    Label::c(|w| {
+      w.set_label("Something went wrong");
+      w.add_css_class("title-4");
+    }),
+    Label::c(move |w| {
+      w.set_label(&error.to_string());
+      w.set_wrap(true);
+      w.set_selectable(true);
+    }),
+    Button::ce(|w, c| {
+      w.set_label("Try Again");
+      w.set_halign(Align::Center);
+      vec![w.connect_clicked(c.d(|_| ErrorBoundaryMessage::Retry))]
+    }),
+  ])
+}
@@ -1 +1,3 @@
+pub mod action_ext;
+pub mod dialog_ext;
 pub mod widget_ext;
@@ -0,0 +1,66 @@
+use adw::gio::{self, prelude::ActionExt, Action};
+use adw::glib::object::{Cast, CastNone, IsA, ObjectExt};
+use gtk4::prelude::GtkApplicationExt;
+
+const ACCELS_KEY: &str = "rouge-action-accels";
+const PREFIX_KEY: &str = "rouge-action-prefix";
+
+pub trait ReactiveActionExt {
+  /// Set the keyboard accelerators of the action, e.g. `["<Control>q"]`.
+  ///
+  /// They are registered with `set_accels_for_action` once the action has
+  /// been added to an application, a window or an action group, since
+  /// that's what gives it its prefix.
+  fn set_accels(&self, accels: &[&str]);
+}
+
+impl<T: IsA<Action>> ReactiveActionExt for T {
+  fn set_accels(&self, accels: &[&str]) {
+    let accels: Vec<String> = accels.iter().map(|accel| accel.to_string()).collect();
+    #[allow(unsafe_code)]
+    let prefix = unsafe {
+      self.set_data(ACCELS_KEY, accels.clone());
+      self
+        .data::<String>(PREFIX_KEY)
+        .map(|prefix| prefix.as_ref().clone())
+    };
+
+    if let Some(prefix) = prefix {
+      register_accels(&prefix, self.upcast_ref(), &accels);
+    }
+  }
+}
+
+/// Record that `action` is now available as `prefix.name`, and register its
+/// accelerators accordingly.
+pub(crate) fn action_added(action: &Action, prefix: &str) {
+  #[allow(unsafe_code)]
+  let accels = unsafe {
+    action.set_data(PREFIX_KEY, prefix.to_string());
+    action
+      .data::<Vec<String>>(ACCELS_KEY)
+      .map(|accels| accels.as_ref().clone())
+  };
+
+  if let Some(accels) = accels {
+    register_accels(prefix, action, &accels);
+  }
+}
+
+/// Unregister the accelerators of an action that is going away.
+pub(crate) fn action_removed(action: &Action) {
+  #[allow(unsafe_code)]
+  let prefix = unsafe { action.steal_data::<String>(PREFIX_KEY) };
+  if let Some(prefix) = prefix {
+    register_accels(&prefix, action, &[]);
+  }
+}
+
+fn register_accels(prefix: &str, action: &Action, accels: &[String]) {
+  let Some(application) = gio::Application::default().and_downcast::<gtk4::Application>() else {
+    return;
+  };
+
+  let accels: Vec<&str> = accels.iter().map(String::as_str).collect();
+  application.set_accels_for_action(&format!("{}.{}", prefix, action.name()), &accels);
+}
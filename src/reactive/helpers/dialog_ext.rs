@@ -0,0 +1,23 @@
+use adw::glib::object::IsA;
+use adw::prelude::AlertDialogExt;
+use adw::AlertDialog;
+
+pub trait ReactiveAlertDialogExt {
+  /// Declare the responses of the dialog, as `(id, label)` pairs.
+  ///
+  /// Responses aren't properties, so they survive patches: they are added
+  /// the first time and only relabelled afterwards.
+  fn set_responses(&self, responses: &[(&str, &str)]);
+}
+
+impl<T: IsA<AlertDialog>> ReactiveAlertDialogExt for T {
+  fn set_responses(&self, responses: &[(&str, &str)]) {
+    for (id, label) in responses {
+      if self.has_response(id) {
+        self.set_response_label(id, label);
+      } else {
+        self.add_response(id, label);
+      }
+    }
+  }
+}
@@ -0,0 +1,237 @@
+use std::{
+  cell::RefCell,
+  ffi::{c_char, CStr, CString},
+  fmt::Display,
+  sync::OnceLock,
+};
+
+use adw::glib::{self, DateTime};
+use futures::channel::mpsc::UnboundedSender;
+use log::warn;
+
+use super::component::{Component, ComponentMessage};
+
+/// Text domain of the application, i.e. the name of its `.mo` files.
+pub const GETTEXT_PACKAGE: &str = env!("CARGO_PKG_NAME");
+
+/// Where `.mo` files are looked up, `LOCALEDIR` at build time.
+pub const LOCALEDIR: &str = match option_env!("LOCALEDIR") {
+  Some(dir) => dir,
+  None => "/usr/share/locale",
+};
+
+static DOMAIN: OnceLock<String> = OnceLock::new();
+
+thread_local! {
+  static RENDERERS: RefCell<Vec<Box<dyn Renderer>>> = RefCell::new(Vec::new());
+}
+
+/// The system channel of a live component.
+trait Renderer {
+  /// Ask for a render, returning whether the component is still there.
+  fn render(&self) -> bool;
+  fn is_closed(&self) -> bool;
+}
+
+impl<C: Component> Renderer for UnboundedSender<ComponentMessage<C>> {
+  fn render(&self) -> bool {
+    self.unbounded_send(ComponentMessage::Render).is_ok()
+  }
+
+  fn is_closed(&self) -> bool {
+    UnboundedSender::is_closed(self)
+  }
+}
+
+#[allow(unsafe_code)]
+unsafe extern "C" {
+  fn bindtextdomain(domainname: *const c_char, dirname: *const c_char) -> *mut c_char;
+  fn bind_textdomain_codeset(domainname: *const c_char, codeset: *const c_char) -> *mut c_char;
+  fn textdomain(domainname: *const c_char) -> *mut c_char;
+}
+
+/// Bind `domain` to `localedir` and make it the default text domain. Called
+/// by `reactive::run` with [`GETTEXT_PACKAGE`] and [`LOCALEDIR`].
+pub fn init(domain: &str, localedir: &str) {
+  let c_domain = CString::new(domain).expect("The text domain contains a NUL byte.");
+  let c_dir = CString::new(localedir).expect("The locale directory contains a NUL byte.");
+  #[allow(unsafe_code)]
+  unsafe {
+    libc::setlocale(libc::LC_ALL, c"".as_ptr());
+    bindtextdomain(c_domain.as_ptr(), c_dir.as_ptr());
+    bind_textdomain_codeset(c_domain.as_ptr(), c"UTF-8".as_ptr());
+    textdomain(c_domain.as_ptr());
+  }
+  if DOMAIN.set(domain.to_string()).is_err() {
+    warn!("Text domain already set, ignoring {}.", domain);
+  }
+}
+
+fn domain() -> Option<&'static str> {
+  DOMAIN.get().map(String::as_str)
+}
+
+/// Translate `msgid`.
+pub fn tr(msgid: &str) -> String {
+  glib::dgettext(domain(), msgid).into()
+}
+
+/// Translate `singular`, or `plural`, depending on `n` and the plural rules
+/// of the language.
+pub fn ntr(singular: &str, plural: &str, n: u64) -> String {
+  glib::dngettext(domain(), singular, plural, n as _).into()
+}
+
+/// Translate `msgid` in `context`, to tell apart identical English strings
+/// with different meanings.
+pub fn tr_ctx(context: &str, msgid: &str) -> String {
+  glib::dpgettext2(domain(), context, msgid).into()
+}
+
+/// Replace the `{}` placeholders of a translated `template` with `args`, in
+/// order. `format!` can't be used, as the template is only known at runtime.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+  let mut filled = String::with_capacity(template.len());
+  let mut args = args.iter();
+  let mut parts = template.split("{}");
+  if let Some(first) = parts.next() {
+    filled.push_str(first);
+  }
+  for part in parts {
+    match args.next() {
+      Some(arg) => filled.push_str(&arg.to_string()),
+      None => filled.push_str("{}"),
+    }
+    filled.push_str(part);
+  }
+  filled
+}
+
+/// The decimal point and thousands separator of the current locale.
+fn separators() -> (String, String) {
+  #[allow(unsafe_code)]
+  unsafe {
+    let conv = libc::localeconv();
+    if conv.is_null() {
+      return (".".to_string(), String::new());
+    }
+    let read = |s: *const c_char| {
+      if s.is_null() {
+        String::new()
+      } else {
+        CStr::from_ptr(s).to_string_lossy().into_owned()
+      }
+    };
+    let point = read((*conv).decimal_point);
+    (
+      if point.is_empty() {
+        ".".to_string()
+      } else {
+        point
+      },
+      read((*conv).thousands_sep),
+    )
+  }
+}
+
+fn group(digits: &str, separator: &str) -> String {
+  let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+  for (i, digit) in digits.chars().enumerate() {
+    if i > 0 && (digits.len() - i).is_multiple_of(3) {
+      grouped.push_str(separator);
+    }
+    grouped.push(digit);
+  }
+  grouped
+}
+
+/// Format an integer with the digit grouping of the current locale.
+pub fn format_number(n: i64) -> String {
+  let (_, separator) = separators();
+  let grouped = group(&n.unsigned_abs().to_string(), &separator);
+  if n < 0 {
+    format!("-{}", grouped)
+  } else {
+    grouped
+  }
+}
+
+/// Format a number with `decimals` digits after the decimal point of the
+/// current locale.
+pub fn format_decimal(n: f64, decimals: usize) -> String {
+  let (point, separator) = separators();
+  let formatted = format!("{:.*}", decimals, n.abs());
+  let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+  let mut result = String::new();
+  if n.is_sign_negative() && n != 0.0 {
+    result.push('-');
+  }
+  result.push_str(&group(integer, &separator));
+  if !fraction.is_empty() {
+    result.push_str(&point);
+    result.push_str(fraction);
+  }
+  result
+}
+
+/// Format a size in bytes for humans, e.g. a download size, with the units
+/// and decimal point of the current locale.
+pub fn format_size(bytes: u64) -> String {
+  glib::format_size(bytes).into()
+}
+
+/// Format a date in the preferred representation of the current locale.
+pub fn format_date(date: &DateTime) -> String {
+  date
+    .format("%x")
+    .or_else(|_| date.format_iso8601())
+    .map(String::from)
+    .unwrap_or_default()
+}
+
+/// `LC_GLOBAL_LOCALE`, the locale set by `setlocale`.
+const GLOBAL_LOCALE: libc::locale_t = -1isize as libc::locale_t;
+
+/// Switch the UI thread to `locale`, e.g. "fr_FR.UTF-8", and re-render every
+/// component so that strings are translated again. An empty `locale` goes
+/// back to the one of the environment.
+///
+/// Only the calling thread changes locale: changing the global locale or the
+/// environment while workers run is a data race. As for the environment,
+/// `LANGUAGE` still takes precedence for messages if it's set.
+pub fn set_locale(locale: &str) {
+  #[allow(unsafe_code)]
+  unsafe {
+    let new = if locale.is_empty() {
+      GLOBAL_LOCALE
+    } else {
+      let c_locale = CString::new(locale).expect("The locale contains a NUL byte.");
+      let new = libc::newlocale(libc::LC_ALL_MASK, c_locale.as_ptr(), std::ptr::null_mut());
+      if new.is_null() {
+        warn!("Locale {} is not available.", locale);
+        return;
+      }
+      new
+    };
+    // Any other locale was created by a previous call.
+    let previous = libc::uselocale(new);
+    if previous != GLOBAL_LOCALE {
+      libc::freelocale(previous);
+    }
+  }
+  rerender();
+}
+
+/// Re-render every live component.
+pub fn rerender() {
+  RENDERERS.with(|renderers| renderers.borrow_mut().retain(|renderer| renderer.render()));
+}
+
+/// Have `channel` re-rendered by [`rerender`] until its task is gone.
+pub(crate) fn subscribe<C: 'static + Component>(channel: UnboundedSender<ComponentMessage<C>>) {
+  RENDERERS.with(|renderers| {
+    let mut renderers = renderers.borrow_mut();
+    renderers.retain(|renderer| !renderer.is_closed());
+    renderers.push(Box::new(channel));
+  });
+}
@@ -0,0 +1,344 @@
+use std::{
+  cell::{Cell, OnceCell, RefCell},
+  collections::HashMap,
+  path::PathBuf,
+  rc::Rc,
+  time::Duration,
+};
+
+use adw::{
+  gio::{prelude::SettingsExt, Settings, SettingsBackend, SettingsSchema, SettingsSchemaSource},
+  glib::{self, prelude::*, FileError, KeyFile, KeyFileFlags, Variant, VariantTy},
+};
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+
+use super::component::Component;
+use super::i18n::GETTEXT_PACKAGE;
+
+/// Delay before changes are written, so that e.g. resizing a window doesn't
+/// write them on every frame.
+const FLUSH_DELAY: Duration = Duration::from_millis(500);
+
+/// The persisted fields of a component, by name.
+type Fields = Map<String, Value>;
+type Save<C> = Box<dyn Fn(&C) -> Fields>;
+type Restore<C> = Box<dyn Fn(&mut C, Fields)>;
+
+thread_local! {
+  static KEY_FILE: OnceCell<Rc<KeyFileStore>> = const { OnceCell::new() };
+  static SETTINGS: RefCell<HashMap<String, Rc<SettingsStore>>> = RefCell::default();
+}
+
+/// Parts of the state of a component saved across restarts, see
+/// `Component::persistence`.
+///
+/// They are stored in the GSettings schema `id` when it's installed, one key
+/// per field, and in the group `id` of `$XDG_CONFIG_HOME/<package>/state.ini`
+/// otherwise, e.g. when running from the build directory. See
+/// `data/install-schema.sh`. GSettings keys use dashes, so persisted structs usually have
+/// `#[serde(rename_all = "kebab-case")]`.
+pub struct Persistence<C> {
+  id: &'static str,
+  save: Save<C>,
+  restore: Restore<C>,
+}
+
+impl<C> Persistence<C> {
+  /// Persist the `P` extracted from the state by `save`, given back to
+  /// `restore` on the next start. `P` must serialize to a struct.
+  pub fn new<P, S, R>(id: &'static str, save: S, restore: R) -> Self
+  where
+    P: Serialize + DeserializeOwned,
+    S: 'static + Fn(&C) -> P,
+    R: 'static + Fn(&mut C, P),
+  {
+    Persistence {
+      id,
+      save: Box::new(move |state| match serde_json::to_value(save(state)) {
+        Ok(Value::Object(map)) => map,
+        _ => panic!("The persisted state of {} must serialize to a struct.", id),
+      }),
+      restore: Box::new(
+        move |state, map| match serde_json::from_value(Value::Object(map)) {
+          Ok(persisted) => restore(state, persisted),
+          Err(error) => warn!("Unable to restore {}: {}", id, error),
+        },
+      ),
+    }
+  }
+}
+
+/// Restore the persisted parts of `state`, meant to be called from
+/// `Component::create`. Keys which were never saved keep their value.
+pub fn restored<C: Component>(mut state: C) -> C {
+  if let Some(persistence) = C::persistence() {
+    let store = Store::open(persistence.id);
+    let defaults = (persistence.save)(&state);
+    let map = store.read(persistence.id, defaults);
+    (persistence.restore)(&mut state, map);
+  }
+  state
+}
+
+/// Saves the persisted parts of the state of a component when they change.
+pub(crate) struct Saver<C> {
+  persistence: Persistence<C>,
+  store: Store,
+  saved: RefCell<Fields>,
+}
+
+impl<C> Saver<C> {
+  pub(crate) fn new(persistence: Persistence<C>, state: &C) -> Self {
+    let saved = (persistence.save)(state);
+    Saver {
+      store: Store::open(persistence.id),
+      persistence,
+      saved: RefCell::new(saved),
+    }
+  }
+
+  pub(crate) fn save(&self, state: &C) {
+    let map = (self.persistence.save)(state);
+    if *self.saved.borrow() == map {
+      return;
+    }
+    self.store.write(self.persistence.id, &map);
+    *self.saved.borrow_mut() = map;
+  }
+
+  /// Write pending changes right away, e.g. when the component unmounts.
+  pub(crate) fn flush(&self) {
+    match &self.store {
+      Store::Settings(store) => store.flush(),
+      Store::KeyFile(store) => store.flush(),
+    }
+  }
+}
+
+/// Write the pending changes of every component right away. Called when the
+/// application shuts down, as the main loop won't run the delayed writes.
+pub fn flush() {
+  KEY_FILE.with(|store| {
+    if let Some(store) = store.get() {
+      store.flush();
+    }
+  });
+  SETTINGS.with_borrow(|stores| {
+    for store in stores.values() {
+      store.flush();
+    }
+  });
+}
+
+/// Stores whose changes are written after [`FLUSH_DELAY`].
+trait Delayed: 'static {
+  fn scheduled(&self) -> &Cell<bool>;
+  fn flush(&self);
+}
+
+/// Flush `store` after [`FLUSH_DELAY`], unless it's already scheduled.
+fn schedule_flush<S: Delayed>(store: &Rc<S>) {
+  if store.scheduled().replace(true) {
+    return;
+  }
+  let store = Rc::downgrade(store);
+  glib::timeout_add_local_once(FLUSH_DELAY, move || {
+    if let Some(store) = store.upgrade() {
+      store.flush();
+    }
+  });
+}
+
+enum Store {
+  Settings(Rc<SettingsStore>),
+  KeyFile(Rc<KeyFileStore>),
+}
+
+impl Store {
+  fn open(id: &str) -> Self {
+    if let Some(store) = SETTINGS.with_borrow(|stores| stores.get(id).cloned()) {
+      return Store::Settings(store);
+    }
+    match SettingsSchemaSource::default().and_then(|source| source.lookup(id, true)) {
+      Some(schema) => {
+        let store = SettingsStore::new(schema);
+        SETTINGS.with_borrow_mut(|stores| stores.insert(id.to_string(), store.clone()));
+        Store::Settings(store)
+      }
+      None => Store::KeyFile(KEY_FILE.with(|store| store.get_or_init(KeyFileStore::load).clone())),
+    }
+  }
+
+  /// Read the keys of `defaults` from group `id`, keeping the default of
+  /// those which are missing or invalid.
+  fn read(&self, id: &str, mut defaults: Fields) -> Fields {
+    for (key, value) in defaults.iter_mut() {
+      let stored = match self {
+        Store::Settings(store) => {
+          if !store.schema.has_key(key) {
+            warn!("Key {} is missing from schema {}.", key, id);
+            continue;
+          }
+          variant_to_json(&store.settings.value(key))
+        }
+        Store::KeyFile(store) => store
+          .file
+          .string(id, key)
+          .ok()
+          .and_then(|text| serde_json::from_str(&text).ok()),
+      };
+      if let Some(stored) = stored {
+        *value = stored;
+      }
+    }
+    defaults
+  }
+
+  fn write(&self, id: &str, map: &Fields) {
+    match self {
+      Store::Settings(store) => {
+        for (key, value) in map {
+          if !store.schema.has_key(key) {
+            warn!("Key {} is missing from schema {}.", key, id);
+            continue;
+          }
+          let ty = store.schema.key(key).value_type();
+          let Some(variant) = json_to_variant(value, &ty) else {
+            warn!("Unable to store {} as {} in {}:{}.", value, ty, id, key);
+            continue;
+          };
+          if let Err(error) = store.settings.set_value(key, &variant) {
+            warn!("Unable to save {}:{}: {}", id, key, error);
+          }
+        }
+        schedule_flush(store);
+      }
+      Store::KeyFile(store) => {
+        for (key, value) in map {
+          store.file.set_string(id, key, &value.to_string());
+        }
+        schedule_flush(store);
+      }
+    }
+  }
+}
+
+/// The settings of a schema, shared by all components of the thread.
+/// Changes are applied after a delay, like those to the keyfile.
+struct SettingsStore {
+  settings: Settings,
+  schema: SettingsSchema,
+  scheduled: Cell<bool>,
+}
+
+impl SettingsStore {
+  fn new(schema: SettingsSchema) -> Rc<Self> {
+    let settings = Settings::new_full(&schema, None::<&SettingsBackend>, None);
+    settings.delay();
+    Rc::new(SettingsStore {
+      settings,
+      schema,
+      scheduled: Cell::new(false),
+    })
+  }
+}
+
+impl Delayed for SettingsStore {
+  fn scheduled(&self) -> &Cell<bool> {
+    &self.scheduled
+  }
+
+  fn flush(&self) {
+    self.scheduled.set(false);
+    if self.settings.has_unapplied() {
+      self.settings.apply();
+      // Wait for the backend to write them, in case the process exits.
+      Settings::sync();
+    }
+  }
+}
+
+/// The keyfile shared by all components of the thread, so that they don't
+/// overwrite each other's groups.
+struct KeyFileStore {
+  file: KeyFile,
+  path: PathBuf,
+  scheduled: Cell<bool>,
+}
+
+impl KeyFileStore {
+  fn load() -> Rc<Self> {
+    let path = glib::user_config_dir()
+      .join(GETTEXT_PACKAGE)
+      .join("state.ini");
+    let file = KeyFile::new();
+    if let Err(error) = file.load_from_file(&path, KeyFileFlags::KEEP_COMMENTS)
+      && !error.matches(FileError::Noent)
+    {
+      warn!("Unable to load {}: {}", path.display(), error);
+    }
+    Rc::new(KeyFileStore {
+      file,
+      path,
+      scheduled: Cell::new(false),
+    })
+  }
+}
+
+impl Delayed for KeyFileStore {
+  fn scheduled(&self) -> &Cell<bool> {
+    &self.scheduled
+  }
+
+  fn flush(&self) {
+    self.scheduled.set(false);
+    let result = match self.path.parent() {
+      Some(dir) => std::fs::create_dir_all(dir).map_err(|error| error.to_string()),
+      None => Ok(()),
+    }
+    .and_then(|_| {
+      self
+        .file
+        .save_to_file(&self.path)
+        .map_err(|error| error.to_string())
+    });
+    if let Err(error) = result {
+      warn!("Unable to save {}: {}", self.path.display(), error);
+    }
+  }
+}
+
+fn variant_to_json(variant: &Variant) -> Option<Value> {
+  Some(match variant.type_().as_str() {
+    "b" => Value::from(variant.get::<bool>()?),
+    "i" => Value::from(variant.get::<i32>()?),
+    "u" => Value::from(variant.get::<u32>()?),
+    "x" => Value::from(variant.get::<i64>()?),
+    "t" => Value::from(variant.get::<u64>()?),
+    "d" => Value::from(variant.get::<f64>()?),
+    "s" => Value::from(variant.str()?),
+    "as" => Value::from(variant.get::<Vec<String>>()?),
+    _ => return None,
+  })
+}
+
+fn json_to_variant(value: &Value, ty: &VariantTy) -> Option<Variant> {
+  Some(match ty.as_str() {
+    "b" => value.as_bool()?.to_variant(),
+    "i" => i32::try_from(value.as_i64()?).ok()?.to_variant(),
+    "u" => u32::try_from(value.as_u64()?).ok()?.to_variant(),
+    "x" => value.as_i64()?.to_variant(),
+    "t" => value.as_u64()?.to_variant(),
+    "d" => value.as_f64()?.to_variant(),
+    "s" => value.as_str()?.to_variant(),
+    "as" => value
+      .as_array()?
+      .iter()
+      .map(|item| item.as_str().map(String::from))
+      .collect::<Option<Vec<String>>>()?
+      .to_variant(),
+    _ => return None,
+  })
+}
@@ -0,0 +1,568 @@
+use std::{
+  cell::{Cell, RefCell},
+  collections::HashMap,
+  env,
+  fs::File,
+  io::{self, BufRead, BufReader, BufWriter, Write},
+  path::Path,
+  str::FromStr,
+  time::{Duration, Instant},
+};
+
+use adw::{
+  gio::{
+    prelude::{ActionMapExt, ApplicationExtManual},
+    SimpleAction,
+  },
+  glib::{self, ExitCode, MainContext},
+  prelude::{ApplicationExt, GtkApplicationExt},
+};
+use colored::Colorize;
+use futures::{
+  channel::mpsc::{unbounded, UnboundedSender},
+  StreamExt,
+};
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+  component::{Component, ComponentMessage},
+  once, start_with_channel, AndThen,
+};
+
+const STEP_ACTION: &str = "replay-step";
+const STEP_BACK_ACTION: &str = "replay-step-back";
+
+/// How long to wait for the component a message goes to to be mounted.
+const MOUNT_TIMEOUT: Duration = Duration::from_secs(1);
+const MOUNT_POLL: Duration = Duration::from_millis(10);
+
+/// A single line of a recording file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedMessage {
+  pub elapsed_ms: u64,
+  /// Path of the component which received the message, e.g.
+  /// `App/Counter#0`.
+  pub component: String,
+  pub message: Value,
+}
+
+/// Delivers an encoded message to a mounted component. Returns [`false`] if
+/// the component is gone.
+type Target = Box<dyn Fn(Value) -> bool>;
+
+/// What the components of the thread take part in.
+#[derive(Default)]
+enum Session {
+  #[default]
+  Idle,
+  Recording {
+    writer: Box<dyn Write>,
+    started: Instant,
+  },
+  Replaying {
+    targets: HashMap<String, Target>,
+  },
+}
+
+thread_local! {
+  static SESSION: RefCell<Session> = const { RefCell::new(Session::Idle) };
+  /// How many component tasks are processing messages.
+  static PROCESSING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Record the messages of every component mounted from now on, one JSON
+/// document per line.
+///
+/// Only messages coming from outside of the components are recorded, e.g.
+/// user input or the results of jobs: the messages sent while a component
+/// processes another one follow from it, and are sent again on replay.
+pub fn start_recording(writer: impl Write + 'static) {
+  SESSION.set(Session::Recording {
+    writer: Box::new(writer),
+    started: Instant::now(),
+  });
+}
+
+/// Make the components mounted from now on reachable by a [`Replayer`].
+pub fn start_replaying() {
+  SESSION.set(Session::Replaying {
+    targets: HashMap::new(),
+  });
+}
+
+/// Stop recording or replaying.
+pub fn stop() {
+  SESSION.set(Session::Idle);
+}
+
+/// Marks a component task as processing messages until dropped.
+pub(crate) struct Processing;
+
+impl Drop for Processing {
+  fn drop(&mut self) {
+    PROCESSING.set(PROCESSING.get() - 1);
+  }
+}
+
+pub(crate) fn processing() -> Processing {
+  PROCESSING.set(PROCESSING.get() + 1);
+  Processing
+}
+
+/// Record a message sent to the component at `path`, if recording.
+pub(crate) fn record<C: Component>(path: &str, message: &C::Message) {
+  if PROCESSING.get() > 0 {
+    return;
+  }
+  SESSION.with_borrow_mut(|session| {
+    let Session::Recording { writer, started } = session else {
+      return;
+    };
+    let Some(message) = C::encode_message(message) else {
+      return;
+    };
+    let entry = RecordedMessage {
+      elapsed_ms: started.elapsed().as_millis() as u64,
+      component: path.to_string(),
+      message,
+    };
+
+    // Flush after every message so that the recording survives a crash,
+    // which is usually when it's needed the most.
+    let result = serde_json::to_writer(&mut *writer, &entry)
+      .map_err(io::Error::from)
+      .and_then(|_| writeln!(writer))
+      .and_then(|_| writer.flush());
+    if let Err(error) = result {
+      warn!("{} {}", "Unable to record message:".bright_red(), error);
+    }
+  });
+}
+
+/// Make the component at `path` reachable by the replayer, through its
+/// system channel so that replayed messages are strictly ordered with
+/// `Reset`s.
+pub(crate) fn register<C: 'static + Component>(
+  path: &str,
+  channel: UnboundedSender<ComponentMessage<C>>,
+) {
+  SESSION.with_borrow_mut(|session| {
+    let Session::Replaying { targets } = session else {
+      return;
+    };
+    let name = path.to_string();
+    let target: Target = Box::new(move |message| match C::decode_message(message) {
+      Some(message) => channel
+        .unbounded_send(ComponentMessage::Update(message))
+        .is_ok(),
+      None => {
+        warn!("{} {}", "Unable to decode message for".bright_red(), name);
+        true
+      }
+    });
+    targets.insert(path.to_string(), target);
+  });
+}
+
+/// Deliver a message to the component at `path`. Returns [`false`] if it
+/// isn't mounted.
+fn deliver(path: &str, message: &Value) -> bool {
+  SESSION.with_borrow_mut(|session| {
+    let Session::Replaying { targets } = session else {
+      return false;
+    };
+    let delivered = targets
+      .get(path)
+      .is_some_and(|target| target(message.clone()));
+    if !delivered {
+      targets.remove(path);
+    }
+    delivered
+  })
+}
+
+/// Forget every component but the root, which are about to be rebuilt.
+fn forget_children(root: &str) {
+  SESSION.with_borrow_mut(|session| {
+    if let Session::Replaying { targets } = session {
+      targets.retain(|path, _| path == root);
+    }
+  });
+}
+
+/// Encode a message for [`Component::encode_message`].
+pub fn encode<M: Serialize>(message: &M) -> Option<Value> {
+  serde_json::to_value(message)
+    .inspect_err(|error| warn!("{} {}", "Unable to encode message:".bright_red(), error))
+    .ok()
+}
+
+/// Decode a message for [`Component::decode_message`].
+pub fn decode<M: DeserializeOwned>(message: Value) -> Option<M> {
+  serde_json::from_value(message)
+    .inspect_err(|error| warn!("{} {}", "Unable to decode message:".bright_red(), error))
+    .ok()
+}
+
+/// How a recording is fed back to the components.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+  /// Feed every message as soon as the application is activated.
+  #[default]
+  Instant,
+  /// Feed messages with the same delays as when they were recorded.
+  Realtime,
+  /// Wait for the user to step through messages, forward with F10 and
+  /// backward with F9.
+  Step,
+}
+
+impl FromStr for ReplayMode {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "instant" => Ok(ReplayMode::Instant),
+      "realtime" => Ok(ReplayMode::Realtime),
+      "step" => Ok(ReplayMode::Step),
+      _ => Err(format!("unknown replay mode \"{}\"", s)),
+    }
+  }
+}
+
+/// Read the messages of a recording.
+pub fn load(reader: impl BufRead) -> io::Result<Vec<RecordedMessage>> {
+  let mut messages = Vec::new();
+  for line in reader.lines() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    messages.push(serde_json::from_str(&line)?);
+  }
+
+  Ok(messages)
+}
+
+/// Feeds a recording to the mounted components, and allows moving back and
+/// forth in it.
+pub struct Replayer {
+  /// Path of the root component, which is reset to travel back in time.
+  root: String,
+  reset: Box<dyn Fn()>,
+  messages: Vec<RecordedMessage>,
+  position: usize,
+}
+
+impl Replayer {
+  pub fn new(root: String, reset: impl Fn() + 'static, messages: Vec<RecordedMessage>) -> Self {
+    Replayer {
+      root,
+      reset: Box::new(reset),
+      messages,
+      position: 0,
+    }
+  }
+
+  /// Number of messages already fed to the components.
+  pub fn position(&self) -> usize {
+    self.position
+  }
+
+  pub fn len(&self) -> usize {
+    self.messages.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.messages.is_empty()
+  }
+
+  /// Feed the next message, once the component it goes to is mounted.
+  /// Returns [`false`] once the recording is over.
+  pub async fn step(&mut self) -> bool {
+    let Some(recorded) = self.messages.get(self.position) else {
+      return false;
+    };
+
+    debug!(
+      "{} {}/{} {}",
+      "Replaying message".bright_yellow(),
+      self.position + 1,
+      self.messages.len(),
+      recorded.component.magenta().bold()
+    );
+    // Components are mounted as their parents render, which happens on the
+    // main loop.
+    let mut waited = Duration::ZERO;
+    while !deliver(&recorded.component, &recorded.message) {
+      if waited >= MOUNT_TIMEOUT {
+        warn!(
+          "{} {}",
+          "Skipping message to unmounted component".bright_red(),
+          recorded.component
+        );
+        break;
+      }
+      glib::timeout_future(MOUNT_POLL).await;
+      waited += MOUNT_POLL;
+    }
+    self.position += 1;
+    true
+  }
+
+  /// Undo the last message by resetting the components and replaying
+  /// everything before it.
+  pub async fn step_back(&mut self) {
+    if let Some(position) = self.position.checked_sub(1) {
+      self.seek(position).await;
+    }
+  }
+
+  /// Move to the state right after the first `position` messages.
+  pub async fn seek(&mut self, position: usize) {
+    let position = position.min(self.messages.len());
+    if position < self.position {
+      forget_children(&self.root);
+      (self.reset)();
+      self.position = 0;
+    }
+
+    while self.position < position {
+      self.step().await;
+    }
+  }
+
+  /// Feed the remaining messages, waiting between each of them as long as
+  /// was waited during the recording.
+  pub async fn play_realtime(&mut self) {
+    let mut previous = self
+      .messages
+      .get(self.position)
+      .map_or(0, |recorded| recorded.elapsed_ms);
+    while let Some(recorded) = self.messages.get(self.position) {
+      let elapsed = recorded.elapsed_ms;
+      glib::timeout_future(Duration::from_millis(elapsed.saturating_sub(previous))).await;
+      previous = elapsed;
+      self.step().await;
+    }
+  }
+}
+
+/// Run the root component while recording the messages of every component
+/// to `path`.
+pub fn run_recording<C: 'static + Component>(path: impl AsRef<Path>) -> io::Result<ExitCode> {
+  start_recording(BufWriter::new(File::create(path)?));
+  let (app, _, _) = start_with_channel::<C>();
+  let args: Vec<String> = env::args().collect();
+  Ok(app.run_with_args(&args))
+}
+
+enum Command {
+  Step,
+  StepBack,
+}
+
+/// Mount the root component and feed the recording found at `path` to the
+/// components.
+pub fn run_replay<C: 'static + Component>(
+  path: impl AsRef<Path>,
+  mode: ReplayMode,
+) -> io::Result<ExitCode> {
+  let messages = load(BufReader::new(File::open(path)?))?;
+  start_replaying();
+  let (app, scope, channel) = start_with_channel::<C>();
+  let reset = move || {
+    channel
+      .unbounded_send(ComponentMessage::Reset)
+      .expect("failed to send reset message over system channel");
+  };
+  let mut replayer = Replayer::new(scope.path().to_string(), reset, messages);
+
+  // Commands are run one after the other, as stepping waits for components
+  // to be mounted.
+  let (commands, mut queue) = unbounded();
+  if mode == ReplayMode::Step {
+    let step = SimpleAction::new(STEP_ACTION, None);
+    let step_commands = commands.clone();
+    step.connect_activate(move |_, _| {
+      let _ = step_commands.unbounded_send(Command::Step);
+    });
+    app.add_action(&step);
+    app.set_accels_for_action(&format!("app.{}", STEP_ACTION), &["F10"]);
+
+    let step_back = SimpleAction::new(STEP_BACK_ACTION, None);
+    step_back.connect_activate(move |_, _| {
+      let _ = commands.unbounded_send(Command::StepBack);
+    });
+    app.add_action(&step_back);
+    app.set_accels_for_action(&format!("app.{}", STEP_BACK_ACTION), &["F9"]);
+  }
+
+  // Connected after the handler mounting the components, so the recording
+  // is fed after the `Mounted` message.
+  let start_replay = once(AndThen::DoNothing, move |_| {
+    MainContext::ref_thread_default().spawn_local(async move {
+      match mode {
+        ReplayMode::Instant => replayer.seek(replayer.len()).await,
+        ReplayMode::Realtime => replayer.play_realtime().await,
+        ReplayMode::Step => {
+          debug!(
+            "{} {}",
+            replayer.len(),
+            "messages loaded, press F10 to step forward and F9 to step back.".bright_yellow()
+          );
+          while let Some(command) = queue.next().await {
+            match command {
+              Command::Step => {
+                replayer.step().await;
+              }
+              Command::StepBack => replayer.step_back().await,
+            }
+          }
+        }
+      }
+    });
+  });
+  app.connect_activate(move |_| start_replay(()));
+
+  let args: Vec<String> = env::args().collect();
+  Ok(app.run_with_args(&args))
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{io::Cursor, rc::Rc};
+
+  use adw::glib::MainContext;
+  use futures::channel::mpsc::UnboundedReceiver;
+
+  use super::*;
+  use crate::reactive::{component::ViewContext, vnode::VNode};
+
+  /// A writer whose contents can be read back once it's been moved into a
+  /// session.
+  #[derive(Clone, Default)]
+  struct Shared(Rc<RefCell<Vec<u8>>>);
+
+  impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.borrow_mut().extend_from_slice(buf);
+      Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+  enum Message {
+    Add(u32),
+    Clear,
+  }
+
+  #[derive(Clone, Debug, Default)]
+  struct Model;
+
+  impl Component for Model {
+    type Message = Message;
+    type Props = ();
+
+    fn encode_message(message: &Message) -> Option<Value> {
+      encode(message)
+    }
+
+    fn decode_message(message: Value) -> Option<Message> {
+      decode(message)
+    }
+
+    fn view(&self, _: &ViewContext<Self>) -> VNode<'_, Self> {
+      unreachable!()
+    }
+  }
+
+  /// The messages waiting in `channel`, [`None`] standing for a reset.
+  fn received(channel: &mut UnboundedReceiver<ComponentMessage<Model>>) -> Vec<Option<Message>> {
+    let mut messages = vec![];
+    while let Ok(Some(message)) = channel.try_next() {
+      messages.push(match message {
+        ComponentMessage::Update(message) => Some(message),
+        ComponentMessage::Reset => None,
+        message => panic!("unexpected {:?}", message),
+      });
+    }
+    messages
+  }
+
+  #[test]
+  fn records_messages_from_outside_components() {
+    let output = Shared::default();
+    start_recording(output.clone());
+    record::<Model>("App", &Message::Add(1));
+    {
+      let _processing = processing();
+      // Follows from the message being processed.
+      record::<Model>("App/Model#0", &Message::Clear);
+    }
+    record::<Model>("App/Model#0", &Message::Add(2));
+    stop();
+
+    let messages = load(Cursor::new(output.0.take())).unwrap();
+    let messages: Vec<_> = messages
+      .into_iter()
+      .map(|recorded| (recorded.component, decode::<Message>(recorded.message)))
+      .collect();
+    assert_eq!(
+      messages,
+      vec![
+        ("App".to_string(), Some(Message::Add(1))),
+        ("App/Model#0".to_string(), Some(Message::Add(2))),
+      ]
+    );
+  }
+
+  #[test]
+  fn replays_a_recording() {
+    let output = Shared::default();
+    start_recording(output.clone());
+    record::<Model>("App", &Message::Add(1));
+    record::<Model>("App/Model#0", &Message::Add(2));
+    record::<Model>("App", &Message::Clear);
+    stop();
+    let messages = load(Cursor::new(output.0.take())).unwrap();
+    assert_eq!(messages.len(), 3);
+
+    start_replaying();
+    let (root, mut root_messages) = unbounded::<ComponentMessage<Model>>();
+    let (child, mut child_messages) = unbounded::<ComponentMessage<Model>>();
+    register::<Model>("App", root.clone());
+    register::<Model>("App/Model#0", child);
+    let reset = move || root.unbounded_send(ComponentMessage::Reset).unwrap();
+    let mut replayer = Replayer::new("App".to_string(), reset, messages);
+
+    let context = MainContext::new();
+    context.block_on(replayer.seek(2));
+    assert_eq!(replayer.position(), 2);
+    assert_eq!(received(&mut root_messages), vec![Some(Message::Add(1))]);
+    assert_eq!(received(&mut child_messages), vec![Some(Message::Add(2))]);
+
+    // Going back resets the root and forgets its children, which are rebuilt
+    // along with it.
+    let (rebuilt, mut rebuilt_messages) = unbounded::<ComponentMessage<Model>>();
+    context.block_on(async {
+      replayer.step_back().await;
+      register::<Model>("App/Model#0", rebuilt);
+      replayer.seek(3).await;
+    });
+    assert_eq!(replayer.position(), 3);
+    assert_eq!(
+      received(&mut root_messages),
+      vec![None, Some(Message::Add(1)), Some(Message::Clear)]
+    );
+    assert_eq!(received(&mut child_messages), vec![]);
+    assert_eq!(received(&mut rebuilt_messages), vec![Some(Message::Add(2))]);
+    stop();
+  }
+}
@@ -1,8 +1,9 @@
 use std::{
   any::TypeId,
+  collections::HashMap,
   sync::{
     atomic::{AtomicPtr, AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex, RwLock,
   },
 };
 
@@ -10,37 +11,98 @@ use colored::Colorize;
 use futures::channel::mpsc::UnboundedSender;
 use log::debug;
 
-use crate::reactive::component::Component;
+use crate::reactive::{component::Component, error_boundary::ComponentError, replay};
+
+/// Receives the panics of the components below an error boundary.
+pub type ErrorHandler = Arc<dyn Fn(ComponentError) + Send + Sync>;
 
 pub struct Scope<C: Component> {
   name: &'static str,
+  /// Where the component is in the tree, e.g. `App/Counter#0`, which stays
+  /// the same from a run to the next. See [`replay`].
+  path: Arc<str>,
+  /// How many children of each type this component has created.
+  children: Arc<Mutex<HashMap<&'static str, usize>>>,
   muted: Arc<AtomicUsize>,
   channel: UnboundedSender<C::Message>,
+  /// Where the panics of this component go.
+  boundary: Option<ErrorHandler>,
+  /// Where the panics of the children of this component go, if this
+  /// component is an error boundary.
+  catches: Arc<RwLock<Option<ErrorHandler>>>,
 }
 
 impl<C: Component> Scope<C> {
   pub(crate) fn new(name: &'static str, channel: UnboundedSender<C::Message>) -> Self {
     Scope {
       name,
+      path: short_name(name).into(),
+      children: Default::default(),
       muted: Default::default(),
       channel,
+      boundary: None,
+      catches: Default::default(),
     }
   }
 }
 
+/// Unmutes its scope when dropped, even when unwinding.
+pub struct MuteGuard {
+  muted: Arc<AtomicUsize>,
+}
+
+impl Drop for MuteGuard {
+  fn drop(&mut self) {
+    self.muted.fetch_sub(1, Ordering::SeqCst);
+  }
+}
+
 impl<C: 'static + Component> Scope<C> {
   pub fn inherit<Child: Component>(
     &self,
     name: &'static str,
     channel: UnboundedSender<Child::Message>,
   ) -> Scope<Child> {
+    let short = short_name(name);
+    let index = {
+      let mut children = self.children.lock().unwrap();
+      let count = children.entry(short).or_default();
+      *count += 1;
+      *count - 1
+    };
     Scope {
       name,
+      path: format!("{}/{}#{}", self.path, short, index).into(),
+      children: Default::default(),
       muted: self.muted.clone(),
       channel,
+      boundary: self.child_boundary(),
+      catches: Default::default(),
     }
   }
 
+  /// Number children from zero again, once they have all been unmounted, so
+  /// that their replacements get the same paths.
+  pub(crate) fn reset_children(&self) {
+    self.children.lock().unwrap().clear();
+  }
+
+  /// The error handler of the nearest error boundary above this component.
+  pub fn boundary(&self) -> Option<ErrorHandler> {
+    self.boundary.clone()
+  }
+
+  /// The error handler children of this component report their panics to.
+  pub fn child_boundary(&self) -> Option<ErrorHandler> {
+    let catches = self.catches.read().unwrap().clone();
+    catches.or_else(|| self.boundary.clone())
+  }
+
+  /// Make this component an error boundary for its children.
+  pub fn catch_panics(&self, handler: ErrorHandler) {
+    *self.catches.write().unwrap() = Some(handler);
+  }
+
   pub fn is_muted(&self) -> bool {
     self.muted.load(Ordering::SeqCst) > 0
   }
@@ -53,9 +115,18 @@ impl<C: 'static + Component> Scope<C> {
     self.muted.fetch_sub(1, Ordering::SeqCst);
   }
 
+  /// Mute the scope until the returned guard is dropped.
+  pub fn muted(&self) -> MuteGuard {
+    self.mute();
+    MuteGuard {
+      muted: self.muted.clone(),
+    }
+  }
+
   pub fn send_message(&self, message: C::Message) {
     self.log(&message);
     if !self.is_muted() {
+      replay::record::<C>(&self.path, &message);
       self
         .channel
         .unbounded_send(message)
@@ -63,6 +134,13 @@ impl<C: 'static + Component> Scope<C> {
     }
   }
 
+  /// Send a message even if the scope is muted. Only for messages that
+  /// can't be an echo of a patch, such as errors.
+  pub(crate) fn post(&self, message: C::Message) {
+    self.log(&message);
+    let _ = self.channel.unbounded_send(message);
+  }
+
   #[inline(always)]
   fn log(&self, message: &C::Message) {
     debug!(
@@ -80,14 +158,29 @@ impl<C: 'static + Component> Scope<C> {
   pub fn name(&self) -> &'static str {
     &self.name
   }
+
+  pub fn path(&self) -> &str {
+    &self.path
+  }
+}
+
+/// The name of a component type without its module path, e.g. `Counter`.
+fn short_name(type_name: &'static str) -> &'static str {
+  let end = type_name.find('<').unwrap_or(type_name.len());
+  let start = type_name[..end].rfind("::").map_or(0, |i| i + 2);
+  &type_name[start..end]
 }
 
 impl<C: Component> Clone for Scope<C> {
   fn clone(&self) -> Self {
     Scope {
       name: self.name,
+      path: self.path.clone(),
+      children: self.children.clone(),
       muted: self.muted.clone(),
       channel: self.channel.clone(),
+      boundary: self.boundary.clone(),
+      catches: self.catches.clone(),
     }
   }
 }
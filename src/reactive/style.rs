@@ -0,0 +1,178 @@
+use std::{
+  any::{type_name, TypeId},
+  cell::RefCell,
+  collections::HashSet,
+};
+
+use adw::glib::{object::Cast, Object};
+use gtk4::{gdk::Display, prelude::WidgetExt, CssProvider, Widget};
+
+use crate::reactive::component::Component;
+
+thread_local! {
+  static LOADED: RefCell<HashSet<TypeId>> = RefCell::new(HashSet::new());
+}
+
+/// CSS declared by a component, see `Component::stylesheet`.
+#[derive(Clone, Debug)]
+pub struct Stylesheet {
+  css: &'static str,
+  scoped: bool,
+}
+
+impl Stylesheet {
+  /// A stylesheet applying to the whole application.
+  pub fn global(css: &'static str) -> Self {
+    Stylesheet { css, scoped: false }
+  }
+
+  /// A stylesheet applying to the component only. `&` stands for the root
+  /// widget of the component, which is given a class generated from the
+  /// type of the component, e.g. `& label.title { font-weight: bold; }`.
+  pub fn scoped(css: &'static str) -> Self {
+    Stylesheet { css, scoped: true }
+  }
+}
+
+/// Class given to the root widget of `C` when its stylesheet is scoped.
+pub fn scope_class<C: 'static>() -> String {
+  let mut class = String::from("rouge");
+  for part in type_name::<C>().split(|c: char| !c.is_ascii_alphanumeric()) {
+    if !part.is_empty() {
+      class.push('-');
+      class.push_str(&part.to_ascii_lowercase());
+    }
+  }
+  class
+}
+
+/// Load `css` for the default display, for the lifetime of the application.
+pub fn load_css(css: &str) -> CssProvider {
+  let provider = CssProvider::new();
+  provider.load_from_string(css);
+  let display = Display::default().expect("CSS can't be loaded before GTK is initialised.");
+  gtk4::style_context_add_provider_for_display(
+    &display,
+    &provider,
+    gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+  );
+  provider
+}
+
+/// Load the stylesheet of `C` the first time one is rendered, and mark
+/// `root` with its scope class if it's scoped.
+pub(crate) fn style_root<C: 'static + Component>(root: &Object) {
+  let Some(stylesheet) = C::stylesheet() else {
+    return;
+  };
+
+  let first = LOADED.with(|loaded| loaded.borrow_mut().insert(TypeId::of::<C>()));
+  if first {
+    if stylesheet.scoped {
+      let selector = format!(".{}", scope_class::<C>());
+      load_css(&stylesheet.css.replace('&', &selector));
+    } else {
+      load_css(stylesheet.css);
+    }
+  }
+
+  if stylesheet.scoped
+    && let Some(widget) = root.downcast_ref::<Widget>()
+  {
+    widget.add_css_class(&scope_class::<C>());
+  }
+}
+
+/// Update the classes of `widget` from `previous` to `classes`, leaving the
+/// others alone.
+pub(crate) fn patch_classes(widget: &Widget, previous: &[String], classes: &[String]) {
+  for class in previous {
+    if !classes.contains(class) {
+      widget.remove_css_class(class);
+    }
+  }
+  for class in classes {
+    if !previous.contains(class) || !widget.has_css_class(class) {
+      widget.add_css_class(class);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use gtk4::{prelude::*, Label};
+
+  use super::*;
+  use crate::reactive::{
+    testing::{scope, with_gtk, Model},
+    vnode::{vobject::VObjectBuilder, VNode},
+    vstate::VState,
+  };
+
+  fn label(classes: &[&str]) -> VNode<'static, Model> {
+    Label::c(|w| w.set_label("Styled")).classes(classes)
+  }
+
+  fn classes(widget: &Widget) -> Vec<String> {
+    let mut classes: Vec<_> = widget
+      .css_classes()
+      .iter()
+      .map(|class| class.to_string())
+      .collect();
+    classes.sort();
+    classes
+  }
+
+  #[test]
+  fn names_scope_classes_after_the_component() {
+    assert_eq!(
+      scope_class::<Model>(),
+      "rouge-rouge-software-reactive-testing-model"
+    );
+  }
+
+  #[test]
+  fn patches_only_the_classes_which_changed() {
+    with_gtk(|| {
+      let widget: Widget = Label::new(None).upcast();
+      widget.add_css_class("custom");
+      patch_classes(&widget, &[], &["title".into(), "dim-label".into()]);
+      assert_eq!(classes(&widget), vec!["custom", "dim-label", "title"]);
+
+      patch_classes(
+        &widget,
+        &["title".into(), "dim-label".into()],
+        &["title".into(), "accent".into()],
+      );
+      assert_eq!(classes(&widget), vec!["accent", "custom", "title"]);
+    });
+  }
+
+  #[test]
+  fn restores_classes_removed_behind_the_view() {
+    with_gtk(|| {
+      let widget: Widget = Label::new(None).upcast();
+      patch_classes(&widget, &[], &["title".into()]);
+      widget.remove_css_class("title");
+      patch_classes(&widget, &["title".into()], &["title".into()]);
+      assert_eq!(classes(&widget), vec!["title"]);
+    });
+  }
+
+  #[test]
+  fn diffs_the_classes_of_views() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let mut state = VState::build(&label(&["title", "dim-label"]), None, &scope);
+      let widget = state.widget().unwrap().clone();
+      // Classes added outside of the view are left alone.
+      widget.add_css_class("custom");
+
+      assert!(state.patch(&label(&["title", "accent"]), None, &scope));
+      assert_eq!(classes(&widget), vec!["accent", "custom", "title"]);
+      assert!(state.patch(&label(&[]), None, &scope));
+      assert_eq!(classes(&widget), vec!["custom"]);
+      state.unmount();
+    });
+  }
+}
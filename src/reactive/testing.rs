@@ -0,0 +1,78 @@
+use std::{
+  panic::{self, AssertUnwindSafe},
+  sync::{mpsc, OnceLock},
+  thread,
+};
+
+use adw::glib::MainContext;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+
+use super::{
+  component::{Component, ViewContext},
+  scope::Scope,
+  vnode::VNode,
+};
+
+type Test = Box<dyn FnOnce() + Send>;
+
+/// Runs the tests which need GTK. GTK can only be used from the thread which
+/// initialized it, while each test runs on its own thread.
+static GTK_THREAD: OnceLock<mpsc::Sender<Test>> = OnceLock::new();
+
+/// Run `test` on the GTK thread, with a new main context as the thread
+/// default so that the components it mounts are spawned on it.
+pub fn with_gtk<R: 'static + Send>(test: impl 'static + Send + FnOnce() -> R) -> R {
+  let gtk_thread = GTK_THREAD.get_or_init(|| {
+    let (sender, receiver) = mpsc::channel::<Test>();
+    thread::spawn(move || {
+      adw::init().expect("GTK failed to initialize.");
+      for test in receiver {
+        test();
+      }
+    });
+    sender
+  });
+
+  let (sender, receiver) = mpsc::channel();
+  gtk_thread
+    .send(Box::new(move || {
+      let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        MainContext::new().with_thread_default(test).unwrap()
+      }));
+      let _ = sender.send(result);
+    }))
+    .expect("the GTK thread is gone");
+  match receiver.recv().expect("the GTK thread is gone") {
+    Ok(result) => result,
+    Err(panic) => panic::resume_unwind(panic),
+  }
+}
+
+/// A component for tests to build views by hand, sending strings as
+/// messages.
+#[derive(Clone, Debug, Default)]
+pub struct Model;
+
+impl Component for Model {
+  type Message = String;
+  type Props = ();
+
+  fn view(&self, _: &ViewContext<Self>) -> VNode<'_, Self> {
+    unreachable!("tests build the views of Model themselves")
+  }
+}
+
+/// A scope for the views of [`Model`], and the messages sent through it.
+pub fn scope() -> (Scope<Model>, UnboundedReceiver<String>) {
+  let (sender, receiver) = unbounded();
+  (Scope::new("Model", sender), receiver)
+}
+
+/// The messages waiting in `messages`.
+pub fn received(messages: &mut UnboundedReceiver<String>) -> Vec<String> {
+  let mut received = vec![];
+  while let Ok(Some(message)) = messages.try_next() {
+    received.push(message);
+  }
+  received
+}
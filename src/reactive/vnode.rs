@@ -1,8 +1,19 @@
+pub mod vaccessible;
+pub mod vaction;
+pub mod vanimation;
 pub mod vcomponent;
+pub mod vcontrolled;
+pub mod vcontroller;
+pub mod vlist;
+pub mod vmenu;
 pub mod vobject;
 pub mod vprops;
 
+use gtk4::AccessibleRole;
+use vaccessible::VAccessible;
+use vanimation::{VAnimation, VTransition};
 use vcomponent::VComponent;
+use vcontroller::VController;
 use vobject::VObject;
 
 use super::component::Component;
@@ -19,4 +30,66 @@ impl<'a, C: Component> VNode<'a, C> {
       VNode::Component(_) => panic!("Not implemented."),
     }
   }
+
+  /// Animate numeric `property` to `value` instead of setting it, see
+  /// [`VAnimation`]. The patcher should leave it alone.
+  pub fn animate(self, property: &'static str, value: f64, animation: VAnimation) -> Self {
+    match self {
+      VNode::Object(node) => node.animate(property, value, animation),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
+
+  /// Set the CSS classes of the widget. Unlike classes added by the patcher,
+  /// they are diffed with the previous ones rather than reset on every patch.
+  pub fn classes(self, classes: &[&str]) -> Self {
+    match self {
+      VNode::Object(node) => node.classes(classes),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
+
+  /// Set accessible properties, relations and states of the widget. Those
+  /// which are no longer declared are reset.
+  pub fn accessible(self, accessible: Vec<VAccessible>) -> Self {
+    match self {
+      VNode::Object(node) => node.accessible(accessible),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
+
+  /// Build the widget with `role`, which can't change afterwards.
+  pub fn accessible_role(self, role: AccessibleRole) -> Self {
+    match self {
+      VNode::Object(node) => node.accessible_role(role),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
+
+  /// Attach an event controller to the widget, see [`VController`].
+  pub fn controller(self, controller: VController<'a, C>) -> Self {
+    match self {
+      VNode::Object(node) => node.controller(controller),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
+
+  /// Tell the node apart from the previous one at the same position: when
+  /// the key changes, the object is built again rather than patched, e.g. a
+  /// dialog closed by the user.
+  pub fn key(self, key: impl ToString) -> Self {
+    match self {
+      VNode::Object(node) => node.key(key),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
+
+  /// Bring the node in and out with `transition` when it's added or removed
+  /// by a patch.
+  pub fn transition(self, transition: VTransition) -> Self {
+    match self {
+      VNode::Object(node) => node.transition(transition),
+      VNode::Component(_) => panic!("Not implemented."),
+    }
+  }
 }
@@ -0,0 +1,274 @@
+use adw::glib::{
+  self,
+  object::{Cast, ObjectExt},
+  Object,
+};
+use gtk4::{
+  accessible::{Property, Relation, State},
+  prelude::{AccessibleExt, AccessibleExtManual, ButtonExt, CheckButtonExt, WidgetExt},
+  Accessible, AccessibleInvalidState, AccessibleProperty, AccessibleRelation, AccessibleState,
+  AccessibleTristate, Button, CheckButton, DropDown, Entry, Label, MenuButton, Scale, SearchEntry,
+  SpinButton, Switch, Widget,
+};
+
+const NAMED_KEY: &str = "rouge-accessible-named";
+
+/// An accessible property, relation or state of a node, see
+/// `VNode::accessible`.
+///
+/// Relations refer to other widgets by their name, set with
+/// `WidgetExt::set_widget_name`, and are resolved within the same window
+/// once the patch is over.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VAccessible {
+  // Properties.
+  Label(String),
+  Description(String),
+  Placeholder(String),
+  RoleDescription(String),
+  KeyShortcuts(String),
+  ValueText(String),
+  HasPopup(bool),
+  ReadOnly(bool),
+  Required(bool),
+  Level(i32),
+  ValueMin(f64),
+  ValueMax(f64),
+  ValueNow(f64),
+  // Relations.
+  LabelledBy(Vec<String>),
+  DescribedBy(Vec<String>),
+  Controls(Vec<String>),
+  // States.
+  Busy(bool),
+  Checked(AccessibleTristate),
+  Disabled(bool),
+  Expanded(Option<bool>),
+  Hidden(bool),
+  Invalid(AccessibleInvalidState),
+  Pressed(AccessibleTristate),
+  Selected(Option<bool>),
+}
+
+enum Kind {
+  Property(AccessibleProperty),
+  Relation(AccessibleRelation),
+  State(AccessibleState),
+}
+
+impl VAccessible {
+  fn kind(&self) -> Kind {
+    match self {
+      VAccessible::Label(_) => Kind::Property(AccessibleProperty::Label),
+      VAccessible::Description(_) => Kind::Property(AccessibleProperty::Description),
+      VAccessible::Placeholder(_) => Kind::Property(AccessibleProperty::Placeholder),
+      VAccessible::RoleDescription(_) => Kind::Property(AccessibleProperty::RoleDescription),
+      VAccessible::KeyShortcuts(_) => Kind::Property(AccessibleProperty::KeyShortcuts),
+      VAccessible::ValueText(_) => Kind::Property(AccessibleProperty::ValueText),
+      VAccessible::HasPopup(_) => Kind::Property(AccessibleProperty::HasPopup),
+      VAccessible::ReadOnly(_) => Kind::Property(AccessibleProperty::ReadOnly),
+      VAccessible::Required(_) => Kind::Property(AccessibleProperty::Required),
+      VAccessible::Level(_) => Kind::Property(AccessibleProperty::Level),
+      VAccessible::ValueMin(_) => Kind::Property(AccessibleProperty::ValueMin),
+      VAccessible::ValueMax(_) => Kind::Property(AccessibleProperty::ValueMax),
+      VAccessible::ValueNow(_) => Kind::Property(AccessibleProperty::ValueNow),
+      VAccessible::LabelledBy(_) => Kind::Relation(AccessibleRelation::LabelledBy),
+      VAccessible::DescribedBy(_) => Kind::Relation(AccessibleRelation::DescribedBy),
+      VAccessible::Controls(_) => Kind::Relation(AccessibleRelation::Controls),
+      VAccessible::Busy(_) => Kind::State(AccessibleState::Busy),
+      VAccessible::Checked(_) => Kind::State(AccessibleState::Checked),
+      VAccessible::Disabled(_) => Kind::State(AccessibleState::Disabled),
+      VAccessible::Expanded(_) => Kind::State(AccessibleState::Expanded),
+      VAccessible::Hidden(_) => Kind::State(AccessibleState::Hidden),
+      VAccessible::Invalid(_) => Kind::State(AccessibleState::Invalid),
+      VAccessible::Pressed(_) => Kind::State(AccessibleState::Pressed),
+      VAccessible::Selected(_) => Kind::State(AccessibleState::Selected),
+    }
+  }
+
+  fn same_kind(&self, other: &VAccessible) -> bool {
+    std::mem::discriminant(self) == std::mem::discriminant(other)
+  }
+
+  fn names(&self) -> bool {
+    matches!(self, VAccessible::Label(_) | VAccessible::LabelledBy(_))
+  }
+
+  fn apply(&self, accessible: &Accessible) {
+    match self {
+      VAccessible::Label(v) => accessible.update_property(&[Property::Label(v)]),
+      VAccessible::Description(v) => accessible.update_property(&[Property::Description(v)]),
+      VAccessible::Placeholder(v) => accessible.update_property(&[Property::Placeholder(v)]),
+      VAccessible::RoleDescription(v) => {
+        accessible.update_property(&[Property::RoleDescription(v)])
+      }
+      VAccessible::KeyShortcuts(v) => accessible.update_property(&[Property::KeyShortcuts(v)]),
+      VAccessible::ValueText(v) => accessible.update_property(&[Property::ValueText(v)]),
+      VAccessible::HasPopup(v) => accessible.update_property(&[Property::HasPopup(*v)]),
+      VAccessible::ReadOnly(v) => accessible.update_property(&[Property::ReadOnly(*v)]),
+      VAccessible::Required(v) => accessible.update_property(&[Property::Required(*v)]),
+      VAccessible::Level(v) => accessible.update_property(&[Property::Level(*v)]),
+      VAccessible::ValueMin(v) => accessible.update_property(&[Property::ValueMin(*v)]),
+      VAccessible::ValueMax(v) => accessible.update_property(&[Property::ValueMax(*v)]),
+      VAccessible::ValueNow(v) => accessible.update_property(&[Property::ValueNow(*v)]),
+      VAccessible::LabelledBy(names)
+      | VAccessible::DescribedBy(names)
+      | VAccessible::Controls(names) => {
+        let relation = self.clone();
+        let names = names.clone();
+        let accessible = accessible.downgrade();
+        // Related widgets may not have been added to the window yet.
+        glib::idle_add_local_once(move || {
+          if let Some(accessible) = accessible.upgrade() {
+            relate(&accessible, &relation, &names);
+          }
+        });
+      }
+      VAccessible::Busy(v) => accessible.update_state(&[State::Busy(*v)]),
+      VAccessible::Checked(v) => accessible.update_state(&[State::Checked(*v)]),
+      VAccessible::Disabled(v) => accessible.update_state(&[State::Disabled(*v)]),
+      VAccessible::Expanded(v) => accessible.update_state(&[State::Expanded(*v)]),
+      VAccessible::Hidden(v) => accessible.update_state(&[State::Hidden(*v)]),
+      VAccessible::Invalid(v) => accessible.update_state(&[State::Invalid(*v)]),
+      VAccessible::Pressed(v) => accessible.update_state(&[State::Pressed(*v)]),
+      VAccessible::Selected(v) => accessible.update_state(&[State::Selected(*v)]),
+    }
+  }
+
+  fn reset(&self, accessible: &Accessible) {
+    match self.kind() {
+      Kind::Property(property) => accessible.reset_property(property),
+      Kind::Relation(relation) => accessible.reset_relation(relation),
+      Kind::State(state) => accessible.reset_state(state),
+    }
+  }
+}
+
+fn relate(accessible: &Accessible, relation: &VAccessible, names: &[String]) {
+  let Some(root) = accessible
+    .downcast_ref::<Widget>()
+    .and_then(|widget| widget.root())
+  else {
+    return;
+  };
+  let related: Vec<Accessible> = names
+    .iter()
+    .filter_map(|name| find_by_name(root.upcast_ref::<Widget>(), name))
+    .map(|widget| widget.upcast())
+    .collect();
+  let related: Vec<&Accessible> = related.iter().collect();
+  match relation {
+    VAccessible::LabelledBy(_) => accessible.update_relation(&[Relation::LabelledBy(&related)]),
+    VAccessible::DescribedBy(_) => accessible.update_relation(&[Relation::DescribedBy(&related)]),
+    VAccessible::Controls(_) => accessible.update_relation(&[Relation::Controls(&related)]),
+    _ => unreachable!(),
+  }
+}
+
+fn find_by_name(widget: &Widget, name: &str) -> Option<Widget> {
+  if widget.widget_name() == name {
+    return Some(widget.clone());
+  }
+  let mut child = widget.first_child();
+  while let Some(current) = child {
+    if let Some(found) = find_by_name(&current, name) {
+      return Some(found);
+    }
+    child = current.next_sibling();
+  }
+  None
+}
+
+/// Apply the accessible attributes of a node, resetting those that were
+/// declared by the `previous` render and are gone.
+pub(crate) fn patch_accessible(object: &Object, previous: &[VAccessible], current: &[VAccessible]) {
+  let Some(accessible) = object.downcast_ref::<Accessible>() else {
+    if !current.is_empty() {
+      panic!("{} is not Accessible", object.type_());
+    }
+    return;
+  };
+
+  for attribute in previous {
+    if !current.iter().any(|a| a.same_kind(attribute)) {
+      attribute.reset(accessible);
+    }
+  }
+  for attribute in current {
+    attribute.apply(accessible);
+  }
+
+  #[allow(unsafe_code)]
+  unsafe {
+    if current.iter().any(VAccessible::names) {
+      object.set_data(NAMED_KEY, true);
+    } else {
+      let _ = object.steal_data::<bool>(NAMED_KEY);
+    }
+  }
+}
+
+fn has_text_child(widget: &Widget) -> bool {
+  let mut child = widget.first_child();
+  while let Some(current) = child {
+    if current
+      .downcast_ref::<Label>()
+      .is_some_and(|label| !label.label().is_empty())
+      || has_text_child(&current)
+    {
+      return true;
+    }
+    child = current.next_sibling();
+  }
+  false
+}
+
+fn is_interactive(widget: &Widget) -> bool {
+  // Toggle and link buttons are buttons too.
+  widget.is::<Button>()
+    || widget.is::<MenuButton>()
+    || widget.is::<CheckButton>()
+    || widget.is::<Switch>()
+    || widget.is::<Entry>()
+    || widget.is::<SearchEntry>()
+    || widget.is::<SpinButton>()
+    || widget.is::<Scale>()
+    || widget.is::<DropDown>()
+}
+
+fn has_name(widget: &Widget) -> bool {
+  #[allow(unsafe_code)]
+  let declared = unsafe { widget.data::<bool>(NAMED_KEY).is_some() };
+  declared
+    || widget
+      .downcast_ref::<Button>()
+      .and_then(|button| button.label())
+      .is_some_and(|label| !label.is_empty())
+    || widget
+      .downcast_ref::<MenuButton>()
+      .and_then(|button| button.label())
+      .is_some_and(|label| !label.is_empty())
+    || widget
+      .downcast_ref::<CheckButton>()
+      .and_then(|button| button.label())
+      .is_some_and(|label| !label.is_empty())
+    // Buttons are named after their content.
+    || ((widget.is::<Button>() || widget.is::<MenuButton>()) && has_text_child(widget))
+}
+
+/// Interactive widgets under `root`, itself included, that have no
+/// accessible name: no label of their own, and none declared with
+/// `VAccessible::Label` or `VAccessible::LabelledBy`. Meant for tests and
+/// debugging, e.g. asserting that it's empty once a window is rendered.
+pub fn unnamed_widgets(root: &Widget) -> Vec<Widget> {
+  let mut unnamed = Vec::new();
+  if is_interactive(root) && !has_name(root) {
+    unnamed.push(root.clone());
+  }
+  let mut child = root.first_child();
+  while let Some(current) = child {
+    unnamed.extend(unnamed_widgets(&current));
+    child = current.next_sibling();
+  }
+  unnamed
+}
@@ -0,0 +1,331 @@
+use std::rc::Rc;
+
+use adw::{
+  gio::{SimpleAction, SimpleActionGroup},
+  glib::{prelude::*, Object, SignalHandlerId, VariantTy, VariantType},
+};
+
+use crate::reactive::{component::Component, vnode::VNode};
+
+use super::vobject::{VObject, VObjectContext};
+
+const GROUP_NAME_KEY: &str = "rouge-action-group-name";
+
+/// Name under which an action group declared with
+/// [`VActionGroupBuilder::group`] is inserted in its parent widget.
+pub(crate) fn group_name(group: &SimpleActionGroup) -> String {
+  #[allow(unsafe_code)]
+  unsafe {
+    group
+      .data::<String>(GROUP_NAME_KEY)
+      .map(|name| name.as_ref().clone())
+      .expect("action group was not declared with VActionGroupBuilder::group()")
+  }
+}
+
+fn action_node<'a, C: Component, P>(
+  name: &str,
+  constructor: Box<dyn Fn() -> Object>,
+  patcher: P,
+) -> VNode<'a, C>
+where
+  P: 'a + Fn(&SimpleAction, &VObjectContext<C>) -> Vec<SignalHandlerId>,
+{
+  let wrapped_patcher = Box::new(move |obj: &Object, context: &VObjectContext<C>| {
+    let casted = obj.downcast_ref::<SimpleAction>().expect("Bad object.");
+    patcher(casted, context)
+  });
+
+  VNode::Object(VObject {
+    object_type: SimpleAction::static_type(),
+    constructor: Some(constructor),
+    patcher: wrapped_patcher,
+    children: vec![],
+    // Names are construct-only, so a renamed action has to be rebuilt.
+    key: Some(name.to_string()),
+    preserved_props: vec![],
+    animated: vec![],
+    transition: None,
+    classes: None,
+    accessible: vec![],
+    controllers: vec![],
+  })
+}
+
+/// Declare `gio::SimpleAction`s, to be added to an `Application`, an
+/// `ApplicationWindow` or an action group.
+///
+/// The patcher sets everything that derives from the component state, such
+/// as `enabled` or the accelerators (see `ReactiveActionExt`). The state of
+/// stateful actions is always the one passed to the builder: activating them
+/// only requests a change, delivered as a message.
+pub trait VActionBuilder<'a, C: Component> {
+  /// Declare an action without parameter.
+  fn action<P: 'a + Fn(&SimpleAction), MB: 'static + Fn() -> C::Message>(
+    name: &str,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C>;
+
+  /// Declare an action taking a parameter of type `T`.
+  fn action_with<
+    T: 'static + FromVariant + StaticVariantType,
+    P: 'a + Fn(&SimpleAction),
+    MB: 'static + Fn(T) -> C::Message,
+  >(
+    name: &str,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C>;
+
+  /// Declare an action with a state of type `T`. Boolean actions toggle
+  /// without parameter, other actions take the requested state as parameter,
+  /// like radio menu items.
+  fn stateful_action<
+    T: 'static + ToVariant + FromVariant + StaticVariantType,
+    P: 'a + Fn(&SimpleAction),
+    MB: 'static + Fn(T) -> C::Message,
+  >(
+    name: &str,
+    state: T,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C>;
+}
+
+impl<'a, C: 'static + Component> VActionBuilder<'a, C> for SimpleAction {
+  fn action<P: 'a + Fn(&SimpleAction), MB: 'static + Fn() -> C::Message>(
+    name: &str,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C> {
+    let owned_name = name.to_string();
+    let constructor = Box::new(move || SimpleAction::new(&owned_name, None).upcast::<Object>());
+
+    let message_builder = Rc::new(message_builder);
+    action_node(name, constructor, move |action, context| {
+      patcher(action);
+      let scope = context.scope();
+      let message_builder = message_builder.clone();
+      vec![action.connect_activate(move |_, _| {
+        scope.send_message(message_builder());
+      })]
+    })
+  }
+
+  fn action_with<
+    T: 'static + FromVariant + StaticVariantType,
+    P: 'a + Fn(&SimpleAction),
+    MB: 'static + Fn(T) -> C::Message,
+  >(
+    name: &str,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C> {
+    let owned_name = name.to_string();
+    let constructor = Box::new(move || {
+      SimpleAction::new(&owned_name, Some(&T::static_variant_type())).upcast::<Object>()
+    });
+
+    let message_builder = Rc::new(message_builder);
+    action_node(name, constructor, move |action, context| {
+      patcher(action);
+      let scope = context.scope();
+      let message_builder = message_builder.clone();
+      vec![action.connect_activate(move |_, parameter| {
+        if let Some(value) = parameter.and_then(T::from_variant) {
+          scope.send_message(message_builder(value));
+        }
+      })]
+    })
+  }
+
+  fn stateful_action<
+    T: 'static + ToVariant + FromVariant + StaticVariantType,
+    P: 'a + Fn(&SimpleAction),
+    MB: 'static + Fn(T) -> C::Message,
+  >(
+    name: &str,
+    state: T,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C> {
+    let state = state.to_variant();
+    let parameter_type: Option<VariantType> = if state.type_() == VariantTy::BOOLEAN {
+      None
+    } else {
+      Some(state.type_().to_owned())
+    };
+
+    let owned_name = name.to_string();
+    let initial_state = state.clone();
+    let constructor = Box::new(move || {
+      SimpleAction::new_stateful(&owned_name, parameter_type.as_deref(), &initial_state)
+        .upcast::<Object>()
+    });
+
+    let message_builder = Rc::new(message_builder);
+    action_node(name, constructor, move |action, context| {
+      action.set_state(&state);
+      patcher(action);
+      let scope = context.scope();
+      let message_builder = message_builder.clone();
+      // Handling `change-state` prevents the default handler from changing
+      // the state by itself.
+      vec![action.connect_change_state(move |_, requested| {
+        if let Some(value) = requested.and_then(T::from_variant) {
+          scope.send_message(message_builder(value));
+        }
+      })]
+    })
+  }
+}
+
+/// Declare a `gio::SimpleActionGroup`, inserted in its parent widget under
+/// `name`. Its children are the actions of the group, available to the
+/// widget and its descendants as `name.action`.
+pub trait VActionGroupBuilder<'a, C: Component> {
+  fn group(name: &str) -> VNode<'a, C>;
+}
+
+impl<'a, C: 'static + Component> VActionGroupBuilder<'a, C> for SimpleActionGroup {
+  fn group(name: &str) -> VNode<'a, C> {
+    let owned_name = name.to_string();
+    let constructor = Box::new(move || {
+      let group = SimpleActionGroup::new();
+      #[allow(unsafe_code)]
+      unsafe {
+        group.set_data(GROUP_NAME_KEY, owned_name.clone());
+      }
+      group.upcast::<Object>()
+    });
+
+    VNode::Object(VObject {
+      object_type: SimpleActionGroup::static_type(),
+      constructor: Some(constructor),
+      patcher: Box::new(|_: &Object, _: &VObjectContext<C>| vec![]),
+      children: vec![],
+      key: Some(name.to_string()),
+      preserved_props: vec![],
+      animated: vec![],
+      transition: None,
+      classes: None,
+      accessible: vec![],
+      controllers: vec![],
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use adw::gio::prelude::{ActionExt, ActionGroupExt, ActionMapExt, ApplicationExt};
+  use gtk4::prelude::GtkApplicationExt;
+
+  use super::*;
+  use crate::reactive::{
+    helpers::action_ext::ReactiveActionExt,
+    scope::Scope,
+    testing::{received, scope, with_gtk, Model},
+    vstate::VState,
+  };
+
+  /// The actions of the `test` group, `refresh` being enabled when `enabled`
+  /// and triggered by `accels`.
+  fn actions(enabled: bool, dark: bool, accels: &'static [&'static str]) -> VNode<'static, Model> {
+    SimpleActionGroup::group("test").children(vec![
+      SimpleAction::action(
+        "refresh",
+        move |a| {
+          a.set_enabled(enabled);
+          a.set_accels(accels);
+        },
+        || "refresh".to_string(),
+      ),
+      SimpleAction::action_with("open", |_| {}, |page: String| format!("open {}", page)),
+      SimpleAction::stateful_action("dark", dark, |_| {}, |dark: bool| format!("dark {}", dark)),
+    ])
+  }
+
+  fn build(view: VNode<Model>, scope: &Scope<Model>) -> (VState<Model>, SimpleActionGroup) {
+    let state = VState::build(&view, None, scope);
+    let group = state.object().clone().downcast().unwrap();
+    (state, group)
+  }
+
+  #[test]
+  fn activating_actions_sends_messages() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let (state, group) = build(actions(true, false, &[]), &scope);
+      group.activate_action("refresh", None);
+      group.activate_action("open", Some(&"updates".to_variant()));
+      // Boolean actions toggle without parameter.
+      group.activate_action("dark", None);
+      assert_eq!(
+        received(&mut messages),
+        vec!["refresh", "open updates", "dark true"]
+      );
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn actions_keep_the_declared_state() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let (mut state, group) = build(actions(true, false, &[]), &scope);
+      group.activate_action("dark", None);
+      assert_eq!(received(&mut messages), vec!["dark true"]);
+      // The change is only requested, the state of the component decides.
+      assert_eq!(group.action_state("dark"), Some(false.to_variant()));
+
+      assert!(state.patch(&actions(true, true, &[]), None, &scope));
+      assert_eq!(group.action_state("dark"), Some(true.to_variant()));
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn patches_actions_in_place() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let (mut state, group) = build(actions(true, false, &[]), &scope);
+      let refresh = group.lookup_action("refresh").unwrap();
+
+      assert!(state.patch(&actions(false, false, &[]), None, &scope));
+      assert_eq!(group.lookup_action("refresh"), Some(refresh.clone()));
+      assert!(!refresh.is_enabled());
+      group.activate_action("refresh", None);
+
+      // Patches don't stack handlers either.
+      assert!(state.patch(&actions(true, false, &[]), None, &scope));
+      group.activate_action("refresh", None);
+      assert_eq!(received(&mut messages), vec!["refresh"]);
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn registers_accelerators_with_the_application() {
+    with_gtk(|| {
+      let application = gtk4::Application::builder()
+        .application_id("dev.rouge.Software.Tests")
+        .build();
+      application.set_default();
+      let (scope, _messages) = scope();
+      let (mut state, _) = build(actions(true, false, &["<Control>r"]), &scope);
+      assert_eq!(
+        application.accels_for_action("test.refresh"),
+        ["<Control>r"]
+      );
+
+      assert!(state.patch(&actions(true, false, &["F5"]), None, &scope));
+      assert_eq!(application.accels_for_action("test.refresh"), ["F5"]);
+
+      // They go away along with the action.
+      assert!(state.patch(&SimpleActionGroup::group("test"), None, &scope));
+      assert!(application.accels_for_action("test.refresh").is_empty());
+      state.unmount();
+    });
+  }
+}
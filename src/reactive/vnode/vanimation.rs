@@ -0,0 +1,335 @@
+use adw::{
+  glib::{prelude::*, Object, Type, Value},
+  prelude::AnimationExt,
+  Animation, Easing, PropertyAnimationTarget, SpringAnimation, SpringParams, TimedAnimation,
+};
+use gtk4::{prelude::WidgetExt, Widget};
+
+/// How a property moves from a value to another, see [`VAnimated`].
+#[derive(Clone, Debug)]
+pub enum VAnimation {
+  Timed {
+    /// In milliseconds.
+    duration: u32,
+    easing: Easing,
+  },
+  Spring {
+    damping_ratio: f64,
+    mass: f64,
+    stiffness: f64,
+  },
+}
+
+impl VAnimation {
+  /// An `adw::TimedAnimation` of `duration` milliseconds, easing out.
+  pub fn timed(duration: u32) -> Self {
+    VAnimation::Timed {
+      duration,
+      easing: Easing::EaseOutCubic,
+    }
+  }
+
+  /// An `adw::SpringAnimation`, see `adw::SpringParams::new`.
+  pub fn spring(damping_ratio: f64, mass: f64, stiffness: f64) -> Self {
+    VAnimation::Spring {
+      damping_ratio,
+      mass,
+      stiffness,
+    }
+  }
+
+  /// Set the easing of a timed animation. Has no effect on springs.
+  pub fn easing(self, easing: Easing) -> Self {
+    match self {
+      VAnimation::Timed { duration, .. } => VAnimation::Timed { duration, easing },
+      other => other,
+    }
+  }
+
+  fn build(&self, widget: &Widget, property: &str, from: f64, to: f64) -> Animation {
+    let target = PropertyAnimationTarget::new(widget, property);
+    match *self {
+      VAnimation::Timed { duration, easing } => {
+        let animation = TimedAnimation::new(widget, from, to, duration, target);
+        animation.set_easing(easing);
+        animation.upcast()
+      }
+      VAnimation::Spring {
+        damping_ratio,
+        mass,
+        stiffness,
+      } => {
+        let params = SpringParams::new(damping_ratio, mass, stiffness);
+        SpringAnimation::new(widget, from, to, params, target).upcast()
+      }
+    }
+  }
+}
+
+/// A numeric property whose changes are animated rather than applied at
+/// once, see `VNode::animate`.
+#[derive(Clone, Debug)]
+pub struct VAnimated {
+  pub property: &'static str,
+  pub value: f64,
+  pub animation: VAnimation,
+}
+
+/// How a node appears when added by a patch, and disappears when removed:
+/// `property` goes from `hidden` to its value, and back. See
+/// `VNode::transition`.
+#[derive(Clone, Debug)]
+pub struct VTransition {
+  pub property: &'static str,
+  pub hidden: f64,
+  pub animation: VAnimation,
+}
+
+impl VTransition {
+  pub fn new(property: &'static str, hidden: f64, animation: VAnimation) -> Self {
+    VTransition {
+      property,
+      hidden,
+      animation,
+    }
+  }
+
+  /// Fade in and out.
+  pub fn fade(animation: VAnimation) -> Self {
+    VTransition::new("opacity", 0.0, animation)
+  }
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+  match value.type_() {
+    Type::F64 => value.get::<f64>().ok(),
+    Type::F32 => value.get::<f32>().ok().map(f64::from),
+    Type::I32 => value.get::<i32>().ok().map(f64::from),
+    Type::U32 => value.get::<u32>().ok().map(f64::from),
+    Type::I64 => value.get::<i64>().ok().map(|v| v as f64),
+    Type::U64 => value.get::<u64>().ok().map(|v| v as f64),
+    _ => None,
+  }
+}
+
+fn property(object: &Object, property: &str) -> f64 {
+  numeric(&object.property_value(property))
+    .unwrap_or_else(|| panic!("{}:{} is not a numeric property", object.type_(), property))
+}
+
+fn set_property(object: &Object, property: &str, value: f64) {
+  let value = match object.property_type(property) {
+    Some(Type::F32) => (value as f32).to_value(),
+    Some(Type::I32) => (value.round() as i32).to_value(),
+    Some(Type::U32) => (value.round() as u32).to_value(),
+    Some(Type::I64) => (value.round() as i64).to_value(),
+    Some(Type::U64) => (value.round() as u64).to_value(),
+    _ => value.to_value(),
+  };
+  object.set_property_from_value(property, &value);
+}
+
+fn widget(object: &Object) -> &Widget {
+  object.downcast_ref::<Widget>().unwrap_or_else(|| {
+    panic!(
+      "Only Widgets can be animated, but {} was found.",
+      object.type_()
+    )
+  })
+}
+
+fn animation_key(property: &str) -> String {
+  format!("rouge-animation-{}", property)
+}
+
+/// Play an animation of `property`, replacing the one running on it.
+fn play(object: &Object, property: &str, animation: Animation, to: f64) {
+  #[allow(unsafe_code)]
+  unsafe {
+    if let Some((running, _)) = object.steal_data::<(Animation, f64)>(&animation_key(property)) {
+      running.pause();
+    }
+    object.set_data(&animation_key(property), (animation.clone(), to));
+  }
+  animation.play();
+}
+
+/// The value an animation of `property` is heading to, if one is running.
+fn running_target(object: &Object, property: &str) -> Option<f64> {
+  #[allow(unsafe_code)]
+  unsafe {
+    object
+      .data::<(Animation, f64)>(&animation_key(property))
+      .map(|data| data.as_ref())
+      .filter(|(animation, _)| animation.state() == adw::AnimationState::Playing)
+      .map(|(_, to)| *to)
+  }
+}
+
+/// Apply animated properties: set them on a new object, tween them on a
+/// patched one.
+pub(crate) fn apply_animated(object: &Object, animated: &[VAnimated], initial: bool) {
+  for spec in animated {
+    if initial {
+      set_property(object, spec.property, spec.value);
+      continue;
+    }
+
+    let target = running_target(object, spec.property);
+    let current = property(object, spec.property);
+    if target == Some(spec.value) || (target.is_none() && current == spec.value) {
+      continue;
+    }
+    let animation = spec
+      .animation
+      .build(widget(object), spec.property, current, spec.value);
+    play(object, spec.property, animation, spec.value);
+  }
+}
+
+/// Bring a newly added object in.
+pub(crate) fn enter(object: &Object, transition: &VTransition) {
+  let to = property(object, transition.property);
+  set_property(object, transition.property, transition.hidden);
+  let animation =
+    transition
+      .animation
+      .build(widget(object), transition.property, transition.hidden, to);
+  play(object, transition.property, animation, to);
+}
+
+/// Take a removed object out, then call `done`.
+pub(crate) fn exit<F: 'static + FnOnce()>(object: &Object, transition: &VTransition, done: F) {
+  let target = transition.property;
+  let from = property(object, target);
+  let animation =
+    transition
+      .animation
+      .build(widget(object), transition.property, from, transition.hidden);
+
+  let done = std::cell::Cell::new(Some(done));
+  let weak = object.downgrade();
+  animation.connect_done(move |_| {
+    if let Some(done) = done.take() {
+      done();
+    }
+    // The animation is stored on the object and its handler holds the
+    // object through `done`; break the cycle.
+    if let Some(object) = weak.upgrade() {
+      #[allow(unsafe_code)]
+      unsafe {
+        let _ = object.steal_data::<(Animation, f64)>(&animation_key(target));
+      }
+    }
+  });
+  // Don't let the removed widget be hit while it goes.
+  widget(object).set_can_target(false);
+  play(object, transition.property, animation, transition.hidden);
+}
+
+#[cfg(test)]
+mod tests {
+  use gtk4::{prelude::*, Box, Label};
+
+  use super::*;
+  use crate::reactive::{
+    testing::{scope, with_gtk, Model},
+    vnode::{vobject::VObjectBuilder, VNode},
+    vstate::VState,
+  };
+
+  // Widgets in tests are never mapped, so their animations skip to the end
+  // as soon as they're played.
+
+  fn label(opacity: f64) -> VNode<'static, Model> {
+    Label::c(|w| w.set_label("Animated")).animate("opacity", opacity, VAnimation::timed(200))
+  }
+
+  fn list(labels: &[&'static str]) -> VNode<'static, Model> {
+    Box::cs().children(
+      labels
+        .iter()
+        .copied()
+        .map(|label| {
+          Label::c(move |w| w.set_label(label))
+            .transition(VTransition::fade(VAnimation::spring(1.0, 1.0, 100.0)))
+        })
+        .collect(),
+    )
+  }
+
+  fn labels(state: &VState<Model>) -> Vec<String> {
+    let mut labels = vec![];
+    let mut child = state.widget().unwrap().first_child();
+    while let Some(widget) = child {
+      labels.push(widget.downcast_ref::<Label>().unwrap().label().to_string());
+      child = widget.next_sibling();
+    }
+    labels
+  }
+
+  #[test]
+  fn sets_animated_properties_when_building() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let state = VState::build(&label(0.5), None, &scope);
+      let widget = state.widget().unwrap();
+      assert_eq!(widget.opacity(), 0.5);
+      assert_eq!(running_target(widget.upcast_ref(), "opacity"), None);
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn animates_properties_to_their_new_value() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let mut state = VState::build(&label(0.5), None, &scope);
+      assert!(state.patch(&label(0.25), None, &scope));
+      let widget = state.widget().unwrap().clone();
+      assert_eq!(widget.opacity(), 0.25);
+      // The patcher doesn't restore animated properties.
+      assert!(state.patch(&label(0.25), None, &scope));
+      assert_eq!(widget.opacity(), 0.25);
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn rounds_values_of_integer_properties() {
+    with_gtk(|| {
+      let widget = Label::new(None);
+      set_property(widget.upcast_ref(), "margin-start", 12.6);
+      assert_eq!(widget.margin_start(), 13);
+      assert_eq!(property(widget.upcast_ref(), "margin-start"), 13.0);
+    });
+  }
+
+  #[test]
+  fn brings_added_nodes_in() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let mut state = VState::build(&list(&["first"]), None, &scope);
+      assert!(state.patch(&list(&["first", "second"]), None, &scope));
+      assert_eq!(labels(&state), vec!["first", "second"]);
+      let added = state.widget().unwrap().last_child().unwrap();
+      assert_eq!(added.opacity(), 1.0);
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn removes_nodes_once_they_are_out() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let mut state = VState::build(&list(&["first", "second"]), None, &scope);
+      let removed = state.widget().unwrap().last_child().unwrap();
+      assert!(state.patch(&list(&["first"]), None, &scope));
+      assert_eq!(labels(&state), vec!["first"]);
+      assert!(removed.parent().is_none());
+      assert!(!removed.can_target());
+      assert_eq!(removed.opacity(), 0.0);
+      state.unmount();
+    });
+  }
+}
@@ -0,0 +1,278 @@
+use std::rc::Rc;
+
+use adw::glib::{
+  object::{Cast, IsA},
+  Object, SignalHandlerId,
+};
+use gtk4::{
+  prelude::{CheckButtonExt, EditableExt, RangeExt},
+  CheckButton, DropDown, Entry, Scale, SearchEntry, SpinButton, Switch,
+};
+
+use crate::reactive::{component::Component, vnode::VNode};
+
+use super::vobject::{VObject, VObjectContext};
+
+/// Widgets holding a value edited by the user, which can be controlled by
+/// the component state with [`VControlledBuilder`].
+pub trait Controlled: IsA<Object> {
+  type Value: 'static + Clone + PartialEq;
+
+  /// Properties holding the value. They aren't restored when patching, as
+  /// that would reset the value, and the cursor along with it.
+  const PROPERTIES: &'static [&'static str];
+
+  fn value(&self) -> Self::Value;
+  fn set_value(&self, value: &Self::Value);
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId;
+}
+
+fn set_text_keeping_position<E: IsA<gtk4::Editable>>(editable: &E, text: &str) {
+  let position = editable.position();
+  editable.set_text(text);
+  editable.set_position(position.min(text.chars().count() as i32));
+}
+
+impl Controlled for Entry {
+  type Value = String;
+  const PROPERTIES: &'static [&'static str] = &["text"];
+
+  fn value(&self) -> String {
+    self.text().to_string()
+  }
+
+  fn set_value(&self, value: &String) {
+    set_text_keeping_position(self, value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    self.connect_changed(f)
+  }
+}
+
+impl Controlled for SearchEntry {
+  type Value = String;
+  const PROPERTIES: &'static [&'static str] = &["text"];
+
+  fn value(&self) -> String {
+    self.text().to_string()
+  }
+
+  fn set_value(&self, value: &String) {
+    set_text_keeping_position(self, value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    self.connect_changed(f)
+  }
+}
+
+impl Controlled for SpinButton {
+  type Value = f64;
+  const PROPERTIES: &'static [&'static str] = &["value", "text"];
+
+  fn value(&self) -> f64 {
+    SpinButton::value(self)
+  }
+
+  fn set_value(&self, value: &f64) {
+    SpinButton::set_value(self, *value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    SpinButton::connect_value_changed(self, f)
+  }
+}
+
+impl Controlled for Switch {
+  type Value = bool;
+  const PROPERTIES: &'static [&'static str] = &["active", "state"];
+
+  fn value(&self) -> bool {
+    self.is_active()
+  }
+
+  fn set_value(&self, value: &bool) {
+    self.set_active(*value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    self.connect_active_notify(f)
+  }
+}
+
+impl Controlled for CheckButton {
+  type Value = bool;
+  const PROPERTIES: &'static [&'static str] = &["active"];
+
+  fn value(&self) -> bool {
+    self.is_active()
+  }
+
+  fn set_value(&self, value: &bool) {
+    self.set_active(*value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    self.connect_toggled(f)
+  }
+}
+
+impl Controlled for Scale {
+  type Value = f64;
+  // The value lives in the adjustment, which is kept as is.
+  const PROPERTIES: &'static [&'static str] = &[];
+
+  fn value(&self) -> f64 {
+    RangeExt::value(self)
+  }
+
+  fn set_value(&self, value: &f64) {
+    RangeExt::set_value(self, *value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    RangeExt::connect_value_changed(self, f)
+  }
+}
+
+impl Controlled for DropDown {
+  type Value = u32;
+  const PROPERTIES: &'static [&'static str] = &["selected"];
+
+  fn value(&self) -> u32 {
+    self.selected()
+  }
+
+  fn set_value(&self, value: &u32) {
+    self.set_selected(*value);
+  }
+
+  fn connect_value_changed<F: 'static + Fn(&Self)>(&self, f: F) -> SignalHandlerId {
+    self.connect_selected_notify(f)
+  }
+}
+
+/// Declare controlled inputs, whose value comes from the component state and
+/// whose edits are delivered as messages.
+///
+/// The value is only written to the widget when it differs from what the
+/// widget already holds, so that re-rendering after an edit leaves the
+/// cursor alone. Writes happen while the scope is muted, so they don't echo
+/// back as messages.
+pub trait VControlledBuilder<'a, W: Controlled, C: Component> {
+  fn controlled<P: 'a + Fn(&W), MB: 'static + Fn(W::Value) -> C::Message>(
+    value: W::Value,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C>;
+}
+
+impl<'a, W: Controlled, C: 'static + Component> VControlledBuilder<'a, W, C> for W {
+  fn controlled<P: 'a + Fn(&W), MB: 'static + Fn(W::Value) -> C::Message>(
+    value: W::Value,
+    patcher: P,
+    message_builder: MB,
+  ) -> VNode<'a, C> {
+    let message_builder = Rc::new(message_builder);
+    let wrapped_patcher = Box::new(move |obj: &Object, context: &VObjectContext<C>| {
+      let casted = obj.downcast_ref::<W>().expect("Bad object.");
+      if casted.value() != value {
+        casted.set_value(&value);
+      }
+      patcher(casted);
+
+      let scope = context.scope();
+      let message_builder = message_builder.clone();
+      vec![casted.connect_value_changed(move |w| {
+        scope.send_message(message_builder(w.value()));
+      })]
+    });
+
+    VNode::Object(VObject {
+      object_type: W::static_type(),
+      constructor: None,
+      patcher: wrapped_patcher,
+      children: vec![],
+      key: None,
+      preserved_props: W::PROPERTIES.to_vec(),
+      animated: vec![],
+      transition: None,
+      classes: None,
+      accessible: vec![],
+      controllers: vec![],
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use gtk4::prelude::EditableExt;
+
+  use super::*;
+  use crate::reactive::{
+    testing::{received, scope, with_gtk, Model},
+    vstate::VState,
+  };
+
+  fn entry(text: &str) -> VNode<'static, Model> {
+    Entry::controlled(text.to_string(), |_| {}, |text| format!("text {}", text))
+  }
+
+  fn switch(active: bool) -> VNode<'static, Model> {
+    Switch::controlled(active, |_| {}, |active| format!("active {}", active))
+  }
+
+  #[test]
+  fn sets_the_value_of_the_state() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let mut state = VState::build(&entry("Maps"), None, &scope);
+      let widget = state.object().clone().downcast::<Entry>().unwrap();
+      assert_eq!(widget.text(), "Maps");
+
+      assert!(state.patch(&entry("Weather"), None, &scope));
+      assert_eq!(widget.text(), "Weather");
+      // Values written by patches don't come back as messages.
+      assert!(received(&mut messages).is_empty());
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn sends_edits_as_messages() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let state = VState::build(&switch(false), None, &scope);
+      let widget = state.object().clone().downcast::<Switch>().unwrap();
+      widget.set_active(true);
+      assert_eq!(received(&mut messages), vec!["active true"]);
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn keeps_the_value_and_cursor_of_edits() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let mut state = VState::build(&entry("Map"), None, &scope);
+      let widget = state.object().clone().downcast::<Entry>().unwrap();
+      widget.set_text("Maps");
+      widget.set_position(2);
+      assert_eq!(received(&mut messages), vec!["text Maps"]);
+
+      // Render again once the state caught up with the edit.
+      assert!(state.patch(&entry("Maps"), None, &scope));
+      assert_eq!(widget.text(), "Maps");
+      assert_eq!(widget.position(), 2);
+      assert!(received(&mut messages).is_empty());
+
+      // The cursor stays within the text when the state shortens it.
+      widget.set_position(4);
+      assert!(state.patch(&entry("Ma"), None, &scope));
+      assert_eq!(widget.text(), "Ma");
+      assert_eq!(widget.position(), 2);
+      state.unmount();
+    });
+  }
+}
@@ -0,0 +1,303 @@
+use std::rc::Rc;
+
+use adw::glib::{prelude::*, value::FromValue, Object, Propagation, SignalHandlerId, Type, Value};
+use gtk4::{
+  gdk::{ContentProvider, DragAction, Key, ModifierType},
+  prelude::WidgetExt,
+  DragSource, DropTarget, EventController, EventControllerKey, EventControllerMotion,
+  EventControllerScroll, EventControllerScrollFlags, GestureClick, Widget,
+};
+
+use crate::reactive::component::Component;
+
+use super::vobject::VObjectContext;
+
+type Constructor<'a> = dyn 'a + Fn() -> EventController;
+type Configure<'a, C> = dyn 'a + Fn(&EventController, &VObjectContext<C>) -> Vec<SignalHandlerId>;
+
+/// An event controller attached to a node, see `VNode::controller`.
+///
+/// Controllers are diffed by position and type: when a patch declares a
+/// controller of the same type at the same position, the existing one is
+/// configured again rather than replaced, so that e.g. an ongoing drag
+/// isn't interrupted.
+pub struct VController<'a, C: Component> {
+  controller_type: Type,
+  constructor: Box<Constructor<'a>>,
+  configure: Box<Configure<'a, C>>,
+}
+
+impl<'a, C: 'static + Component> VController<'a, C> {
+  /// A controller built by `constructor`, configured like the patcher of
+  /// `VObjectBuilder::ce`: `configure` runs on every patch and returns the
+  /// handlers to disconnect before the next one.
+  pub fn new<E, F, P>(constructor: F, configure: P) -> Self
+  where
+    E: IsA<EventController>,
+    F: 'a + Fn() -> E,
+    P: 'a + Fn(&E, &VObjectContext<C>) -> Vec<SignalHandlerId>,
+  {
+    VController {
+      controller_type: E::static_type(),
+      constructor: Box::new(move || constructor().upcast()),
+      configure: Box::new(move |controller, context| {
+        let casted = controller.downcast_ref::<E>().expect("Bad controller.");
+        configure(casted, context)
+      }),
+    }
+  }
+
+  /// Send the message built from the number of presses and the position of
+  /// a click with the primary button.
+  pub fn click<MB: 'static + Fn(i32, f64, f64) -> C::Message>(message_builder: MB) -> Self {
+    let message_builder = Rc::new(message_builder);
+    VController::new(GestureClick::new, move |gesture, context| {
+      let message_builder = message_builder.clone();
+      let scope = context.scope();
+      vec![gesture.connect_pressed(move |_, n_press, x, y| {
+        scope.send_message(message_builder(n_press, x, y));
+      })]
+    })
+  }
+
+  /// Send the message built from a pressed key, if any, in which case the
+  /// key doesn't propagate further.
+  pub fn key<MB: 'static + Fn(Key, ModifierType) -> Option<C::Message>>(
+    message_builder: MB,
+  ) -> Self {
+    let message_builder = Rc::new(message_builder);
+    VController::new(EventControllerKey::new, move |controller, context| {
+      let message_builder = message_builder.clone();
+      let scope = context.scope();
+      vec![controller.connect_key_pressed(move |_, key, _, modifiers| {
+        match message_builder(key, modifiers) {
+          Some(message) => {
+            scope.send_message(message);
+            Propagation::Stop
+          }
+          None => Propagation::Proceed,
+        }
+      })]
+    })
+  }
+
+  /// Send the message built from the deltas of a scroll, if any, in which
+  /// case the scroll doesn't propagate further.
+  pub fn scroll<MB: 'static + Fn(f64, f64) -> Option<C::Message>>(
+    flags: EventControllerScrollFlags,
+    message_builder: MB,
+  ) -> Self {
+    let message_builder = Rc::new(message_builder);
+    VController::new(
+      move || EventControllerScroll::new(flags),
+      move |controller, context| {
+        controller.set_flags(flags);
+        let message_builder = message_builder.clone();
+        let scope = context.scope();
+        vec![
+          controller.connect_scroll(move |_, dx, dy| match message_builder(dx, dy) {
+            Some(message) => {
+              scope.send_message(message);
+              Propagation::Stop
+            }
+            None => Propagation::Proceed,
+          }),
+        ]
+      },
+    )
+  }
+
+  /// Send the message built with `true` when the pointer enters the widget,
+  /// and `false` when it leaves.
+  pub fn hover<MB: 'static + Fn(bool) -> C::Message>(message_builder: MB) -> Self {
+    let message_builder = Rc::new(message_builder);
+    VController::new(EventControllerMotion::new, move |controller, context| {
+      let on_enter = message_builder.clone();
+      let on_leave = message_builder.clone();
+      let enter_scope = context.scope();
+      let leave_scope = context.scope();
+      vec![
+        controller.connect_enter(move |_, _, _| enter_scope.send_message(on_enter(true))),
+        controller.connect_leave(move |_| leave_scope.send_message(on_leave(false))),
+      ]
+    })
+  }
+
+  /// Let the widget be dragged, carrying `value`.
+  pub fn drag_source<V: ToValue>(value: V, actions: DragAction) -> Self {
+    let value: Value = value.to_value();
+    VController::new(DragSource::new, move |source, _| {
+      source.set_actions(actions);
+      let value = value.clone();
+      vec![source.connect_prepare(move |_, _, _| Some(ContentProvider::for_value(&value)))]
+    })
+  }
+
+  /// Accept `T` values dropped on the widget, sending the message built from
+  /// the value and the position of the drop, if any.
+  pub fn drop_target<T, MB>(actions: DragAction, message_builder: MB) -> Self
+  where
+    T: 'static + StaticType + for<'v> FromValue<'v>,
+    MB: 'static + Fn(T, f64, f64) -> Option<C::Message>,
+  {
+    let message_builder = Rc::new(message_builder);
+    VController::new(
+      move || DropTarget::new(T::static_type(), actions),
+      move |target, context| {
+        target.set_actions(actions);
+        let message_builder = message_builder.clone();
+        let scope = context.scope();
+        vec![target.connect_drop(move |_, value, x, y| {
+          let Ok(value) = value.get::<T>() else {
+            return false;
+          };
+          match message_builder(value, x, y) {
+            Some(message) => {
+              scope.send_message(message);
+              true
+            }
+            None => false,
+          }
+        })]
+      },
+    )
+  }
+}
+
+/// A controller added to a widget by a node.
+pub(crate) struct ControllerState {
+  controller: EventController,
+  handlers: Vec<SignalHandlerId>,
+}
+
+/// Update the controllers of `object` from `previous` to `controllers`,
+/// keeping those of the same type at the same position.
+pub(crate) fn patch_controllers<C: 'static + Component>(
+  object: &Object,
+  previous: Vec<ControllerState>,
+  controllers: &[VController<C>],
+  context: &VObjectContext<C>,
+) -> Vec<ControllerState> {
+  if previous.is_empty() && controllers.is_empty() {
+    return previous;
+  }
+  let widget = object.downcast_ref::<Widget>().unwrap_or_else(|| {
+    panic!(
+      "Event controllers can only be added to Widgets, but {} was found.",
+      object.type_()
+    )
+  });
+
+  let mut previous = previous.into_iter();
+  let mut states = Vec::with_capacity(controllers.len());
+  for spec in controllers {
+    let controller = match previous.next() {
+      Some(state) if state.controller.type_() == spec.controller_type => {
+        for handler in state.handlers {
+          state.controller.disconnect(handler);
+        }
+        state.controller
+      }
+      stale => {
+        if let Some(state) = stale {
+          widget.remove_controller(&state.controller);
+        }
+        let controller = (spec.constructor)();
+        widget.add_controller(controller.clone());
+        controller
+      }
+    };
+    let handlers = (spec.configure)(&controller, context);
+    states.push(ControllerState {
+      controller,
+      handlers,
+    });
+  }
+  for state in previous {
+    widget.remove_controller(&state.controller);
+  }
+  states
+}
+
+#[cfg(test)]
+mod tests {
+  use gtk4::{gio::prelude::ListModelExt, Label};
+
+  use super::*;
+  use crate::reactive::testing::{received, scope, with_gtk, Model};
+
+  fn click(message: &'static str) -> VController<'static, Model> {
+    VController::click(move |n_press, _, _| format!("{} {}", message, n_press))
+  }
+
+  fn hover() -> VController<'static, Model> {
+    VController::hover(|inside| format!("hover {}", inside))
+  }
+
+  fn controllers(widget: &Label) -> Vec<EventController> {
+    let model = widget.observe_controllers();
+    (0..model.n_items())
+      .map(|index| model.item(index).unwrap().downcast().unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn keeps_controllers_of_the_same_type_at_the_same_position() {
+    with_gtk(|| {
+      let (scope, mut messages) = scope();
+      let context = VObjectContext::new(scope);
+      let widget = Label::new(None);
+      let object = widget.upcast_ref::<Object>();
+      let builtin = controllers(&widget).len();
+
+      let states = patch_controllers(object, vec![], &[click("first"), hover()], &context);
+      let added = controllers(&widget)[builtin..].to_vec();
+      assert_eq!(added.len(), 2);
+
+      let states = patch_controllers(object, states, &[click("second"), hover()], &context);
+      assert_eq!(controllers(&widget)[builtin..], added[..]);
+
+      // Only the handlers of the last patch are connected.
+      added[0].emit_by_name::<()>("pressed", &[&2i32, &0.0f64, &0.0f64]);
+      assert_eq!(received(&mut messages), vec!["second 2"]);
+      drop(states);
+    });
+  }
+
+  #[test]
+  fn replaces_controllers_whose_type_changed() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let context = VObjectContext::new(scope);
+      let widget = Label::new(None);
+      let object = widget.upcast_ref::<Object>();
+      let builtin = controllers(&widget).len();
+
+      let states = patch_controllers(object, vec![], &[click("click"), hover()], &context);
+      let hovering = controllers(&widget)[builtin + 1].clone();
+      patch_controllers(object, states, &[hover()], &context);
+      let remaining = controllers(&widget)[builtin..].to_vec();
+      assert_eq!(remaining.len(), 1);
+      assert!(remaining[0].is::<EventControllerMotion>());
+      assert_ne!(remaining[0], hovering);
+    });
+  }
+
+  #[test]
+  fn removes_controllers_left_out() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let context = VObjectContext::new(scope);
+      let widget = Label::new(None);
+      let object = widget.upcast_ref::<Object>();
+      let builtin = controllers(&widget).len();
+
+      let states = patch_controllers(object, vec![], &[click("click"), hover()], &context);
+      let clicking = controllers(&widget)[builtin].clone();
+      let states = patch_controllers(object, states, &[click("click")], &context);
+      assert_eq!(controllers(&widget)[builtin..], [clicking]);
+      assert!(patch_controllers(object, states, &[], &context).is_empty());
+      assert_eq!(controllers(&widget).len(), builtin);
+    });
+  }
+}
@@ -0,0 +1,498 @@
+use std::{
+  any::type_name,
+  cell::RefCell,
+  collections::{HashMap, HashSet},
+  rc::Rc,
+};
+
+use adw::{
+  gio::{prelude::ListModelExt, ListModel, ListStore},
+  glib::{prelude::*, BoxedAnyObject, Object, SignalHandlerId, Type},
+};
+use gtk4::{
+  prelude::ListItemExt, ColumnView, ColumnViewColumn, GridView, ListItem, ListView, NoSelection,
+  SignalListItemFactory, Widget,
+};
+
+use crate::reactive::{component::Component, scope::Scope, vnode::VNode, vstate::VState};
+
+use super::vobject::{VObject, VObjectContext};
+
+const LIST_BINDING_KEY: &str = "rouge-list-binding";
+
+type RowView<C, T> = Rc<dyn for<'r> Fn(&'r T) -> VNode<'r, C>>;
+type Patcher<'a, C> = Box<dyn 'a + Fn(&Object, &VObjectContext<C>) -> Vec<SignalHandlerId>>;
+
+/// Key of the item at `position` of a list declared with [`VListBuilder`] or
+/// [`VColumnViewBuilder`], e.g. to handle `activate`.
+pub fn item_key(model: &impl IsA<ListModel>, position: u32) -> Option<String> {
+  model.item(position).map(|item| key_of(&item))
+}
+
+fn key_of(item: &Object) -> String {
+  item
+    .downcast_ref::<BoxedAnyObject>()
+    .expect("list items must be declared with VListBuilder")
+    .borrow::<String>()
+    .clone()
+}
+
+/// Update `store` to hold `keys`, with as few and as large splices as
+/// possible: removed items are spliced out a run at a time, then new items
+/// are spliced in a run at a time, and moved items are moved one by one.
+fn sync_store(store: &ListStore, keys: &[String]) {
+  let wanted: HashSet<&str> = keys.iter().map(String::as_str).collect();
+  let mut current: Vec<String> = (0..store.n_items())
+    .filter_map(|position| store.item(position))
+    .map(|item| key_of(&item))
+    .collect();
+
+  // Removals, from the end so that positions stay valid.
+  let mut end = current.len();
+  while end > 0 {
+    if wanted.contains(current[end - 1].as_str()) {
+      end -= 1;
+      continue;
+    }
+    let mut start = end - 1;
+    while start > 0 && !wanted.contains(current[start - 1].as_str()) {
+      start -= 1;
+    }
+    store.splice(start as u32, (end - start) as u32, &[] as &[Object]);
+    current.drain(start..end);
+    end = start;
+  }
+
+  // Insertions and moves.
+  let present: HashSet<String> = current.iter().cloned().collect();
+  let mut position = 0;
+  while position < keys.len() {
+    if current.get(position) == Some(&keys[position]) {
+      position += 1;
+    } else if present.contains(&keys[position]) {
+      let from = position
+        + current[position..]
+          .iter()
+          .position(|key| *key == keys[position])
+          .expect("moved item is after the position");
+      let item = store.item(from as u32).unwrap();
+      store.remove(from as u32);
+      store.insert(position as u32, &item);
+      let key = current.remove(from);
+      current.insert(position, key);
+      position += 1;
+    } else {
+      let run = keys[position..]
+        .iter()
+        .take_while(|key| !present.contains(*key))
+        .cloned()
+        .collect::<Vec<_>>();
+      let items: Vec<BoxedAnyObject> = run.iter().cloned().map(BoxedAnyObject::new).collect();
+      store.splice(position as u32, 0, &items);
+      let start = position;
+      position += run.len();
+      current.splice(start..start, run);
+    }
+  }
+}
+
+/// A list item and the row rendered in it, kept across binds so that
+/// recycled items are patched rather than rebuilt.
+struct Row<C: Component> {
+  key: Option<String>,
+  state: Option<VState<C>>,
+}
+
+struct Column<C: Component, T> {
+  view_column: Option<ColumnViewColumn>,
+  row: RefCell<RowView<C, T>>,
+  rows: RefCell<HashMap<ListItem, Row<C>>>,
+}
+
+/// State shared by a list widget and its factories: the items, the store
+/// holding their keys, and the rows currently rendered.
+struct ListBinding<C: Component, T> {
+  store: ListStore,
+  items: RefCell<HashMap<String, T>>,
+  scope: RefCell<Scope<C>>,
+  columns: RefCell<Vec<Column<C, T>>>,
+}
+
+impl<C: 'static + Component, T: 'static + Clone + PartialEq> ListBinding<C, T> {
+  fn new(scope: Scope<C>) -> Rc<Self> {
+    Rc::new(ListBinding {
+      store: ListStore::new::<BoxedAnyObject>(),
+      items: Default::default(),
+      scope: RefCell::new(scope),
+      columns: Default::default(),
+    })
+  }
+
+  /// The binding attached to `object`, if any. The key of the list node
+  /// guarantees it has the right type.
+  fn get(object: &Object) -> Option<Rc<Self>> {
+    #[allow(unsafe_code)]
+    unsafe {
+      object
+        .data::<Rc<Self>>(LIST_BINDING_KEY)
+        .map(|binding| binding.as_ref().clone())
+    }
+  }
+
+  /// Attach the binding to `object`, replacing the previous one.
+  fn attach(self: &Rc<Self>, object: &Object) {
+    #[allow(unsafe_code)]
+    unsafe {
+      object.set_data(LIST_BINDING_KEY, self.clone());
+    }
+  }
+
+  /// Unmount all the rows, before the binding is replaced.
+  fn clear(&self) {
+    for column in self.columns.borrow().iter() {
+      let rows: Vec<_> = column.rows.borrow_mut().drain().collect();
+      for (list_item, row) in rows {
+        list_item.set_child(Option::<&Widget>::None);
+        if let Some(state) = row.state {
+          state.unmount();
+        }
+      }
+    }
+  }
+
+  fn titles(&self) -> Vec<String> {
+    self
+      .columns
+      .borrow()
+      .iter()
+      .filter_map(|column| column.view_column.as_ref())
+      .map(|column| {
+        column
+          .title()
+          .map(|title| title.to_string())
+          .unwrap_or_default()
+      })
+      .collect()
+  }
+
+  fn model(&self) -> NoSelection {
+    NoSelection::new(Some(self.store.clone()))
+  }
+
+  /// Add a column rendering rows with `row`, and return its factory.
+  fn add_column(
+    self: &Rc<Self>,
+    view_column: Option<ColumnViewColumn>,
+    row: RowView<C, T>,
+  ) -> SignalListItemFactory {
+    let index = self.columns.borrow().len();
+    self.columns.borrow_mut().push(Column {
+      view_column,
+      row: RefCell::new(row),
+      rows: Default::default(),
+    });
+
+    let factory = SignalListItemFactory::new();
+    let weak = Rc::downgrade(self);
+    factory.connect_bind(move |_, item| {
+      if let (Some(binding), Some(item)) = (weak.upgrade(), item.downcast_ref::<ListItem>()) {
+        binding.bind(index, item);
+      }
+    });
+    let weak = Rc::downgrade(self);
+    factory.connect_unbind(move |_, item| {
+      if let (Some(binding), Some(item)) = (weak.upgrade(), item.downcast_ref::<ListItem>())
+        && let Some(row) = binding.columns.borrow()[index]
+          .rows
+          .borrow_mut()
+          .get_mut(item)
+      {
+        row.key = None;
+      }
+    });
+    let weak = Rc::downgrade(self);
+    factory.connect_teardown(move |_, item| {
+      if let (Some(binding), Some(item)) = (weak.upgrade(), item.downcast_ref::<ListItem>()) {
+        let row = binding.columns.borrow()[index]
+          .rows
+          .borrow_mut()
+          .remove(item);
+        item.set_child(Option::<&Widget>::None);
+        if let Some(state) = row.and_then(|row| row.state) {
+          state.unmount();
+        }
+      }
+    });
+    factory
+  }
+
+  /// Render the row of `list_item`, patching the one it held if any.
+  fn bind(&self, index: usize, list_item: &ListItem) {
+    let Some(key) = list_item.item().map(|item| key_of(&item)) else {
+      return;
+    };
+    let Some(item) = self.items.borrow().get(&key).cloned() else {
+      return;
+    };
+    let row = self.columns.borrow()[index].row.borrow().clone();
+    let previous = self.columns.borrow()[index]
+      .rows
+      .borrow_mut()
+      .remove(list_item);
+    let scope = self.scope.borrow().clone();
+
+    let node = row(&item);
+    let state = {
+      let _muted = scope.muted();
+      render(previous.and_then(|row| row.state), &node, list_item, &scope)
+    };
+
+    self.columns.borrow()[index].rows.borrow_mut().insert(
+      list_item.clone(),
+      Row {
+        key: Some(key),
+        state: Some(state),
+      },
+    );
+  }
+
+  /// Replace the items, splice the store accordingly and re-render the
+  /// bound rows whose item changed.
+  fn update(&self, items: Vec<(String, T)>, scope: Scope<C>, rows: Vec<RowView<C, T>>) {
+    let keys: Vec<String> = items.iter().map(|(key, _)| key.clone()).collect();
+    let previous = self.items.replace(items.into_iter().collect());
+    *self.scope.borrow_mut() = scope;
+    for (column, row) in self.columns.borrow().iter().zip(rows) {
+      *column.row.borrow_mut() = row;
+    }
+
+    sync_store(&self.store, &keys);
+
+    // Rows only depend on their item, so unchanged items are left alone.
+    let changed: Vec<(usize, ListItem)> = {
+      let items = self.items.borrow();
+      let columns = self.columns.borrow();
+      columns
+        .iter()
+        .enumerate()
+        .flat_map(|(index, column)| {
+          let rows = column.rows.borrow();
+          rows
+            .iter()
+            .filter(|(_, row)| {
+              row
+                .key
+                .as_ref()
+                .is_some_and(|key| items.get(key).is_some() && items.get(key) != previous.get(key))
+            })
+            .map(|(list_item, _)| (index, list_item.clone()))
+            .collect::<Vec<_>>()
+        })
+        .collect()
+    };
+    for (index, list_item) in changed {
+      self.bind(index, &list_item);
+    }
+  }
+}
+
+fn render<C: 'static + Component>(
+  previous: Option<VState<C>>,
+  node: &VNode<C>,
+  list_item: &ListItem,
+  scope: &Scope<C>,
+) -> VState<C> {
+  if let Some(mut state) = previous {
+    let same_type = match (&state, node) {
+      (VState::Object(state), VNode::Object(spec)) => state.object.type_() == spec.object_type,
+      (VState::Component(state), VNode::Component(spec)) => state.model_type() == spec.model_type,
+      _ => false,
+    };
+    if same_type && state.patch(node, None, scope) {
+      return state;
+    }
+    list_item.set_child(Option::<&Widget>::None);
+    state.unmount();
+  }
+
+  let state = VState::build(node, None, scope);
+  let widget = state.widget().unwrap_or_else(|| {
+    panic!(
+      "List rows must be Widgets, but {} was found.",
+      state.object().type_()
+    )
+  });
+  list_item.set_child(Some(widget));
+  state
+}
+
+fn list_node<'a, C: 'static + Component, T: 'static>(
+  object_type: Type,
+  patcher: Patcher<'a, C>,
+  preserved_props: Vec<&'static str>,
+) -> VNode<'a, C> {
+  VNode::Object(VObject {
+    object_type,
+    constructor: None,
+    patcher,
+    children: vec![],
+    // The binding attached to the widget is typed by its items.
+    key: Some(type_name::<T>().to_string()),
+    preserved_props,
+    animated: vec![],
+    transition: None,
+    classes: None,
+    accessible: vec![],
+    controllers: vec![],
+  })
+}
+
+/// Widgets showing a list model through a single factory.
+pub trait ListWidget: IsA<Object> + IsA<Widget> {
+  fn set_list_model(&self, model: &NoSelection);
+  fn set_list_factory(&self, factory: &SignalListItemFactory);
+}
+
+impl ListWidget for ListView {
+  fn set_list_model(&self, model: &NoSelection) {
+    self.set_model(Some(model));
+  }
+
+  fn set_list_factory(&self, factory: &SignalListItemFactory) {
+    self.set_factory(Some(factory));
+  }
+}
+
+impl ListWidget for GridView {
+  fn set_list_model(&self, model: &NoSelection) {
+    self.set_model(Some(model));
+  }
+
+  fn set_list_factory(&self, factory: &SignalListItemFactory) {
+    self.set_factory(Some(factory));
+  }
+}
+
+/// Declare a virtualized `ListView` or `GridView` over keyed items.
+///
+/// The items are kept in a `gio::ListStore` of their keys, which is spliced
+/// to follow the items on every render. Rows are rendered with `row` when
+/// GTK binds them, and patched when the item they show changes. Keys must
+/// be unique, and rows must only depend on their item.
+pub trait VListBuilder<'a, W: ListWidget, C: Component> {
+  fn list<T, P, R>(items: Vec<(String, T)>, patcher: P, row: R) -> VNode<'a, C>
+  where
+    T: 'static + Clone + PartialEq,
+    P: 'a + Fn(&W, &VObjectContext<C>) -> Vec<SignalHandlerId>,
+    R: 'static + for<'r> Fn(&'r T) -> VNode<'r, C>;
+}
+
+impl<'a, W: ListWidget, C: 'static + Component> VListBuilder<'a, W, C> for W {
+  fn list<T, P, R>(items: Vec<(String, T)>, patcher: P, row: R) -> VNode<'a, C>
+  where
+    T: 'static + Clone + PartialEq,
+    P: 'a + Fn(&W, &VObjectContext<C>) -> Vec<SignalHandlerId>,
+    R: 'static + for<'r> Fn(&'r T) -> VNode<'r, C>,
+  {
+    let row: RowView<C, T> = Rc::new(row);
+    let wrapped_patcher = Box::new(move |obj: &Object, context: &VObjectContext<C>| {
+      let casted = obj.downcast_ref::<W>().expect("Bad object.");
+      let binding = ListBinding::get(obj).unwrap_or_else(|| {
+        let binding = ListBinding::new(context.scope());
+        binding.attach(obj);
+        casted.set_list_factory(&binding.add_column(None, row.clone()));
+        casted.set_list_model(&binding.model());
+        binding
+      });
+      binding.update(items.clone(), context.scope(), vec![row.clone()]);
+      patcher(casted, context)
+    });
+
+    list_node::<C, T>(W::static_type(), wrapped_patcher, vec!["model", "factory"])
+  }
+}
+
+/// A column of a [`VColumnViewBuilder::columns`] view.
+pub struct VColumn<C: Component, T> {
+  title: String,
+  expand: bool,
+  row: RowView<C, T>,
+}
+
+impl<C: Component, T> VColumn<C, T> {
+  pub fn new<R: 'static + for<'r> Fn(&'r T) -> VNode<'r, C>>(title: &str, row: R) -> Self {
+    VColumn {
+      title: title.to_string(),
+      expand: false,
+      row: Rc::new(row),
+    }
+  }
+
+  /// Let the column take the extra space of the view.
+  pub fn expand(self, expand: bool) -> Self {
+    VColumn { expand, ..self }
+  }
+}
+
+/// Declare a virtualized `ColumnView` over keyed items, see
+/// [`VListBuilder`]. Columns are rebuilt when their titles change.
+pub trait VColumnViewBuilder<'a, C: Component> {
+  fn columns<T, P>(
+    items: Vec<(String, T)>,
+    columns: Vec<VColumn<C, T>>,
+    patcher: P,
+  ) -> VNode<'a, C>
+  where
+    T: 'static + Clone + PartialEq,
+    P: 'a + Fn(&ColumnView, &VObjectContext<C>) -> Vec<SignalHandlerId>;
+}
+
+impl<'a, C: 'static + Component> VColumnViewBuilder<'a, C> for ColumnView {
+  fn columns<T, P>(items: Vec<(String, T)>, columns: Vec<VColumn<C, T>>, patcher: P) -> VNode<'a, C>
+  where
+    T: 'static + Clone + PartialEq,
+    P: 'a + Fn(&ColumnView, &VObjectContext<C>) -> Vec<SignalHandlerId>,
+  {
+    let wrapped_patcher = Box::new(move |obj: &Object, context: &VObjectContext<C>| {
+      let view = obj.downcast_ref::<ColumnView>().expect("Bad object.");
+      let titles: Vec<String> = columns.iter().map(|column| column.title.clone()).collect();
+      let binding = match ListBinding::<C, T>::get(obj) {
+        Some(binding) if binding.titles() == titles => binding,
+        previous => {
+          // Start over with new columns, tearing the old rows down.
+          if let Some(previous) = previous {
+            for column in previous.columns.borrow().iter() {
+              if let Some(ref view_column) = column.view_column {
+                view.remove_column(view_column);
+              }
+            }
+            previous.clear();
+          }
+
+          let binding = ListBinding::new(context.scope());
+          binding.attach(obj);
+          for column in &columns {
+            let view_column =
+              ColumnViewColumn::new(Some(&column.title), None::<SignalListItemFactory>);
+            let factory = binding.add_column(Some(view_column.clone()), column.row.clone());
+            view_column.set_factory(Some(&factory));
+            view.append_column(&view_column);
+          }
+          view.set_model(Some(&binding.model()));
+          binding
+        }
+      };
+
+      for (column, spec) in binding.columns.borrow().iter().zip(&columns) {
+        if let Some(ref view_column) = column.view_column {
+          view_column.set_expand(spec.expand);
+        }
+      }
+      let rows = columns.iter().map(|column| column.row.clone()).collect();
+      binding.update(items.clone(), context.scope(), rows);
+      patcher(view, context)
+    });
+
+    list_node::<C, T>(ColumnView::static_type(), wrapped_patcher, vec!["model"])
+  }
+}
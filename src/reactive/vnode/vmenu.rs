@@ -0,0 +1,420 @@
+use adw::{
+  gio::{
+    prelude::{IconExt, MenuModelExt},
+    Menu, MenuItem, ThemedIcon,
+  },
+  glib::{prelude::*, Object, Variant},
+  Application,
+};
+use gtk4::{
+  gdk::{self, Rectangle},
+  prelude::{GestureExt, GestureSingleExt, GtkApplicationExt, PopoverExt, WidgetExt},
+  Align, EventSequenceState, GestureClick, MenuButton, PopoverMenu, PopoverMenuBar, Widget,
+};
+
+use crate::reactive::{component::Component, vnode::VNode};
+
+use super::vobject::{VObject, VObjectContext};
+
+const CONTEXT_MENU_KEY: &str = "rouge-context-menu";
+
+/// An entry of a declarative menu, see [`VMenuBuilder::menu`].
+#[derive(Clone, Debug)]
+pub enum VMenuItem {
+  Item {
+    label: String,
+    action: Option<String>,
+    target: Option<Variant>,
+    icon: Option<String>,
+  },
+  Section {
+    label: Option<String>,
+    items: Vec<VMenuItem>,
+  },
+  Submenu {
+    label: String,
+    items: Vec<VMenuItem>,
+  },
+}
+
+impl VMenuItem {
+  /// An item activating `action`, e.g. `app.quit`.
+  pub fn item(label: &str, action: &str) -> Self {
+    VMenuItem::Item {
+      label: label.to_string(),
+      action: Some(action.to_string()),
+      target: None,
+      icon: None,
+    }
+  }
+
+  /// An item activating `action` with `target` as parameter. For stateful
+  /// actions, the item is shown as a radio item.
+  pub fn item_with<T: ToVariant>(label: &str, action: &str, target: T) -> Self {
+    VMenuItem::Item {
+      label: label.to_string(),
+      action: Some(action.to_string()),
+      target: Some(target.to_variant()),
+      icon: None,
+    }
+  }
+
+  pub fn section(items: Vec<VMenuItem>) -> Self {
+    VMenuItem::Section { label: None, items }
+  }
+
+  pub fn labelled_section(label: &str, items: Vec<VMenuItem>) -> Self {
+    VMenuItem::Section {
+      label: Some(label.to_string()),
+      items,
+    }
+  }
+
+  pub fn submenu(label: &str, items: Vec<VMenuItem>) -> Self {
+    VMenuItem::Submenu {
+      label: label.to_string(),
+      items,
+    }
+  }
+
+  /// Set the icon of an item. Has no effect on sections and submenus.
+  pub fn icon(self, icon_name: &str) -> Self {
+    match self {
+      VMenuItem::Item {
+        label,
+        action,
+        target,
+        ..
+      } => VMenuItem::Item {
+        label,
+        action,
+        target,
+        icon: Some(icon_name.to_string()),
+      },
+      other => other,
+    }
+  }
+
+  fn build(&self) -> MenuItem {
+    match self {
+      VMenuItem::Item {
+        label,
+        action,
+        target,
+        icon,
+      } => {
+        let item = MenuItem::new(Some(label), None);
+        item.set_action_and_target_value(action.as_deref(), target.as_ref());
+        if let Some(icon) = icon {
+          item.set_icon(&ThemedIcon::new(icon));
+        }
+        item
+      }
+      VMenuItem::Section { label, items } => {
+        let section = Menu::new();
+        patch_menu(&section, items);
+        MenuItem::new_section(label.as_deref(), &section)
+      }
+      VMenuItem::Submenu { label, items } => {
+        let submenu = Menu::new();
+        patch_menu(&submenu, items);
+        MenuItem::new_submenu(Some(label), &submenu)
+      }
+    }
+  }
+
+  /// Whether the item at `index` of `menu` was built from an item like this
+  /// one, links aside.
+  fn matches(&self, menu: &Menu, index: i32) -> bool {
+    let attribute = |name: &str| menu.item_attribute_value(index, name, None);
+    let string = |value: &str| Some(value.to_variant());
+    match self {
+      VMenuItem::Item {
+        label,
+        action,
+        target,
+        icon,
+      } => {
+        menu.item_link(index, "section").is_none()
+          && menu.item_link(index, "submenu").is_none()
+          && attribute("label") == string(label)
+          && attribute("action") == action.as_deref().and_then(string)
+          && attribute("target") == *target
+          && attribute("icon")
+            == icon
+              .as_ref()
+              .and_then(|icon| ThemedIcon::new(icon).serialize())
+      }
+      VMenuItem::Section { label, .. } => {
+        menu.item_link(index, "section").is_some()
+          && attribute("label") == label.as_deref().and_then(string)
+      }
+      VMenuItem::Submenu { label, .. } => {
+        menu.item_link(index, "submenu").is_some() && attribute("label") == string(label)
+      }
+    }
+  }
+
+  fn items(&self) -> Option<(&'static str, &[VMenuItem])> {
+    match self {
+      VMenuItem::Item { .. } => None,
+      VMenuItem::Section { items, .. } => Some(("section", items)),
+      VMenuItem::Submenu { items, .. } => Some(("submenu", items)),
+    }
+  }
+}
+
+/// Update `menu` in place to match `items`, so that open menus and
+/// popovers built from it are updated rather than rebuilt.
+pub fn patch_menu(menu: &Menu, items: &[VMenuItem]) {
+  for (index, item) in items.iter().enumerate() {
+    let index = index as i32;
+    if index < menu.n_items() && item.matches(menu, index) {
+      // Same item; patch its section or submenu, if any.
+      if let Some((link, items)) = item.items() {
+        if let Some(link) = menu.item_link(index, link).and_downcast::<Menu>() {
+          patch_menu(&link, items);
+          continue;
+        }
+      } else {
+        continue;
+      }
+    }
+
+    if index < menu.n_items() {
+      menu.remove(index);
+    }
+    menu.insert_item(index, &item.build());
+  }
+
+  while menu.n_items() > items.len() as i32 {
+    menu.remove(items.len() as i32);
+  }
+}
+
+/// Declare a `gio::Menu` from its items.
+///
+/// It is attached to its parent: as the menu model of a `MenuButton`, a
+/// `PopoverMenu` or a `PopoverMenuBar`, as the menubar of an `Application`,
+/// or as a context menu opened with a right click for any other widget.
+pub trait VMenuBuilder<'a, C: Component> {
+  fn menu(items: Vec<VMenuItem>) -> VNode<'a, C>;
+}
+
+impl<'a, C: 'static + Component> VMenuBuilder<'a, C> for Menu {
+  fn menu(items: Vec<VMenuItem>) -> VNode<'a, C> {
+    let patcher = Box::new(move |obj: &Object, _: &VObjectContext<C>| {
+      let menu = obj.downcast_ref::<Menu>().expect("Bad object.");
+      patch_menu(menu, &items);
+      vec![]
+    });
+
+    VNode::Object(VObject {
+      object_type: Menu::static_type(),
+      constructor: Some(Box::new(|| Menu::new().upcast::<Object>())),
+      patcher,
+      children: vec![],
+      key: None,
+      preserved_props: vec![],
+      animated: vec![],
+      transition: None,
+      classes: None,
+      accessible: vec![],
+      controllers: vec![],
+    })
+  }
+}
+
+/// Attach `menu` to `parent`, see [`VMenuBuilder`].
+pub(crate) fn attach_menu(parent: &Object, menu: &Menu) {
+  if let Some(button) = parent.downcast_ref::<MenuButton>() {
+    button.set_menu_model(Some(menu));
+  } else if let Some(popover) = parent.downcast_ref::<PopoverMenu>() {
+    popover.set_menu_model(Some(menu));
+  } else if let Some(bar) = parent.downcast_ref::<PopoverMenuBar>() {
+    bar.set_menu_model(Some(menu));
+  } else if let Some(application) = parent.downcast_ref::<Application>() {
+    application.set_menubar(Some(menu));
+  } else if let Some(widget) = parent.downcast_ref::<Widget>() {
+    let popover = PopoverMenu::from_model(Some(menu));
+    popover.set_has_arrow(false);
+    popover.set_halign(Align::Start);
+    popover.set_parent(widget);
+
+    let gesture = GestureClick::new();
+    gesture.set_button(gdk::BUTTON_SECONDARY);
+    let const_popover = popover.clone();
+    gesture.connect_pressed(move |gesture, _, x, y| {
+      gesture.set_state(EventSequenceState::Claimed);
+      const_popover.set_pointing_to(Some(&Rectangle::new(x as i32, y as i32, 1, 1)));
+      const_popover.popup();
+    });
+    widget.add_controller(gesture.clone());
+
+    #[allow(unsafe_code)]
+    unsafe {
+      menu.set_data(CONTEXT_MENU_KEY, (popover, gesture));
+    }
+  } else {
+    panic!("Don't know how to attach a menu to a {}", parent.type_());
+  }
+}
+
+/// Detach `menu` from `parent`, undoing [`attach_menu`].
+pub(crate) fn detach_menu(parent: &Object, menu: &Menu) {
+  if let Some(button) = parent.downcast_ref::<MenuButton>() {
+    button.set_menu_model(Option::<&Menu>::None);
+  } else if let Some(popover) = parent.downcast_ref::<PopoverMenu>() {
+    popover.set_menu_model(Option::<&Menu>::None);
+  } else if let Some(bar) = parent.downcast_ref::<PopoverMenuBar>() {
+    bar.set_menu_model(Option::<&Menu>::None);
+  } else if let Some(application) = parent.downcast_ref::<Application>() {
+    application.set_menubar(Option::<&Menu>::None);
+  } else if let Some(widget) = parent.downcast_ref::<Widget>() {
+    #[allow(unsafe_code)]
+    let context_menu = unsafe { menu.steal_data::<(PopoverMenu, GestureClick)>(CONTEXT_MENU_KEY) };
+    if let Some((popover, gesture)) = context_menu {
+      widget.remove_controller(&gesture);
+      popover.unparent();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{cell::RefCell, rc::Rc};
+
+  use gtk4::Label;
+
+  use super::*;
+  use crate::reactive::{
+    testing::{scope, with_gtk, Model},
+    vnode::vobject::VObjectBuilder,
+    vstate::VState,
+  };
+
+  fn label(menu: &Menu, index: i32) -> Option<String> {
+    menu
+      .item_attribute_value(index, "label", None)
+      .and_then(|label| label.get())
+  }
+
+  fn section(menu: &Menu, index: i32) -> Menu {
+    menu
+      .item_link(index, "section")
+      .and_downcast()
+      .expect("not a section")
+  }
+
+  /// The `items-changed` emitted by `menu`, as `(position, removed, added)`.
+  fn changes(menu: &Menu) -> Rc<RefCell<Vec<(i32, i32, i32)>>> {
+    let changes = Rc::new(RefCell::new(vec![]));
+    let const_changes = changes.clone();
+    menu.connect_items_changed(move |_, position, removed, added| {
+      const_changes.borrow_mut().push((position, removed, added));
+    });
+    changes
+  }
+
+  fn file_menu(save_label: &str) -> Vec<VMenuItem> {
+    vec![
+      VMenuItem::item("Open", "app.open"),
+      VMenuItem::section(vec![
+        VMenuItem::item(save_label, "app.save"),
+        VMenuItem::item_with("Sort by Name", "app.sort", "name"),
+      ]),
+      VMenuItem::submenu("Recent", vec![]),
+    ]
+  }
+
+  #[test]
+  fn builds_menus() {
+    let menu = Menu::new();
+    patch_menu(&menu, &file_menu("Save"));
+
+    assert_eq!(menu.n_items(), 3);
+    assert_eq!(label(&menu, 0).as_deref(), Some("Open"));
+    assert_eq!(
+      menu.item_attribute_value(0, "action", None),
+      Some("app.open".to_variant())
+    );
+    let section = section(&menu, 1);
+    assert_eq!(label(&section, 0).as_deref(), Some("Save"));
+    assert_eq!(
+      section.item_attribute_value(1, "target", None),
+      Some("name".to_variant())
+    );
+    assert!(menu.item_link(2, "submenu").is_some());
+  }
+
+  #[test]
+  fn patches_only_the_items_which_changed() {
+    let menu = Menu::new();
+    patch_menu(&menu, &file_menu("Save"));
+    let saves = section(&menu, 1);
+    let menu_changes = changes(&menu);
+    let section_changes = changes(&saves);
+
+    patch_menu(&menu, &file_menu("Save As…"));
+    // The section is kept, and only its first item is replaced.
+    assert_eq!(section(&menu, 1), saves);
+    assert_eq!(label(&saves, 0).as_deref(), Some("Save As…"));
+    assert!(menu_changes.borrow().is_empty());
+    assert_eq!(*section_changes.borrow(), vec![(0, 1, 0), (0, 0, 1)]);
+
+    patch_menu(&menu, &file_menu("Save As…"));
+    assert!(menu_changes.borrow().is_empty());
+    assert_eq!(section_changes.borrow().len(), 2);
+  }
+
+  #[test]
+  fn removes_items_left_out() {
+    let menu = Menu::new();
+    patch_menu(&menu, &file_menu("Save"));
+    let menu_changes = changes(&menu);
+
+    patch_menu(&menu, &[VMenuItem::item("Open", "app.open")]);
+    assert_eq!(menu.n_items(), 1);
+    assert_eq!(*menu_changes.borrow(), vec![(1, 1, 0), (1, 1, 0)]);
+
+    // Replacing an item by a section rebuilds it.
+    patch_menu(&menu, &[VMenuItem::section(vec![])]);
+    assert_eq!(menu.n_items(), 1);
+    assert!(menu.item_link(0, "section").is_some());
+  }
+
+  #[test]
+  fn attaches_menus_to_their_parent() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let button = |menu: bool| {
+        let children = match menu {
+          true => vec![Menu::menu(file_menu("Save"))],
+          false => vec![],
+        };
+        MenuButton::cs().children(children)
+      };
+      let mut state = VState::<Model>::build(&button(true), None, &scope);
+      let button_widget = state.object().clone().downcast::<MenuButton>().unwrap();
+      let model = button_widget.menu_model().and_downcast::<Menu>().unwrap();
+      assert_eq!(model.n_items(), 3);
+
+      assert!(state.patch(&button(false), None, &scope));
+      assert!(button_widget.menu_model().is_none());
+      state.unmount();
+
+      // Other widgets get a context menu.
+      let state = VState::<Model>::build(
+        &Label::cs().children(vec![Menu::menu(file_menu("Save"))]),
+        None,
+        &scope,
+      );
+      let label = state.widget().unwrap().clone();
+      assert!(label
+        .first_child()
+        .is_some_and(|child| child.is::<PopoverMenu>()));
+      state.unmount();
+      assert!(label.first_child().is_none());
+    });
+  }
+}
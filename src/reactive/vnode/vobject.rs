@@ -1,10 +1,18 @@
 use adw::glib::{
   object::{Cast, IsA},
-  Object, SignalHandlerId, Type,
+  Object, Propagation, SignalHandlerId, Type,
 };
 
+use gtk4::AccessibleRole;
+
 use crate::reactive::{component::Component, scope::Scope, vnode::VNode};
 
+use super::{
+  vaccessible::VAccessible,
+  vanimation::{VAnimated, VAnimation, VTransition},
+  vcontroller::VController,
+};
+
 pub struct VObjectContext<C: Component> {
   scope: Scope<C>,
 }
@@ -16,6 +24,10 @@ impl<C: Component> VObjectContext<C> {
 }
 
 impl<C: 'static + Component> VObjectContext<C> {
+  pub fn scope(&self) -> Scope<C> {
+    self.scope.clone()
+  }
+
   pub fn d<W: IsA<Object>, MB: 'static + Fn(&W) -> C::Message>(
     &self,
     message_builder: MB,
@@ -26,6 +38,34 @@ impl<C: 'static + Component> VObjectContext<C> {
       scope_clone.send_message(message);
     }
   }
+
+  /// Same as [`VObjectContext::d`], for signals carrying a value, such as
+  /// the response of an `AlertDialog`.
+  pub fn dv<W: IsA<Object>, V: ?Sized, MB: 'static + Fn(&W, &V) -> C::Message>(
+    &self,
+    message_builder: MB,
+  ) -> impl 'static + Fn(&W, &V) {
+    let scope_clone = self.scope.clone();
+    move |o, v| {
+      let message = message_builder(o, v);
+      scope_clone.send_message(message);
+    }
+  }
+
+  /// Same as [`VObjectContext::d`], for signals expecting a `Propagation`,
+  /// such as `close-request`. The default handler is always stopped, so that
+  /// the state stays in charge of whether the window is closed or not.
+  pub fn dp<W: IsA<Object>, MB: 'static + Fn(&W) -> C::Message>(
+    &self,
+    message_builder: MB,
+  ) -> impl 'static + Fn(&W) -> Propagation {
+    let scope_clone = self.scope.clone();
+    move |o| {
+      let message = message_builder(o);
+      scope_clone.send_message(message);
+      Propagation::Stop
+    }
+  }
 }
 
 pub struct VObject<'a, C: Component> {
@@ -33,6 +73,23 @@ pub struct VObject<'a, C: Component> {
   pub constructor: Option<Box<dyn Fn() -> Object>>,
   pub patcher: Box<dyn 'a + Fn(&Object, &VObjectContext<C>) -> Vec<SignalHandlerId>>,
   pub children: Vec<VNode<'a, C>>,
+  /// Identity of the object among objects of the same type. When it changes,
+  /// the object is rebuilt instead of patched.
+  pub key: Option<String>,
+  /// Properties owned by the user rather than the view, which are not
+  /// restored to their initial value when patching.
+  pub preserved_props: Vec<&'static str>,
+  /// Properties tweened to their value when patching.
+  pub animated: Vec<VAnimated>,
+  /// How the object comes in and goes out when added or removed by a patch.
+  pub transition: Option<VTransition>,
+  /// CSS classes of the widget, diffed with the previous ones when patching.
+  pub classes: Option<Vec<String>>,
+  /// Accessible properties, relations and states of the widget.
+  pub accessible: Vec<VAccessible>,
+  /// Event controllers of the widget, diffed with the previous ones when
+  /// patching.
+  pub controllers: Vec<VController<'a, C>>,
   // pub props: Vec<VProperty>,
   // pub handlers: Vec<VHandler<Model>>,
 }
@@ -41,6 +98,70 @@ impl<'a, C: Component> VObject<'a, C> {
   pub fn children(self, children: Vec<VNode<'a, C>>) -> VNode<'a, C> {
     VNode::Object(Self { children, ..self })
   }
+
+  pub fn animate(
+    mut self,
+    property: &'static str,
+    value: f64,
+    animation: VAnimation,
+  ) -> VNode<'a, C> {
+    // The value is owned by the animation from now on.
+    self.preserved_props.push(property);
+    self.animated.push(VAnimated {
+      property,
+      value,
+      animation,
+    });
+    VNode::Object(self)
+  }
+
+  pub fn classes(mut self, classes: &[&str]) -> VNode<'a, C> {
+    // Restoring the classes would undo the diff.
+    self.preserved_props.push("css-classes");
+    self.classes = Some(classes.iter().map(|class| class.to_string()).collect());
+    VNode::Object(self)
+  }
+
+  pub fn accessible(self, accessible: Vec<VAccessible>) -> VNode<'a, C> {
+    VNode::Object(Self { accessible, ..self })
+  }
+
+  pub fn accessible_role(self, role: AccessibleRole) -> VNode<'a, C> {
+    // The role is construct-only.
+    assert!(
+      self.constructor.is_none(),
+      "Accessible roles can't be set on {}, which has its own constructor.",
+      self.object_type
+    );
+    let object_type = self.object_type;
+    VNode::Object(Self {
+      constructor: Some(Box::new(move || {
+        Object::builder_with_type(object_type)
+          .property("accessible-role", role)
+          .build()
+      })),
+      ..self
+    })
+  }
+
+  pub fn controller(mut self, controller: VController<'a, C>) -> VNode<'a, C> {
+    self.controllers.push(controller);
+    VNode::Object(self)
+  }
+
+  pub fn key(self, key: impl ToString) -> VNode<'a, C> {
+    VNode::Object(Self {
+      key: Some(key.to_string()),
+      ..self
+    })
+  }
+
+  pub fn transition(self, transition: VTransition) -> VNode<'a, C> {
+    VNode::Object(Self {
+      transition: Some(transition),
+      ..self
+    })
+  }
 }
 
 type MessageBuilder<W, M> = Box<dyn Fn(&W) -> M + 'static>;
@@ -64,6 +185,13 @@ impl<'a, W: IsA<Object>, C: Component> VObjectBuilder<'a, W, C> for W {
       constructor: None,
       patcher: wrapped_patcher,
       children: vec![],
+      key: None,
+      preserved_props: vec![],
+      animated: vec![],
+      transition: None,
+      classes: None,
+      accessible: vec![],
+      controllers: vec![],
     })
   }
 
@@ -79,6 +207,13 @@ impl<'a, W: IsA<Object>, C: Component> VObjectBuilder<'a, W, C> for W {
       constructor: None,
       patcher: wrapped_patcher,
       children: vec![],
+      key: None,
+      preserved_props: vec![],
+      animated: vec![],
+      transition: None,
+      classes: None,
+      accessible: vec![],
+      controllers: vec![],
     })
   }
 
@@ -89,6 +224,13 @@ impl<'a, W: IsA<Object>, C: Component> VObjectBuilder<'a, W, C> for W {
       constructor: None,
       patcher,
       children: vec![],
+      key: None,
+      preserved_props: vec![],
+      animated: vec![],
+      transition: None,
+      classes: None,
+      accessible: vec![],
+      controllers: vec![],
     })
   }
 }
@@ -48,6 +48,15 @@ impl<Model: 'static + Component> VState<Model> {
     }
   }
 
+  /// Tear the children down and build them again, e.g. to start over with
+  /// new component states.
+  pub fn rebuild_children(&mut self, vnode: &VNode<Model>, scope: &Scope<Model>) {
+    match (vnode, self) {
+      (VNode::Object(vobject), VState::Object(state)) => state.rebuild_children(vobject, scope),
+      _ => unimplemented!(),
+    }
+  }
+
   #[must_use]
   pub(crate) fn patch(
     &mut self,
@@ -67,6 +76,13 @@ impl<Model: 'static + Component> VState<Model> {
     }
   }
 
+  /// Play the enter transition of a state built by a patch.
+  pub fn enter(&self) {
+    if let VState::Object(state) = self {
+      state.enter();
+    }
+  }
+
   pub fn unmount(self) {
     match self {
       VState::Object(state) => state.unmount(),
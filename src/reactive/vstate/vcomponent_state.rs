@@ -1,11 +1,17 @@
-use std::{any::TypeId, marker::PhantomData};
+use std::{
+  any::{type_name, TypeId},
+  marker::PhantomData,
+  panic::{self, AssertUnwindSafe},
+};
 
 use adw::glib::{object::Cast, MainContext, Object};
-use futures::channel::mpsc::UnboundedSender;
-use gtk4::{prelude::WidgetExt, Widget};
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use gtk4::{prelude::WidgetExt, Orientation, Widget};
+use log::error;
 
 use crate::reactive::{
   component::{Component, ComponentMessage, ComponentTask},
+  error_boundary::ComponentError,
   scope::Scope,
   vnode::{vcomponent::VComponent, vprops::VProps},
 };
@@ -60,6 +66,10 @@ impl<C: 'static + Component> VComponentState<C> {
   pub fn unmount(self) {
     self.state.unmounting();
   }
+
+  pub fn model_type(&self) -> TypeId {
+    self.model_type
+  }
 }
 
 pub struct VSubcomponentState<C: Component> {
@@ -73,7 +83,13 @@ impl<C: 'static + Component> VSubcomponentState<C> {
     parent_scope: &Scope<P>,
   ) -> (Self, Object) {
     let props: C::Props = props.unwrap();
-    let (channel, task) = ComponentTask::<C, P>::new(props, parent, Some(parent_scope));
+    let built = panic::catch_unwind(AssertUnwindSafe(|| {
+      ComponentTask::<C, P>::new(props, parent, Some(parent_scope))
+    }));
+    let (channel, task) = match built {
+      Ok(built) => built,
+      Err(payload) => return Self::failed(parent_scope, payload),
+    };
     let object = task.object().unwrap();
     // for prop in child_props {
     //   (prop.set)(object.upcast_ref(), parent, true);
@@ -81,21 +97,36 @@ impl<C: 'static + Component> VSubcomponentState<C> {
     MainContext::ref_thread_default().spawn_local(task);
     (VSubcomponentState { channel }, object)
   }
+
+  /// Report a component which panicked while being built to the nearest
+  /// error boundary, and stand in for it with an empty widget until the
+  /// boundary renders its fallback.
+  fn failed<P: 'static + Component>(
+    parent_scope: &Scope<P>,
+    payload: Box<dyn std::any::Any + Send>,
+  ) -> (Self, Object) {
+    let Some(boundary) = parent_scope.child_boundary() else {
+      panic::resume_unwind(payload);
+    };
+    let error = ComponentError::from_panic(type_name::<C>(), payload.as_ref());
+    error!("{}", error);
+    boundary(error);
+
+    let (channel, _) = unbounded();
+    let placeholder = gtk4::Box::new(Orientation::Horizontal, 0).upcast::<Object>();
+    (VSubcomponentState { channel }, placeholder)
+  }
 }
 
 impl<Model: 'static + Component> PropertiesReceiver for VSubcomponentState<Model> {
+  // The task is gone if the component panicked, in which case there is
+  // nobody left to tell.
   fn update(&self, raw_props: &VProps) {
     let props = raw_props.unwrap();
-    self
-      .channel
-      .unbounded_send(ComponentMessage::Props(props))
-      .expect("failed to send props message over system channel")
+    let _ = self.channel.unbounded_send(ComponentMessage::Props(props));
   }
 
   fn unmounting(&self) {
-    self
-      .channel
-      .unbounded_send(ComponentMessage::Unmounted)
-      .expect("failed to send unmount message over system channel")
+    let _ = self.channel.unbounded_send(ComponentMessage::Unmounted);
   }
 }
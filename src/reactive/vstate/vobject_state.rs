@@ -1,27 +1,33 @@
 use adw::{
   gio::{
-    prelude::{ActionExt, ActionMapExt},
-    Action, Menu, MenuItem,
+    prelude::{ActionExt, ActionGroupExt, ActionMapExt, ApplicationExtManual},
+    Action, Menu, SimpleActionGroup,
   },
   glib::{
-    object::{Cast, IsA, ObjectClassExt, ObjectExt},
-    subclass::object,
+    object::{Cast, IsA, ObjectExt},
     Object, ParamFlags, SignalHandlerId, Value,
   },
-  prelude::{AdwWindowExt, BinExt},
-  Application, ApplicationWindow, Bin, HeaderBar, Window,
+  prelude::{AdwApplicationWindowExt, AdwDialogExt, AdwWindowExt, BinExt},
+  Application, ApplicationWindow, Bin, Dialog, HeaderBar, Window,
 };
 use gtk4::{
-  prelude::{ApplicationWindowExt, BoxExt, GridExt, GtkApplicationExt, GtkWindowExt, WidgetExt},
-  Box, Builder, Grid, Notebook, ScrolledWindow, ShortcutsWindow, Widget,
+  prelude::{BoxExt, GridExt, GtkApplicationExt, GtkWindowExt, WidgetExt},
+  Box, Builder, Grid, ScrolledWindow, Widget, Window as GtkWindow,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 use super::VState;
 use crate::reactive::{
   component::Component,
+  helpers::action_ext::{action_added, action_removed},
   scope::Scope,
+  style::patch_classes,
   vnode::{
+    vaccessible::{patch_accessible, VAccessible},
+    vaction::group_name,
+    vanimation::{apply_animated, enter, exit, VTransition},
+    vcontroller::{patch_controllers, ControllerState},
+    vmenu::{attach_menu, detach_menu},
     vobject::{VObject, VObjectContext},
     VNode,
   },
@@ -29,6 +35,11 @@ use crate::reactive::{
 
 pub struct VObjectState<Model: Component> {
   pub object: Object,
+  key: Option<String>,
+  transition: Option<Rc<VTransition>>,
+  classes: Vec<String>,
+  accessible: Vec<VAccessible>,
+  controllers: Vec<ControllerState>,
   initial_props: HashMap<&'static str, Value>,
   handlers: Vec<SignalHandlerId>,
   children: Vec<VState<Model>>,
@@ -56,12 +67,38 @@ fn build_obj<A: IsA<Object>, C: Component>(spec: &VObject<C>) -> A {
 }
 
 fn add_child(parent: &Object, index: usize, total: usize, child: &Object) {
+  // Dialogs are presented over their parent, whatever it is.
+  if let Some(dialog) = child.downcast_ref::<Dialog>() {
+    dialog.present(parent.downcast_ref::<Widget>());
+    return;
+  }
+
+  // Menus are attached to their parent as its menu model, or as a context
+  // menu.
+  if let Some(menu) = child.downcast_ref::<Menu>() {
+    attach_menu(parent, menu);
+    return;
+  }
+
+  // Action groups are inserted in their parent widget.
+  if let Some(group) = child.downcast_ref::<SimpleActionGroup>() {
+    let widget = parent.downcast_ref::<Widget>().unwrap_or_else(|| {
+      panic!(
+        "Action groups can only be added to Widgets, but {} was found.",
+        parent.type_()
+      )
+    });
+    widget.insert_action_group(&group_name(group), Some(group));
+    return;
+  }
+
   // Application.
   if let Some(application) = parent.downcast_ref::<Application>() {
-    if let Some(window) = child.downcast_ref::<Window>() {
+    if let Some(window) = child.downcast_ref::<GtkWindow>() {
       application.add_window(window);
     } else if let Some(action) = child.downcast_ref::<Action>() {
       application.add_action(action);
+      action_added(action, "app");
     } else {
       panic!(
         "Application's children must be Windows or Actions, but {} was found.",
@@ -69,39 +106,35 @@ fn add_child(parent: &Object, index: usize, total: usize, child: &Object) {
       );
     }
   }
-  // // ApplicationWindow.
-  // else if let Some(window) = parent.downcast_ref::<ApplicationWindow>() {
-  //   // ApplicationWindow: takes any number of Actions, optionally one
-  //   // ShortcutsWindow added with `set_help_overlay()`, and either 1 or 2
-  //   // Widgets. If 1, it's the main widget. If 2, the first is added with
-  //   // `set_titlebar()` and the second is the main widget.
-  //   if let Some(action) = child.downcast_ref::<Action>() {
-  //     window.add_action(action);
-  //   } else if let Some(help_overlay) = child.downcast_ref::<ShortcutsWindow>() {
-  //     window.set_help_overlay(Some(help_overlay));
-  //   } else if let Some(widget) = child.downcast_ref::<Widget>() {
-  //     window.set_child(Some(widget));
-  //     // match window.child() {
-  //     //   None => window.set_child(Some(widget)),
-  //     //   Some(ref titlebar) if window.titlebar().is_none() => {
-  //     //     window.set_titlebar(Some(titlebar));
-  //     //     window.set_child(Some(widget));
-  //     //   }
-  //     //   _ => panic!("ApplicationWindow can have at most two Widget children."),
-  //     // }
-  //   } else {
-  //     panic!(
-  //       "ApplicationWindow's children must be Actions or Widgets, but {} was found.",
-  //       child.type_()
-  //     );
-  //   }
-  // }
+  // ApplicationWindow.
+  else if let Some(window) = parent.downcast_ref::<ApplicationWindow>() {
+    // ApplicationWindow: takes any number of Actions, available as `win.*`,
+    // transient Windows and one Widget, which is the window's content.
+    if let Some(action) = child.downcast_ref::<Action>() {
+      window.add_action(action);
+      action_added(action, "win");
+    } else if let Some(transient) = child.downcast_ref::<GtkWindow>() {
+      transient.set_application(window.application().as_ref());
+      transient.set_transient_for(Some(window));
+      transient.present();
+    } else if let Some(widget) = child.downcast_ref::<Widget>() {
+      window.set_content(Some(widget));
+    } else {
+      panic!(
+        "ApplicationWindow's children must be Actions or Widgets, but {} was found.",
+        child.type_()
+      );
+    }
+  }
   // Window.
   else if let Some(window) = parent.downcast_ref::<Window>() {
-    // Window: takes only 1 or 2 Widgets. If 1 widget child, it's the
-    // window's main widget. If 2, the first is the title bar and the second
-    // is the main widget. More than 2 goes boom.
-    if let Some(widget) = child.downcast_ref::<Widget>() {
+    // Window: its Widget child is the window's content. Windows can also
+    // have other Windows as children, which are opened on top of it.
+    if let Some(transient) = child.downcast_ref::<GtkWindow>() {
+      transient.set_application(window.application().as_ref());
+      transient.set_transient_for(Some(window));
+      transient.present();
+    } else if let Some(widget) = child.downcast_ref::<Widget>() {
       window.set_content(Some(widget));
       // if total == 2 && index == 0 {
       //   window.set_titlebar(Some(widget));
@@ -114,6 +147,40 @@ fn add_child(parent: &Object, index: usize, total: usize, child: &Object) {
       );
     }
   }
+  // SimpleActionGroup.
+  else if let Some(group) = parent.downcast_ref::<SimpleActionGroup>() {
+    if let Some(action) = child.downcast_ref::<Action>() {
+      group.add_action(action);
+      action_added(action, &group_name(group));
+    } else {
+      panic!(
+        "Action groups' children must be Actions, but {} was found.",
+        child.type_()
+      );
+    }
+  }
+  // Dialog.
+  else if let Some(dialog) = parent.downcast_ref::<Dialog>() {
+    if let Some(widget) = child.downcast_ref::<Widget>() {
+      dialog.set_child(Some(widget));
+    } else {
+      panic!(
+        "Dialog's child must be Widgets, but {} was found.",
+        child.type_()
+      );
+    }
+  }
+  // Bin.
+  else if let Some(bin) = parent.downcast_ref::<Bin>() {
+    if let Some(widget) = child.downcast_ref::<Widget>() {
+      bin.set_child(Some(widget));
+    } else {
+      panic!(
+        "Bin's child must be Widgets, but {} was found.",
+        child.type_()
+      );
+    }
+  }
   // HeaderBar.
   else if let Some(parent) = parent.downcast_ref::<HeaderBar>() {
     // HeaderBar: added normally, except one widget can be added using
@@ -187,12 +254,45 @@ fn add_child(parent: &Object, index: usize, total: usize, child: &Object) {
 }
 
 fn remove_child(parent: &Object, child: &Object) {
+  // Dialogs. They might already have been closed by the user.
+  if let Some(dialog) = child.downcast_ref::<Dialog>() {
+    if dialog.parent().is_some() {
+      dialog.force_close();
+    }
+    return;
+  }
+
+  // Menus.
+  if let Some(menu) = child.downcast_ref::<Menu>() {
+    detach_menu(parent, menu);
+    return;
+  }
+
+  // Action groups.
+  if child.is::<SimpleActionGroup>() {
+    if let Some(widget) = parent.downcast_ref::<Widget>() {
+      let group = child.downcast_ref::<SimpleActionGroup>().unwrap();
+      for name in group.list_actions() {
+        if let Some(action) = group.lookup_action(&name) {
+          action_removed(&action);
+        }
+      }
+      widget.insert_action_group(&group_name(group), Option::<&SimpleActionGroup>::None);
+    }
+    return;
+  }
+
   // Application.
   if let Some(application) = parent.downcast_ref::<Application>() {
-    if let Some(window) = child.downcast_ref::<Window>() {
-      application.remove_window(window);
+    if let Some(window) = child.downcast_ref::<GtkWindow>() {
+      // Windows closed by the user have already left the application.
+      if window.application().is_some() {
+        application.remove_window(window);
+      }
+      window.destroy();
     } else if let Some(action) = child.downcast_ref::<Action>() {
       application.remove_action(&action.name());
+      action_removed(action);
     } else {
       panic!(
         "Applications can only contain Windows, but was asked to remove a {}.",
@@ -200,9 +300,29 @@ fn remove_child(parent: &Object, child: &Object) {
       );
     }
   }
+  // ApplicationWindow.
+  else if let Some(window) = parent.downcast_ref::<ApplicationWindow>() {
+    if let Some(action) = child.downcast_ref::<Action>() {
+      window.remove_action(&action.name());
+      action_removed(action);
+    } else if let Some(transient) = child.downcast_ref::<GtkWindow>() {
+      transient.destroy();
+    } else if let Some(widget) = child.downcast_ref::<Widget>() {
+      if window.content().is_some_and(|w| w.eq(widget)) {
+        window.set_content(Option::<&Widget>::None);
+      }
+    } else {
+      panic!(
+        "ApplicationWindow's children must be Actions or Widgets, but {} was found.",
+        child.type_()
+      );
+    }
+  }
   // Window.
   else if let Some(window) = parent.downcast_ref::<Window>() {
-    if let Some(widget) = child.downcast_ref::<Widget>() {
+    if let Some(transient) = child.downcast_ref::<GtkWindow>() {
+      transient.destroy();
+    } else if let Some(widget) = child.downcast_ref::<Widget>() {
       if window.content().is_some_and(|w| w.eq(widget)) {
         window.set_content(Option::<&Widget>::None);
       }
@@ -213,6 +333,42 @@ fn remove_child(parent: &Object, child: &Object) {
       );
     }
   }
+  // SimpleActionGroup.
+  else if let Some(group) = parent.downcast_ref::<SimpleActionGroup>() {
+    if let Some(action) = child.downcast_ref::<Action>() {
+      group.remove_action(&action.name());
+      action_removed(action);
+    } else {
+      panic!(
+        "Action groups' children must be Actions, but {} was found.",
+        child.type_()
+      );
+    }
+  }
+  // Dialog.
+  else if let Some(dialog) = parent.downcast_ref::<Dialog>() {
+    if child.downcast_ref::<Widget>().is_some() {
+      dialog.set_child(Option::<&Widget>::None);
+    } else {
+      panic!(
+        "Dialog's child must be Widgets, but {} was found.",
+        child.type_()
+      );
+    }
+  }
+  // Bin.
+  else if let Some(bin) = parent.downcast_ref::<Bin>() {
+    if let Some(widget) = child.downcast_ref::<Widget>() {
+      if bin.child().is_some_and(|w| w.eq(widget)) {
+        bin.set_child(Option::<&Widget>::None);
+      }
+    } else {
+      panic!(
+        "Bin's child must be Widgets, but {} was found.",
+        child.type_()
+      );
+    }
+  }
   // HeaderBar.
   else if let Some(parent) = parent.downcast_ref::<HeaderBar>() {
     if let Some(widget) = child.downcast_ref::<Widget>() {
@@ -263,7 +419,35 @@ fn remove_child(parent: &Object, child: &Object) {
   }
 }
 
-const IGNORED_PROPS: [&str; 8] = [
+/// Remove `child` from `parent` and unmount it, after its exit transition if
+/// it has one.
+fn leave<C: 'static + Component>(parent: &Object, child: VState<C>) {
+  let transition = match child {
+    VState::Object(ref state) => state.transition.clone(),
+    VState::Component(_) => None,
+  };
+  let object = child.object().clone();
+  match transition {
+    Some(ref transition) if object.is::<Widget>() => {
+      child.unmount();
+      let parent = parent.clone();
+      let exiting = object.clone();
+      exit(&exiting, transition, move || {
+        // The parent may have been destroyed in the meantime.
+        let widget = object.downcast_ref::<Widget>().unwrap();
+        if widget.parent().is_some() {
+          remove_child(&parent, &object);
+        }
+      });
+    }
+    _ => {
+      remove_child(parent, &object);
+      child.unmount();
+    }
+  }
+}
+
+const IGNORED_PROPS: [&str; 10] = [
   "parent",
   "root",
   "child",
@@ -272,6 +456,9 @@ const IGNORED_PROPS: [&str; 8] = [
   "flags",
   "icon-name",
   "version",
+  // Set when attaching a menu, see `attach_menu`.
+  "menu-model",
+  "popover",
 ];
 
 fn should_save_prop(object: &Object, prop_name: &str) -> bool {
@@ -285,8 +472,14 @@ fn should_save_prop(object: &Object, prop_name: &str) -> bool {
     return false;
   }
 
-  // Window.
-  if object.downcast_ref::<Window>().is_some() {
+  // Actions are entirely described by their builder, and restoring their
+  // state would notify it twice on every patch.
+  if object.downcast_ref::<Action>().is_some() {
+    return false;
+  }
+
+  // Windows and dialogs.
+  if object.downcast_ref::<GtkWindow>().is_some() || object.downcast_ref::<Dialog>().is_some() {
     return false;
   }
 
@@ -336,9 +529,21 @@ impl<C: 'static + Component> VObjectState<C> {
 
     let context = VObjectContext::new(scope.clone());
     let handlers = (vobj.patcher)(&object, &context);
+    apply_animated(&object, &vobj.animated, true);
+    let classes = vobj.classes.clone().unwrap_or_default();
+    if let Some(widget) = object.downcast_ref::<Widget>() {
+      patch_classes(widget, &[], &classes);
+    }
+    patch_accessible(&object, &[], &vobj.accessible);
+    let controllers = patch_controllers(&object, Vec::new(), &vobj.controllers, &context);
 
     VObjectState {
       object: object.upcast(),
+      key: vobj.key.clone(),
+      transition: vobj.transition.clone().map(Rc::new),
+      classes,
+      accessible: vobj.accessible.clone(),
+      controllers,
       initial_props,
       handlers,
       children: Vec::new(),
@@ -363,6 +568,19 @@ impl<C: 'static + Component> VObjectState<C> {
     }
   }
 
+  pub fn rebuild_children(&mut self, vobj: &VObject<C>, scope: &Scope<C>) {
+    // An application quits along with its last window.
+    let _hold = self
+      .object
+      .downcast_ref::<Application>()
+      .map(|application| application.hold());
+    for child in self.children.drain(..) {
+      remove_child(&self.object, child.object());
+      child.unmount();
+    }
+    self.build_children(vobj, scope);
+  }
+
   pub fn build(vobj: &VObject<C>, parent: Option<&Object>, scope: &Scope<C>) -> Self {
     let mut state = Self::build_root(vobj, parent, scope);
     state.build_children(vobj, scope);
@@ -394,8 +612,8 @@ impl<C: 'static + Component> VObjectState<C> {
         (Some(VState::Object(target)), Some(spec_item)) => {
           match spec_item {
             VNode::Object(spec) => {
-              if target.object.type_() == spec.object_type {
-                // Objects have same type; patch down
+              if target.object.type_() == spec.object_type && target.key == spec.key {
+                // Objects have same type and key; patch down
                 target.patch(spec, Some(&self.object), scope);
               } else {
                 // Objects are different, need to reconstruct everything from here
@@ -421,6 +639,7 @@ impl<C: 'static + Component> VObjectState<C> {
           // New spec; construct
           let state = VState::build(spec, Some(&self.object), scope);
           add_child(&self.object, index, vobj.children.len(), state.object());
+          state.enter();
           to_append.push(state);
         }
         (None, None) => break,
@@ -428,12 +647,8 @@ impl<C: 'static + Component> VObjectState<C> {
     }
     if let Some(index) = reconstruct_from {
       // Remove all previous children from here onwards
-      if self.object.is::<Window>() && index == 0 && self.children.len() == 2 {
-        panic!("Can't remove a title bar widget from an existing Window!");
-      }
       for child in self.children.drain(index..) {
-        remove_child(&self.object, child.object());
-        child.unmount();
+        leave(&self.object, child);
       }
       // Rebuild children from new specs
       for (index, child_spec) in vobj.children.iter().enumerate().skip(index) {
@@ -442,23 +657,17 @@ impl<C: 'static + Component> VObjectState<C> {
         if let Some(w) = state.widget() {
           w.set_visible(true);
         }
+        state.enter();
         self.children.push(state);
       }
     } else {
       // Remove children flagged as extraneous
       if let Some(remove_from) = to_remove {
-        if self.object.is::<Window>() && remove_from == 1 && self.children.len() == 2 {
-          panic!("Can't remove a title bar widget from an existing Window!");
-        }
         for child in self.children.drain(remove_from..) {
-          remove_child(&self.object, child.object());
-          child.unmount();
+          leave(&self.object, child);
         }
       }
       // Or append newly constructed children
-      if self.object.is::<Window>() && !to_append.is_empty() && self.children.len() == 1 {
-        panic!("Can't add a title bar widget to an existing Window!");
-      }
       for child in to_append {
         if let Some(w) = child.widget() {
           w.set_visible(true);
@@ -469,6 +678,9 @@ impl<C: 'static + Component> VObjectState<C> {
 
     // Restore props.
     for (name, value) in self.initial_props.iter() {
+      if vobj.preserved_props.contains(name) {
+        continue;
+      }
       self.object.set_property_from_value(name, value);
     }
 
@@ -481,6 +693,17 @@ impl<C: 'static + Component> VObjectState<C> {
     let context = VObjectContext::new(scope.clone());
     let new_handlers = (vobj.patcher)(&self.object, &context);
     self.handlers = new_handlers;
+    apply_animated(&self.object, &vobj.animated, false);
+    self.transition = vobj.transition.clone().map(Rc::new);
+    let classes = vobj.classes.clone().unwrap_or_default();
+    if let Some(widget) = self.object.downcast_ref::<Widget>() {
+      patch_classes(widget, &self.classes, &classes);
+    }
+    self.classes = classes;
+    patch_accessible(&self.object, &self.accessible, &vobj.accessible);
+    self.accessible = vobj.accessible.clone();
+    let controllers = std::mem::take(&mut self.controllers);
+    self.controllers = patch_controllers(&self.object, controllers, &vobj.controllers, &context);
 
     // // Patch properties
     // self.patch_properties(&vobj.properties, parent);
@@ -494,9 +717,119 @@ impl<C: 'static + Component> VObjectState<C> {
     true
   }
 
+  /// Play the enter transition of the object, if any.
+  pub fn enter(&self) {
+    if let Some(ref transition) = self.transition {
+      enter(&self.object, transition);
+    }
+  }
+
   pub fn unmount(self) {
     for child in self.children {
+      // Dialogs, windows and context menus outlive their parent widget; close
+      // them too.
+      if child.object().is::<Dialog>()
+        || child.object().is::<GtkWindow>()
+        || child.object().is::<Menu>()
+      {
+        remove_child(&self.object, child.object());
+      }
       child.unmount();
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use adw::{prelude::AlertDialogExt, AlertDialog};
+  use gtk4::Label;
+
+  use super::*;
+  use crate::reactive::{
+    testing::{scope, with_gtk, Model},
+    vnode::{vobject::VObjectBuilder, VNode},
+  };
+
+  /// A window showing a label, along with a dialog and a secondary window.
+  fn window(dialog: bool, secondary: bool) -> VNode<'static, Model> {
+    let mut children = vec![Label::c(|w| w.set_label("Content"))];
+    if dialog {
+      children.push(AlertDialog::c(|d| d.set_heading(Some("Sure?"))));
+    }
+    if secondary {
+      children.push(Window::c(|w| w.set_title(Some("Details"))));
+    }
+    Window::cs().children(children)
+  }
+
+  fn build(view: VNode<Model>, scope: &Scope<Model>) -> VObjectState<Model> {
+    let VNode::Object(vobj) = view else {
+      unreachable!("the view is a window");
+    };
+    VObjectState::build(&vobj, None, scope)
+  }
+
+  fn patch(state: &mut VObjectState<Model>, view: VNode<Model>, scope: &Scope<Model>) {
+    let VNode::Object(vobj) = view else {
+      unreachable!("the view is a window");
+    };
+    assert!(state.patch(&vobj, None, scope));
+  }
+
+  fn child<T: IsA<Object>>(state: &VObjectState<Model>, index: usize) -> T {
+    state.children[index].object().clone().downcast().unwrap()
+  }
+
+  #[test]
+  fn presents_dialogs_while_they_are_in_the_view() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let mut state = build(window(false, false), &scope);
+      let main = state.object.clone().downcast::<Window>().unwrap();
+      let content = child::<Widget>(&state, 0);
+      assert_eq!(main.content(), Some(content.clone()));
+
+      patch(&mut state, window(true, false), &scope);
+      let dialog = child::<AlertDialog>(&state, 1);
+      assert!(dialog.parent().is_some());
+      // The dialog isn't the content of the window.
+      assert_eq!(main.content(), Some(content));
+
+      patch(&mut state, window(false, false), &scope);
+      assert!(dialog.parent().is_none());
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn opens_secondary_windows_on_top_of_their_parent() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let mut state = build(window(false, true), &scope);
+      let main = state.object.clone().downcast::<Window>().unwrap();
+      let secondary = child::<Window>(&state, 1);
+      assert_eq!(secondary.transient_for(), Some(main.upcast()));
+      assert!(secondary.is_visible());
+
+      patch(&mut state, window(false, false), &scope);
+      assert!(!secondary.is_visible());
+      state.unmount();
+    });
+  }
+
+  #[test]
+  fn closes_dialogs_and_windows_on_unmount() {
+    with_gtk(|| {
+      let (scope, _messages) = scope();
+      let state = build(window(true, true), &scope);
+      let dialog = child::<AlertDialog>(&state, 1);
+      let secondary = child::<Window>(&state, 2);
+      assert!(dialog.parent().is_some());
+      assert!(secondary.is_visible());
+
+      state.unmount();
+      assert!(dialog.parent().is_none());
+      assert!(!secondary.is_visible());
+    });
+  }
+}
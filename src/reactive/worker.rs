@@ -0,0 +1,245 @@
+use std::{
+  future::Future,
+  pin::Pin,
+  task::{Context, Poll},
+};
+
+use adw::{
+  gio::{
+    self,
+    prelude::{CancellableExt, CancellableExtManual},
+    Cancellable,
+  },
+  glib::JoinHandle,
+};
+use futures::{
+  channel::mpsc::{unbounded, UnboundedSender},
+  stream, Stream, StreamExt,
+};
+
+use super::component::{Component, UpdateAction};
+
+/// Messages produced in the background on behalf of a component, see
+/// [`UpdateAction::Defer`].
+///
+/// The messages are delivered through the component's `Scope` on the GTK
+/// thread, and the job is cancelled when the component unmounts.
+pub struct Job<C: Component> {
+  pub(crate) messages: Pin<Box<dyn Stream<Item = C::Message>>>,
+  pub(crate) cancellable: Cancellable,
+}
+
+impl<C: 'static + Component> Job<C> {
+  /// A job delivering the output of a future running on the GTK thread.
+  pub fn from_future<F: 'static + Future<Output = C::Message>>(future: F) -> Self {
+    Job {
+      messages: Box::pin(stream::once(future)),
+      cancellable: Cancellable::new(),
+    }
+  }
+
+  /// A job running `func` on the blocking thread pool of GIO, then
+  /// delivering the message it returns. Intermediate messages can be sent
+  /// with [`Worker::progress`].
+  pub fn blocking<F>(func: F) -> Self
+  where
+    F: 'static + Send + FnOnce(&Worker<C::Message>) -> C::Message,
+  {
+    let (sender, receiver) = unbounded();
+    let cancellable = Cancellable::new();
+    let worker = Worker {
+      sender,
+      cancellable: cancellable.clone(),
+    };
+
+    // The result goes through the same channel as progress, so that it can't
+    // overtake it. Nothing is delivered if the job panics.
+    drop(gio::spawn_blocking(move || {
+      let message = func(&worker);
+      if !worker.is_cancelled() {
+        let _ = worker.sender.unbounded_send(message);
+      }
+    }));
+
+    Job {
+      messages: Box::pin(receiver),
+      cancellable,
+    }
+  }
+}
+
+/// A job is the stream of the messages it delivers, e.g. to run it outside
+/// of a component.
+impl<C: Component> Stream for Job<C> {
+  type Item = C::Message;
+
+  fn poll_next(mut self: Pin<&mut Self>, ctx: &mut Context<'_>) -> Poll<Option<C::Message>> {
+    self.messages.as_mut().poll_next(ctx)
+  }
+}
+
+/// Handle given to blocking jobs, see [`Job::blocking`].
+pub struct Worker<M> {
+  sender: UnboundedSender<M>,
+  cancellable: Cancellable,
+}
+
+impl<M> Clone for Worker<M> {
+  fn clone(&self) -> Self {
+    Worker {
+      sender: self.sender.clone(),
+      cancellable: self.cancellable.clone(),
+    }
+  }
+}
+
+impl<M: Send> Worker<M> {
+  /// Deliver an intermediate message to the component, such as progress.
+  pub fn progress(&self, message: M) {
+    if !self.is_cancelled() {
+      let _ = self.sender.unbounded_send(message);
+    }
+  }
+
+  /// Whether the component has unmounted. Long jobs should check it
+  /// regularly and return early.
+  pub fn is_cancelled(&self) -> bool {
+    self.cancellable.is_cancelled()
+  }
+
+  /// Cancellable triggered when the component unmounts, to pass along to
+  /// GIO operations.
+  pub fn cancellable(&self) -> &Cancellable {
+    &self.cancellable
+  }
+
+  /// Trigger `cancellable` as well when the component unmounts, e.g. one
+  /// kept in the state so that the user can cancel the job.
+  pub fn link_cancellable(&self, cancellable: &Cancellable) {
+    let linked = cancellable.clone();
+    self.cancellable.connect_cancelled(move |_| linked.cancel());
+  }
+}
+
+/// Shorthand for deferring a [`Job::blocking`] from `update`.
+pub fn spawn_blocking<C, F>(func: F) -> UpdateAction<C>
+where
+  C: 'static + Component,
+  F: 'static + Send + FnOnce(&Worker<C::Message>) -> C::Message,
+{
+  UpdateAction::Defer(Job::blocking(func))
+}
+
+/// A job started by a `ComponentTask`.
+pub(crate) struct RunningJob {
+  cancellable: Cancellable,
+  handle: JoinHandle<()>,
+}
+
+impl RunningJob {
+  pub(crate) fn new(cancellable: Cancellable, handle: JoinHandle<()>) -> Self {
+    RunningJob {
+      cancellable,
+      handle,
+    }
+  }
+
+  pub(crate) fn is_finished(&self) -> bool {
+    self.handle.source().is_destroyed()
+  }
+
+  pub(crate) fn cancel(self) {
+    self.cancellable.cancel();
+    if !self.is_finished() {
+      self.handle.abort();
+    }
+  }
+}
+
+impl<C: Component> Job<C> {
+  pub(crate) fn into_parts(self) -> (Pin<Box<dyn Stream<Item = C::Message>>>, Cancellable) {
+    (self.messages, self.cancellable)
+  }
+}
+
+/// Drain the messages of a job into `send`.
+pub(crate) async fn deliver<M, F: Fn(M)>(mut messages: Pin<Box<dyn Stream<Item = M>>>, send: F) {
+  while let Some(message) = messages.next().await {
+    send(message);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::mpsc;
+
+  use adw::glib::MainContext;
+  use futures::{executor::block_on, future};
+
+  use super::*;
+  use crate::reactive::testing::Model;
+
+  fn messages(job: Job<Model>) -> Vec<String> {
+    block_on(job.messages.collect())
+  }
+
+  #[test]
+  fn delivers_progress_then_the_result() {
+    let job = Job::<Model>::blocking(|worker| {
+      worker.progress("1/2".to_string());
+      worker.progress("2/2".to_string());
+      "done".to_string()
+    });
+    assert_eq!(messages(job), vec!["1/2", "2/2", "done"]);
+  }
+
+  #[test]
+  fn delivers_the_output_of_futures() {
+    let job = Job::<Model>::from_future(async { "done".to_string() });
+    assert_eq!(messages(job), vec!["done"]);
+  }
+
+  #[test]
+  fn cancelled_jobs_deliver_nothing() {
+    let (start, started) = mpsc::channel();
+    let job = Job::<Model>::blocking(move |worker| {
+      started.recv().unwrap();
+      assert!(worker.is_cancelled());
+      assert!(worker.cancellable().is_cancelled());
+      worker.progress("1/2".to_string());
+      "done".to_string()
+    });
+    job.cancellable.cancel();
+    start.send(()).unwrap();
+    assert_eq!(messages(job), Vec::<String>::new());
+  }
+
+  #[test]
+  fn panicking_jobs_deliver_nothing() {
+    let job = Job::<Model>::blocking(|worker| {
+      worker.progress("1/2".to_string());
+      panic!("failed");
+    });
+    assert_eq!(messages(job), vec!["1/2"]);
+  }
+
+  #[test]
+  fn delivers_messages_in_order() {
+    let delivered = std::cell::RefCell::new(vec![]);
+    let messages = Box::pin(stream::iter(vec![1, 2, 3]));
+    block_on(deliver(messages, |message| {
+      delivered.borrow_mut().push(message)
+    }));
+    assert_eq!(delivered.into_inner(), vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn cancels_running_jobs() {
+    let context = MainContext::new();
+    let cancellable = Cancellable::new();
+    let job = RunningJob::new(cancellable.clone(), context.spawn_local(future::pending()));
+    assert!(!job.is_finished());
+    job.cancel();
+    assert!(cancellable.is_cancelled());
+  }
+}
@@ -1 +1,3 @@
+pub mod catalog;
 pub mod flatpak;
+pub mod search;
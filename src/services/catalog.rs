@@ -0,0 +1,725 @@
+use std::{
+  collections::BTreeMap,
+  io::Read,
+  path::{Path, PathBuf},
+};
+
+use adw::{
+  gio::{
+    prelude::{FileExt, InputStreamExtManual},
+    Cancellable, ConverterInputStream, File, IOErrorEnum, ZlibCompressorFormat, ZlibDecompressor,
+  },
+  glib::{self, Error},
+};
+use libflatpak::{
+  prelude::{InstallationExt, InstallationExtManual, RemoteExt},
+  Installation, Remote,
+};
+use log::{debug, warn};
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+
+use super::flatpak::{self, ContentRating, InstallationId};
+
+const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Something a remote provides: an app, a runtime, an addon, a font…
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Component {
+  /// The AppStream id, usually the id of the app, e.g. `org.gnome.Maps`.
+  pub id: String,
+  /// E.g. `desktop-application`, `runtime` or `addon`.
+  pub kind: String,
+  pub installation: Option<InstallationId>,
+  pub remote: String,
+  /// The ref to install, e.g. `app/org.gnome.Maps/x86_64/stable`.
+  pub bundle: Option<String>,
+  pub name: Option<String>,
+  pub summary: Option<String>,
+  /// Plain text, with paragraphs separated by blank lines.
+  pub description: Option<String>,
+  pub categories: Vec<String>,
+  pub keywords: Vec<String>,
+  pub screenshots: Vec<Screenshot>,
+  /// Newest first.
+  pub releases: Vec<Release>,
+  pub icons: Vec<Icon>,
+  /// URLs by type, e.g. `homepage` or `bugtracker`.
+  pub urls: BTreeMap<String, String>,
+  pub content_rating: Option<ContentRating>,
+  pub project_license: Option<String>,
+  pub developer: Option<String>,
+  pub provides: Vec<Provided>,
+}
+
+impl Component {
+  /// The name, or the id when there is no name.
+  pub fn display_name(&self) -> &str {
+    self.name.as_deref().unwrap_or(&self.id)
+  }
+
+  pub fn is_app(&self) -> bool {
+    self.kind == "desktop-application"
+      || self.kind == "desktop"
+      || self.kind == "console-application"
+  }
+
+  /// The version of the latest release, if any.
+  pub fn version(&self) -> Option<&str> {
+    self
+      .releases
+      .first()
+      .map(|release| release.version.as_str())
+  }
+
+  /// The icon best suited to be shown at `size` pixels: the smallest one at
+  /// least that big, or the biggest one.
+  pub fn icon(&self, size: u32) -> Option<&Icon> {
+    let big_enough = self
+      .icons
+      .iter()
+      .filter(|icon| icon.width * icon.scale >= size)
+      .min_by_key(|icon| icon.width * icon.scale);
+    big_enough.or_else(|| self.icons.iter().max_by_key(|icon| icon.width * icon.scale))
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Screenshot {
+  /// Whether it's the one to show first.
+  pub default: bool,
+  pub caption: Option<String>,
+  /// The same screenshot in several sizes.
+  pub images: Vec<Image>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+  pub url: String,
+  /// Whether it's a thumbnail rather than the source image.
+  pub thumbnail: bool,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+  pub version: String,
+  /// In seconds since the Unix epoch.
+  pub timestamp: Option<i64>,
+  /// E.g. `stable` or `development`.
+  pub kind: Option<String>,
+  pub urgency: Option<String>,
+  pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum IconSource {
+  /// A file in the cache of the remote.
+  Cached(PathBuf),
+  /// A file to download.
+  Remote(String),
+  /// An icon of the icon theme.
+  Stock(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Icon {
+  pub source: IconSource,
+  /// In pixels, 0 when unknown.
+  pub width: u32,
+  pub height: u32,
+  pub scale: u32,
+}
+
+/// What a component provides besides itself, e.g. `binary` → `gimp` or
+/// `mediatype` → `image/png`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Provided {
+  pub kind: String,
+  pub value: String,
+}
+
+/// The components of every remote.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Catalog {
+  pub components: Vec<Component>,
+}
+
+impl Catalog {
+  /// The components with the id `id`, one per remote providing it.
+  pub fn get<'a>(&'a self, id: &'a str) -> impl Iterator<Item = &'a Component> {
+    self.components.iter().filter(move |c| c.id == id)
+  }
+
+  /// The component installing `ref_`, if any.
+  pub fn by_bundle(&self, ref_: &str) -> Option<&Component> {
+    self
+      .components
+      .iter()
+      .find(|c| c.bundle.as_deref() == Some(ref_))
+  }
+
+  pub fn apps(&self) -> impl Iterator<Item = &Component> {
+    self.components.iter().filter(|c| c.is_app())
+  }
+
+  /// Every category used by the components, sorted.
+  pub fn categories(&self) -> Vec<&str> {
+    let mut categories: Vec<&str> = self
+      .components
+      .iter()
+      .flat_map(|c| c.categories.iter().map(String::as_str))
+      .collect();
+    categories.sort_unstable();
+    categories.dedup();
+    categories
+  }
+}
+
+/// Load the catalog of every enabled remote from the cache, without going
+/// online. Remotes whose catalog was never downloaded, or can't be read, are
+/// skipped, see [`refresh`].
+pub fn load() -> Result<Catalog, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let mut components = Vec::new();
+  for installation in flatpak::all_installations()? {
+    let id = InstallationId::of(&installation);
+    let remotes = match installation.list_remotes(cancellable) {
+      Ok(remotes) => remotes,
+      Err(error) => {
+        warn!("Unable to list remotes of installation {}: {}", id, error);
+        continue;
+      }
+    };
+    for remote in remotes.iter().filter(|remote| !remote.is_disabled()) {
+      match load_remote(&id, remote) {
+        Ok(loaded) => components.extend(loaded),
+        Err(error) => warn!(
+          "Unable to load the catalog of {}: {}",
+          remote.name().unwrap_or_default(),
+          error
+        ),
+      }
+    }
+  }
+  Ok(Catalog { components })
+}
+
+/// Load the catalog of the remote `name` of `installation` from the cache,
+/// see [`load`].
+pub(crate) fn load_from(installation: &Installation, name: &str) -> Result<Catalog, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let remote = installation.remote_by_name(name, cancellable)?;
+  let components = load_remote(&InstallationId::of(installation), &remote)?;
+  Ok(Catalog { components })
+}
+
+fn load_remote(id: &InstallationId, remote: &Remote) -> Result<Vec<Component>, Error> {
+  let name = remote.name().map(String::from).unwrap_or_default();
+  let Some(dir) = remote.appstream_dir(None).and_then(|dir| dir.path()) else {
+    return Ok(vec![]);
+  };
+  let compressed = dir.join("appstream.xml.gz");
+  let xml = if compressed.exists() {
+    read_gzip(&compressed)?
+  } else if dir.join("appstream.xml").exists() {
+    std::fs::read_to_string(dir.join("appstream.xml")).map_err(io_error)?
+  } else {
+    debug!("No cached catalog for {}", name);
+    return Ok(vec![]);
+  };
+
+  parse(&xml, Some(id), &name, Some(&dir.join("icons")))
+}
+
+fn read_gzip(path: &Path) -> Result<String, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let stream = File::for_path(path).read(cancellable)?;
+  let decompressor = ZlibDecompressor::new(ZlibCompressorFormat::Gzip);
+  let mut xml = String::new();
+  ConverterInputStream::new(&stream, &decompressor)
+    .into_read()
+    .read_to_string(&mut xml)
+    .map_err(io_error)?;
+  Ok(xml)
+}
+
+fn io_error(error: std::io::Error) -> Error {
+  Error::new(IOErrorEnum::Failed, &error.to_string())
+}
+
+/// Download the latest catalog of `remote` to the cache of the installation
+/// `id`, where [`load`] reads it.
+pub fn refresh(id: &InstallationId, remote: &str) -> Result<(), Error> {
+  let cancellable: Option<&Cancellable> = None;
+  flatpak::open(id)?.update_appstream_sync(remote, None, cancellable)
+}
+
+/// Parse an AppStream catalog, in the language of the user. Cached icons
+/// are looked for in `icons_dir`.
+pub fn parse(
+  xml: &str,
+  installation: Option<&InstallationId>,
+  remote: &str,
+  icons_dir: Option<&Path>,
+) -> Result<Vec<Component>, Error> {
+  let document = Document::parse(xml)
+    .map_err(|error| Error::new(IOErrorEnum::InvalidData, &error.to_string()))?;
+  let languages: Vec<String> = glib::language_names()
+    .iter()
+    .map(|language| language.to_string())
+    .collect();
+
+  Ok(
+    document
+      .root_element()
+      .children()
+      .filter(|node| node.has_tag_name("component"))
+      .filter_map(|node| {
+        let mut component = parse_component(node, &languages, icons_dir)?;
+        component.installation = installation.cloned();
+        component.remote = remote.to_string();
+        Some(component)
+      })
+      .collect(),
+  )
+}
+
+fn parse_component(
+  node: Node,
+  languages: &[String],
+  icons_dir: Option<&Path>,
+) -> Option<Component> {
+  let id = text(child(node, "id")?)?;
+  let localized_text = |name: &str| {
+    localized(node.children().filter(|n| n.has_tag_name(name)), languages).and_then(text)
+  };
+
+  Some(Component {
+    id,
+    kind: node.attribute("type").unwrap_or("generic").to_string(),
+    installation: None,
+    remote: String::new(),
+    bundle: node
+      .children()
+      .find(|n| n.has_tag_name("bundle") && n.attribute("type") == Some("flatpak"))
+      .and_then(text),
+    name: localized_text("name"),
+    summary: localized_text("summary"),
+    description: description(node, languages),
+    categories: child(node, "categories")
+      .map(|categories| children_text(categories, "category"))
+      .unwrap_or_default(),
+    keywords: localized_keywords(node, languages),
+    screenshots: child(node, "screenshots")
+      .map(|screenshots| {
+        screenshots
+          .children()
+          .filter(|n| n.has_tag_name("screenshot"))
+          .map(|n| parse_screenshot(n, languages))
+          .collect()
+      })
+      .unwrap_or_default(),
+    releases: child(node, "releases")
+      .map(|releases| {
+        releases
+          .children()
+          .filter(|n| n.has_tag_name("release"))
+          .filter_map(|n| parse_release(n, languages))
+          .collect()
+      })
+      .unwrap_or_default(),
+    icons: node
+      .children()
+      .filter(|n| n.has_tag_name("icon"))
+      .filter_map(|n| parse_icon(n, icons_dir))
+      .collect(),
+    urls: node
+      .children()
+      .filter(|n| n.has_tag_name("url"))
+      .filter_map(|n| {
+        Some((
+          n.attribute("type").unwrap_or("homepage").to_string(),
+          text(n)?,
+        ))
+      })
+      .collect(),
+    content_rating: child(node, "content_rating").map(parse_content_rating),
+    project_license: child(node, "project_license").and_then(text),
+    developer: child(node, "developer")
+      .and_then(|developer| {
+        localized(
+          developer.children().filter(|n| n.has_tag_name("name")),
+          languages,
+        )
+      })
+      .or_else(|| {
+        // Before AppStream 1.0.
+        localized(
+          node.children().filter(|n| n.has_tag_name("developer_name")),
+          languages,
+        )
+      })
+      .and_then(text),
+    provides: child(node, "provides")
+      .map(|provides| {
+        provides
+          .children()
+          .filter(Node::is_element)
+          .filter_map(|n| {
+            Some(Provided {
+              kind: n.tag_name().name().to_string(),
+              value: text(n)?,
+            })
+          })
+          .collect()
+      })
+      .unwrap_or_default(),
+  })
+}
+
+fn parse_screenshot(node: Node, languages: &[String]) -> Screenshot {
+  Screenshot {
+    default: node.attribute("type") == Some("default"),
+    caption: localized(
+      node.children().filter(|n| n.has_tag_name("caption")),
+      languages,
+    )
+    .and_then(text),
+    images: node
+      .children()
+      .filter(|n| n.has_tag_name("image"))
+      .filter_map(|n| {
+        Some(Image {
+          url: text(n)?,
+          thumbnail: n.attribute("type") == Some("thumbnail"),
+          width: n.attribute("width").and_then(|w| w.parse().ok()),
+          height: n.attribute("height").and_then(|h| h.parse().ok()),
+        })
+      })
+      .collect(),
+  }
+}
+
+fn parse_release(node: Node, languages: &[String]) -> Option<Release> {
+  Some(Release {
+    version: node.attribute("version")?.to_string(),
+    timestamp: node
+      .attribute("timestamp")
+      .and_then(|t| t.parse().ok())
+      .or_else(|| {
+        let date = node.attribute("date")?;
+        // Dates may omit the time.
+        let date = if date.contains('T') {
+          date.to_string()
+        } else {
+          format!("{}T00:00:00Z", date)
+        };
+        glib::DateTime::from_iso8601(&date, Some(&glib::TimeZone::utc()))
+          .ok()
+          .map(|date| date.to_unix())
+      }),
+    kind: node.attribute("type").map(String::from),
+    urgency: node.attribute("urgency").map(String::from),
+    description: description(node, languages),
+  })
+}
+
+fn parse_icon(node: Node, icons_dir: Option<&Path>) -> Option<Icon> {
+  let value = text(node)?;
+  let width: u32 = node
+    .attribute("width")
+    .and_then(|w| w.parse().ok())
+    .unwrap_or(0);
+  let height: u32 = node
+    .attribute("height")
+    .and_then(|h| h.parse().ok())
+    .unwrap_or(0);
+  let scale: u32 = node
+    .attribute("scale")
+    .and_then(|s| s.parse().ok())
+    .unwrap_or(1);
+  let source = match node.attribute("type")? {
+    "cached" => {
+      let size = if scale > 1 {
+        format!("{}x{}@{}", width, height, scale)
+      } else {
+        format!("{}x{}", width, height)
+      };
+      IconSource::Cached(icons_dir?.join(size).join(value))
+    }
+    "remote" => IconSource::Remote(value),
+    "stock" => IconSource::Stock(value),
+    _ => return None,
+  };
+  Some(Icon {
+    source,
+    width,
+    height,
+    scale,
+  })
+}
+
+fn parse_content_rating(node: Node) -> ContentRating {
+  ContentRating {
+    kind: node.attribute("type").map(String::from),
+    attributes: node
+      .children()
+      .filter(|n| n.has_tag_name("content_attribute"))
+      .filter_map(|n| Some((n.attribute("id")?.to_string(), text(n)?)))
+      .collect(),
+  }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+  node.children().find(|n| n.has_tag_name(name))
+}
+
+/// The text of `node` and its descendants, with whitespace collapsed.
+fn text(node: Node) -> Option<String> {
+  let raw: String = node
+    .descendants()
+    .filter(Node::is_text)
+    .filter_map(|n| n.text())
+    .collect();
+  let text = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+  (!text.is_empty()).then_some(text)
+}
+
+fn children_text(node: Node, name: &str) -> Vec<String> {
+  node
+    .children()
+    .filter(|n| n.has_tag_name(name))
+    .filter_map(text)
+    .collect()
+}
+
+/// How well `node` matches the languages of the user, lower is better, if
+/// at all. Untranslated nodes match the `C` locale, always listed last.
+fn language_rank(node: Node, languages: &[String]) -> Option<usize> {
+  let language = node.attribute((XML_NAMESPACE, "lang")).unwrap_or("C");
+  languages
+    .iter()
+    .position(|l| l == language)
+    .or_else(|| (language == "C").then_some(languages.len()))
+}
+
+/// The node in the best language among `nodes`, translations of one
+/// another.
+fn localized<'a, 'input>(
+  nodes: impl Iterator<Item = Node<'a, 'input>>,
+  languages: &[String],
+) -> Option<Node<'a, 'input>> {
+  nodes
+    .filter_map(|n| Some((language_rank(n, languages)?, n)))
+    .min_by_key(|(rank, _)| *rank)
+    .map(|(_, n)| n)
+}
+
+fn localized_keywords(node: Node, languages: &[String]) -> Vec<String> {
+  let Some(keywords) = child(node, "keywords") else {
+    return vec![];
+  };
+  let all: Vec<Node> = keywords
+    .children()
+    .filter(|n| n.has_tag_name("keyword"))
+    .collect();
+  let Some(best) = all
+    .iter()
+    .filter_map(|n| language_rank(*n, languages))
+    .min()
+  else {
+    return vec![];
+  };
+  all
+    .into_iter()
+    .filter(|n| language_rank(*n, languages) == Some(best))
+    .filter_map(text)
+    .collect()
+}
+
+/// The description of `node` as plain text. Descriptions are either
+/// translated as a whole, or paragraph by paragraph in older catalogs.
+fn description(node: Node, languages: &[String]) -> Option<String> {
+  let description = localized(
+    node.children().filter(|n| n.has_tag_name("description")),
+    languages,
+  )?;
+  let blocks: Vec<Node> = description.children().filter(Node::is_element).collect();
+  let is_list = |n: &Node| n.has_tag_name("ul") || n.has_tag_name("ol");
+  // Paragraphs and list items are translated, not lists.
+  let best = blocks
+    .iter()
+    .flat_map(|n| if is_list(n) { list_items(*n) } else { vec![*n] })
+    .filter_map(|n| language_rank(n, languages))
+    .min()?;
+
+  let paragraphs: Vec<String> = blocks
+    .into_iter()
+    .filter_map(|block| {
+      if is_list(&block) {
+        let items: Vec<String> = list_items(block)
+          .into_iter()
+          .filter(|n| language_rank(*n, languages) == Some(best))
+          .filter_map(text)
+          .map(|item| format!("• {}", item))
+          .collect();
+        (!items.is_empty()).then(|| items.join("\n"))
+      } else if language_rank(block, languages) == Some(best) {
+        text(block)
+      } else {
+        None
+      }
+    })
+    .collect();
+  (!paragraphs.is_empty()).then(|| paragraphs.join("\n\n"))
+}
+
+fn list_items<'a, 'input>(list: Node<'a, 'input>) -> Vec<Node<'a, 'input>> {
+  list.children().filter(|n| n.has_tag_name("li")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CATALOG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<components version="0.8" origin="flathub">
+  <component type="desktop-application">
+    <id>org.gnome.Maps</id>
+    <name>Maps</name>
+    <name xml:lang="fr">Cartes</name>
+    <summary>Find places
+      around the world</summary>
+    <summary xml:lang="fr">Trouver des lieux</summary>
+    <bundle type="flatpak">app/org.gnome.Maps/x86_64/stable</bundle>
+    <icon type="stock">org.gnome.Maps</icon>
+    <icon type="cached" width="64" height="64">org.gnome.Maps.png</icon>
+    <icon type="cached" width="64" height="64" scale="2">org.gnome.Maps.png</icon>
+    <icon type="remote" width="128" height="128">https://example.org/maps.png</icon>
+  </component>
+  <component>
+    <id>org.example.Bare</id>
+  </component>
+  <component type="desktop-application">
+    <name>No id</name>
+  </component>
+  <component type="desktop-application">
+    <id>  </id>
+    <name>Blank id</name>
+  </component>
+  <component type="desktop-application">
+    <id>org.example.Broken</id>
+    <name></name>
+    <icon>no-type</icon>
+    <icon type="unknown">unknown</icon>
+    <icon type="cached" width="big">org.example.Broken.png</icon>
+    <icon type="remote"></icon>
+  </component>
+</components>"#;
+
+  fn parse_in(xml: &str, languages: &[&str]) -> Vec<Component> {
+    let document = Document::parse(xml).unwrap();
+    let languages: Vec<String> = languages.iter().map(|l| l.to_string()).collect();
+    document
+      .root_element()
+      .children()
+      .filter(|node| node.has_tag_name("component"))
+      .filter_map(|node| parse_component(node, &languages, Some(Path::new("/icons"))))
+      .collect()
+  }
+
+  #[test]
+  fn parses_components() {
+    let components = parse(CATALOG, None, "flathub", Some(Path::new("/icons"))).unwrap();
+    let ids: Vec<&str> = components.iter().map(|c| c.id.as_str()).collect();
+    assert_eq!(
+      ids,
+      ["org.gnome.Maps", "org.example.Bare", "org.example.Broken"]
+    );
+    assert!(components.iter().all(|c| c.remote == "flathub"));
+
+    let maps = &components[0];
+    assert_eq!(maps.kind, "desktop-application");
+    assert_eq!(
+      maps.bundle.as_deref(),
+      Some("app/org.gnome.Maps/x86_64/stable")
+    );
+  }
+
+  #[test]
+  fn parses_names_and_summaries() {
+    let components = parse_in(CATALOG, &["C"]);
+    assert_eq!(components[0].name.as_deref(), Some("Maps"));
+    assert_eq!(
+      components[0].summary.as_deref(),
+      Some("Find places around the world")
+    );
+  }
+
+  #[test]
+  fn parses_icons() {
+    let components = parse_in(CATALOG, &["C"]);
+    let icons: Vec<&IconSource> = components[0].icons.iter().map(|i| &i.source).collect();
+    assert_eq!(
+      icons,
+      [
+        &IconSource::Stock("org.gnome.Maps".to_string()),
+        &IconSource::Cached(PathBuf::from("/icons/64x64/org.gnome.Maps.png")),
+        &IconSource::Cached(PathBuf::from("/icons/64x64@2/org.gnome.Maps.png")),
+        &IconSource::Remote("https://example.org/maps.png".to_string()),
+      ]
+    );
+    assert_eq!(components[0].icons[3].width, 128);
+    assert_eq!(components[0].icons[0].scale, 1);
+    assert!(matches!(
+      components[0].icon(100).map(|i| &i.source),
+      Some(IconSource::Cached(path)) if path.ends_with("64x64@2/org.gnome.Maps.png")
+    ));
+  }
+
+  #[test]
+  fn prefers_the_language_of_the_user() {
+    let components = parse_in(CATALOG, &["fr", "C"]);
+    assert_eq!(components[0].name.as_deref(), Some("Cartes"));
+    assert_eq!(components[0].summary.as_deref(), Some("Trouver des lieux"));
+
+    let components = parse_in(CATALOG, &["de", "C"]);
+    assert_eq!(components[0].name.as_deref(), Some("Maps"));
+  }
+
+  #[test]
+  fn tolerates_missing_fields() {
+    let components = parse_in(CATALOG, &["C"]);
+    let bare = &components[1];
+    assert_eq!(bare.kind, "generic");
+    assert_eq!(bare.name, None);
+    assert_eq!(bare.summary, None);
+    assert_eq!(bare.bundle, None);
+    assert!(bare.icons.is_empty());
+    assert_eq!(bare.display_name(), "org.example.Bare");
+  }
+
+  #[test]
+  fn skips_malformed_fields() {
+    let components = parse_in(CATALOG, &["C"]);
+    let broken = &components[2];
+    assert_eq!(broken.name, None);
+    assert_eq!(
+      broken.icons,
+      [Icon {
+        source: IconSource::Cached(PathBuf::from("/icons/0x0/org.example.Broken.png")),
+        width: 0,
+        height: 0,
+        scale: 1,
+      }]
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_xml() {
+    assert!(parse("<components><component>", None, "flathub", None).is_err());
+    assert!(parse("", None, "flathub", None).is_err());
+  }
+}
@@ -1,36 +1,543 @@
+pub mod file;
+pub mod permissions;
+pub mod remote;
+pub mod transaction;
+
+use std::{
+  collections::{BTreeMap, HashMap},
+  ffi::CStr,
+  fmt,
+  path::PathBuf,
+};
+
 use adw::{
-  gio::{prelude::FileMonitorExt, Cancellable},
-  glib::Error,
+  gio::{prelude::FileExt, Cancellable},
+  glib::{self, translate::ToGlibPtr, Error},
 };
 use libflatpak::{
-  prelude::{InstallationExt, InstalledRefExt},
-  Installation,
+  prelude::{InstallationExt, InstalledRefExt, RefExt, RemoteRefExt},
+  Installation, InstalledRef,
 };
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::services::catalog::{self, Catalog};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefKind {
+  App,
+  Runtime,
+}
+
+impl RefKind {
+  fn from_flatpak(kind: libflatpak::RefKind) -> Self {
+    match kind {
+      libflatpak::RefKind::App => RefKind::App,
+      _ => RefKind::Runtime,
+    }
+  }
+
+  fn to_flatpak(self) -> libflatpak::RefKind {
+    match self {
+      RefKind::App => libflatpak::RefKind::App,
+      RefKind::Runtime => libflatpak::RefKind::Runtime,
+    }
+  }
+}
+
+impl fmt::Display for RefKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      RefKind::App => write!(f, "app"),
+      RefKind::Runtime => write!(f, "runtime"),
+    }
+  }
+}
+
+/// An installation refs are deployed to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InstallationId {
+  /// The per-user installation, in `~/.local/share/flatpak`.
+  User,
+  /// A system installation: `default`, in `/var/lib/flatpak`, or one
+  /// configured under `/etc/flatpak/installations.d`.
+  System(String),
+}
+
+impl InstallationId {
+  pub fn default_system() -> Self {
+    InstallationId::System("default".to_string())
+  }
+
+  pub(crate) fn of(installation: &Installation) -> Self {
+    if installation.is_user() {
+      InstallationId::User
+    } else {
+      InstallationId::System(
+        installation
+          .id()
+          .map(String::from)
+          .unwrap_or_else(|| "default".to_string()),
+      )
+    }
+  }
+}
+
+impl fmt::Display for InstallationId {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      InstallationId::User => write!(f, "user"),
+      InstallationId::System(id) => write!(f, "{}", id),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InstallationInfo {
+  pub id: InstallationId,
+  pub display_name: Option<String>,
+  pub path: Option<String>,
+  /// Installations with a higher priority are preferred.
+  pub priority: i32,
+}
 
-#[derive(Clone, Debug)]
+/// OARS content rating of an app, e.g. `violence-cartoon` → `mild`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContentRating {
+  /// Version of the rating system, e.g. `oars-1.1`.
+  pub kind: Option<String>,
+  pub attributes: BTreeMap<String, String>,
+}
+
+/// An installed ref with a newer commit on its remote.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingUpdate {
+  /// The deployed ref, with the current commit and version.
+  pub installed: FpRef,
+  /// The commit which will be deployed.
+  pub commit: Option<String>,
+  /// The version which will be deployed, when the appdata of the remote is
+  /// known.
+  pub new_version: Option<String>,
+  /// In bytes, 0 when unknown.
+  pub download_size: u64,
+  /// In bytes, once updated, 0 when unknown.
+  pub installed_size: u64,
+}
+
+/// An installed app or runtime.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct FpRef {
-  pub name: String,
-  pub summary: String,
-  pub version: String,
+  /// The id of the app or runtime, e.g. `org.gnome.Maps`.
+  pub id: String,
+  pub installation: InstallationId,
+  pub kind: RefKind,
+  pub arch: String,
+  pub branch: String,
+  /// The remote it was installed from.
+  pub origin: Option<String>,
+  /// The deployed commit.
+  pub commit: Option<String>,
+  /// The latest commit pulled from the remote, which may not be deployed.
+  pub latest_commit: Option<String>,
+  /// In bytes.
+  pub installed_size: u64,
+  pub deploy_dir: Option<String>,
+  /// Why the ref is no longer maintained, if it isn't.
+  pub end_of_life: Option<String>,
+  /// The ref replacing this one, if it's end of life.
+  pub end_of_life_rebase: Option<String>,
+  pub name: Option<String>,
+  pub summary: Option<String>,
+  pub version: Option<String>,
+  pub license: Option<String>,
+  pub content_rating: Option<ContentRating>,
+}
+
+impl FpRef {
+  /// The full ref, e.g. `app/org.gnome.Maps/x86_64/stable`.
+  pub fn format_ref(&self) -> String {
+    format!("{}/{}/{}/{}", self.kind, self.id, self.arch, self.branch)
+  }
+
+  /// The appdata name, or the id when there is no appdata.
+  pub fn display_name(&self) -> &str {
+    self.name.as_deref().unwrap_or(&self.id)
+  }
+
+  pub fn is_end_of_life(&self) -> bool {
+    self.end_of_life.is_some() || self.end_of_life_rebase.is_some()
+  }
+
+  /// Where an app keeps the data of the user, `~/.var/app/<id>`. Runtimes
+  /// have none.
+  pub fn data_dir(&self) -> Option<PathBuf> {
+    match self.kind {
+      RefKind::App => Some(glib::home_dir().join(".var").join("app").join(&self.id)),
+      RefKind::Runtime => None,
+    }
+  }
+
+  fn from_installed(installation: &InstallationId, installed: &InstalledRef) -> Self {
+    let string = |s: Option<adw::glib::GString>| s.map(|s| s.to_string()).filter(|s| !s.is_empty());
+    FpRef {
+      id: installed.name().map(String::from).unwrap_or_default(),
+      installation: installation.clone(),
+      kind: RefKind::from_flatpak(installed.kind()),
+      arch: installed.arch().map(String::from).unwrap_or_default(),
+      branch: installed.branch().map(String::from).unwrap_or_default(),
+      origin: string(installed.origin()),
+      commit: string(installed.commit()),
+      latest_commit: string(installed.latest_commit()),
+      installed_size: installed.installed_size(),
+      deploy_dir: string(installed.deploy_dir()),
+      end_of_life: string(installed.eol()),
+      end_of_life_rebase: string(installed.eol_rebase()),
+      name: string(installed.appdata_name()),
+      summary: string(installed.appdata_summary()),
+      version: string(installed.appdata_version()),
+      license: string(installed.appdata_license()),
+      content_rating: content_rating(installed),
+    }
+  }
+}
+
+fn content_rating(installed: &InstalledRef) -> Option<ContentRating> {
+  let mut attributes = BTreeMap::new();
+  // Not covered by the bindings: a table of strings owned by the ref.
+  #[allow(unsafe_code)]
+  unsafe {
+    use libflatpak::glib::ffi::{g_hash_table_iter_init, g_hash_table_iter_next, GHashTableIter};
+
+    let table =
+      libflatpak::ffi::flatpak_installed_ref_get_appdata_content_rating(installed.to_glib_none().0);
+    if !table.is_null() {
+      let mut iter = std::mem::MaybeUninit::<GHashTableIter>::uninit();
+      g_hash_table_iter_init(iter.as_mut_ptr(), table);
+      let mut iter = iter.assume_init();
+      let mut key = std::ptr::null_mut();
+      let mut value = std::ptr::null_mut();
+      while g_hash_table_iter_next(&mut iter, &mut key, &mut value) != 0 {
+        if key.is_null() || value.is_null() {
+          continue;
+        }
+        attributes.insert(
+          CStr::from_ptr(key as *const _)
+            .to_string_lossy()
+            .into_owned(),
+          CStr::from_ptr(value as *const _)
+            .to_string_lossy()
+            .into_owned(),
+        );
+      }
+    }
+  }
+
+  let kind = installed.appdata_content_rating_type().map(String::from);
+  if kind.is_none() && attributes.is_empty() {
+    None
+  } else {
+    Some(ContentRating { kind, attributes })
+  }
+}
+
+/// The user installation, then the system ones by decreasing priority.
+pub(crate) fn all_installations() -> Result<Vec<Installation>, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let mut installations = vec![Installation::new_user(cancellable)?];
+  let mut system = libflatpak::system_installations(cancellable)?;
+  system.sort_by_key(|installation| -installation.priority());
+  installations.extend(system);
+  Ok(installations)
+}
+
+/// Describe the installations of the machine, see [`open`].
+pub fn installations() -> Result<Vec<InstallationInfo>, Error> {
+  Ok(
+    all_installations()?
+      .iter()
+      .map(|installation| InstallationInfo {
+        id: InstallationId::of(installation),
+        display_name: installation.display_name().map(String::from),
+        path: installation
+          .path()
+          .and_then(|path| path.path())
+          .map(|path| path.display().to_string()),
+        priority: installation.priority(),
+      })
+      .collect(),
+  )
 }
 
+/// Open the installation `id`, to run operations on it.
+pub fn open(id: &InstallationId) -> Result<Installation, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  match id {
+    InstallationId::User => Installation::new_user(cancellable),
+    InstallationId::System(id) if id == "default" => Installation::new_system(cancellable),
+    InstallationId::System(id) => Installation::new_system_with_id(Some(id), cancellable),
+  }
+}
+
+/// Collect what `list` returns for every installation. Installations which
+/// can't be read are skipped, unless none can.
+fn list_all<T, L>(list: L) -> Result<Vec<T>, Error>
+where
+  L: Fn(&Installation) -> Result<Vec<T>, Error>,
+{
+  let mut items = Vec::new();
+  let mut first_error = None;
+  let mut listed = false;
+  for installation in all_installations()? {
+    match list(&installation) {
+      Ok(listed_items) => {
+        items.extend(listed_items);
+        listed = true;
+      }
+      Err(error) => {
+        warn!(
+          "Unable to read installation {}: {}",
+          InstallationId::of(&installation),
+          error
+        );
+        first_error.get_or_insert(error);
+      }
+    }
+  }
+
+  match first_error {
+    Some(error) if !listed => Err(error),
+    _ => Ok(items),
+  }
+}
+
+/// List the refs installed in every installation.
 pub fn list() -> Result<Vec<FpRef>, Error> {
+  list_all(list_installation)
+}
+
+/// List the refs installed in the installation `id`.
+pub fn list_in(id: &InstallationId) -> Result<Vec<FpRef>, Error> {
+  list_installation(&open(id)?)
+}
+
+fn list_installation(installation: &Installation) -> Result<Vec<FpRef>, Error> {
   let cancellable: Option<&Cancellable> = None;
-  let installation = Installation::new_system(cancellable)?;
+  let id = InstallationId::of(installation);
   let refs = installation.list_installed_refs(cancellable)?;
 
-  let refs = refs
-    .iter()
-    .map(|f| FpRef {
-      name: f.appdata_name().map_or("".to_string(), |s| s.to_string()),
-      summary: f
-        .appdata_summary()
-        .map_or("".to_string(), |s| s.to_string()),
-      version: f
-        .appdata_version()
-        .map_or("".to_string(), |s| s.to_string()),
-    })
-    .collect();
-
-  Ok(refs)
+  Ok(
+    refs
+      .iter()
+      .map(|installed| FpRef::from_installed(&id, installed))
+      .collect(),
+  )
+}
+
+/// List the runtimes and extensions of the installation `id` which no
+/// installed app needs anymore.
+pub fn unused_in(id: &InstallationId) -> Result<Vec<FpRef>, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let refs = open(id)?.list_unused_refs(None, cancellable)?;
+
+  Ok(
+    refs
+      .iter()
+      .map(|installed| FpRef::from_installed(id, installed))
+      .collect(),
+  )
+}
+
+/// List the refs with an update in every installation. This fetches the
+/// latest commits from the remotes.
+pub fn updates() -> Result<Vec<PendingUpdate>, Error> {
+  list_all(list_updates)
+}
+
+/// List the refs with an update in the installation `id`.
+pub fn updates_in(id: &InstallationId) -> Result<Vec<PendingUpdate>, Error> {
+  list_updates(&open(id)?)
+}
+
+fn list_updates(installation: &Installation) -> Result<Vec<PendingUpdate>, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let id = InstallationId::of(installation);
+  let refs = installation.list_installed_refs_for_update(cancellable)?;
+  let mut catalogs: HashMap<String, Catalog> = HashMap::new();
+
+  Ok(
+    refs
+      .iter()
+      .map(|installed| {
+        let installed = FpRef::from_installed(&id, installed);
+        // Sizes are only known from the remote, the latest commit is also
+        // known locally.
+        let remote = installed.origin.as_deref().and_then(|origin| {
+          installation
+            .fetch_remote_ref_sync(
+              origin,
+              installed.kind.to_flatpak(),
+              &installed.id,
+              Some(&installed.arch),
+              Some(&installed.branch),
+              cancellable,
+            )
+            .inspect_err(|error| warn!("Unable to fetch {}: {}", installed.format_ref(), error))
+            .ok()
+        });
+        // New versions are only known from the catalogs of the remotes.
+        let new_version = installed.origin.as_deref().and_then(|origin| {
+          let catalog = catalogs.entry(origin.to_string()).or_insert_with(|| {
+            catalog::load_from(installation, origin).unwrap_or_else(|error| {
+              warn!("Unable to load the catalog of {}: {}", origin, error);
+              Catalog::default()
+            })
+          });
+          let version = catalog.by_bundle(&installed.format_ref())?.version()?;
+          // A stale catalog still lists the installed version.
+          (installed.version.as_deref() != Some(version)).then(|| version.to_string())
+        });
+        PendingUpdate {
+          commit: remote
+            .as_ref()
+            .and_then(|remote| remote.commit())
+            .map(String::from)
+            .or_else(|| installed.latest_commit.clone()),
+          new_version,
+          download_size: remote.as_ref().map_or(0, |remote| remote.download_size()),
+          installed_size: remote.as_ref().map_or(0, |remote| remote.installed_size()),
+          installed,
+        }
+      })
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::PathBuf;
+
+  use adw::{gio::File, glib::Object};
+
+  use super::*;
+
+  /// An empty installation in a directory of its own.
+  pub(super) fn installation(name: &str, user: bool) -> (Installation, PathBuf) {
+    let path = std::env::temp_dir().join(format!("rouge-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&path).unwrap();
+    let cancellable: Option<&Cancellable> = None;
+    let installation = Installation::for_path(&File::for_path(&path), user, cancellable).unwrap();
+    (installation, path)
+  }
+
+  fn installed_ref(name: &str, appdata_name: &str, eol: Option<&str>) -> InstalledRef {
+    Object::builder()
+      .property("kind", libflatpak::RefKind::App)
+      .property("name", name)
+      .property("arch", "x86_64")
+      .property("branch", "stable")
+      .property("commit", "4c5e")
+      .property("origin", "flathub")
+      .property("latest-commit", "")
+      .property("installed-size", 4096u64)
+      .property("eol", eol)
+      .property("appdata-name", appdata_name)
+      .property("appdata-summary", "Find places around the world")
+      .property("appdata-version", "47.0")
+      .build()
+  }
+
+  #[test]
+  fn describes_installed_refs() {
+    let fp_ref = FpRef::from_installed(
+      &InstallationId::User,
+      &installed_ref("org.gnome.Maps", "Maps", None),
+    );
+    assert_eq!(fp_ref.id, "org.gnome.Maps");
+    assert_eq!(fp_ref.kind, RefKind::App);
+    assert_eq!(fp_ref.format_ref(), "app/org.gnome.Maps/x86_64/stable");
+    assert_eq!(fp_ref.origin.as_deref(), Some("flathub"));
+    assert_eq!(fp_ref.commit.as_deref(), Some("4c5e"));
+    assert_eq!(fp_ref.installed_size, 4096);
+    assert_eq!(fp_ref.display_name(), "Maps");
+    assert_eq!(
+      fp_ref.summary.as_deref(),
+      Some("Find places around the world")
+    );
+    assert_eq!(fp_ref.version.as_deref(), Some("47.0"));
+    assert!(!fp_ref.is_end_of_life());
+  }
+
+  #[test]
+  fn leaves_out_empty_strings() {
+    let fp_ref = FpRef::from_installed(
+      &InstallationId::User,
+      &installed_ref("org.gnome.Maps", "", None),
+    );
+    assert_eq!(fp_ref.latest_commit, None);
+    assert_eq!(fp_ref.name, None);
+    assert_eq!(fp_ref.display_name(), "org.gnome.Maps");
+    assert_eq!(fp_ref.content_rating, None);
+  }
+
+  #[test]
+  fn tells_end_of_life_refs() {
+    let fp_ref = FpRef::from_installed(
+      &InstallationId::User,
+      &installed_ref(
+        "org.gnome.Maps",
+        "Maps",
+        Some("Replaced by org.gnome.Maps2"),
+      ),
+    );
+    assert!(fp_ref.is_end_of_life());
+  }
+
+  #[test]
+  fn tags_refs_with_their_installation() {
+    let system = InstallationId::System("extra".to_string());
+    let fp_ref = FpRef::from_installed(&system, &installed_ref("org.gnome.Maps", "Maps", None));
+    assert_eq!(fp_ref.installation, system);
+  }
+
+  #[test]
+  fn names_installations() {
+    assert_eq!(InstallationId::User.to_string(), "user");
+    assert_eq!(InstallationId::default_system().to_string(), "default");
+    assert_eq!(
+      InstallationId::System("extra".to_string()).to_string(),
+      "extra"
+    );
+  }
+
+  #[test]
+  fn tells_user_installations_from_system_ones() {
+    let (user, user_path) = installation("user", true);
+    assert_eq!(InstallationId::of(&user), InstallationId::User);
+    assert_eq!(list_installation(&user).unwrap(), vec![]);
+
+    let (system, system_path) = installation("system", false);
+    assert!(matches!(
+      InstallationId::of(&system),
+      InstallationId::System(_)
+    ));
+
+    std::fs::remove_dir_all(user_path).unwrap();
+    std::fs::remove_dir_all(system_path).unwrap();
+  }
+
+  #[test]
+  fn keeps_data_of_apps_only() {
+    let app = FpRef::from_installed(
+      &InstallationId::User,
+      &installed_ref("org.gnome.Maps", "Maps", None),
+    );
+    assert!(app.data_dir().unwrap().ends_with(".var/app/org.gnome.Maps"));
+
+    let runtime = FpRef {
+      kind: RefKind::Runtime,
+      ..app
+    };
+    assert_eq!(runtime.data_dir(), None);
+  }
 }
@@ -0,0 +1,301 @@
+use std::path::{Path, PathBuf};
+
+use adw::{
+  gio::{prelude::FileExtManual, Cancellable, File, IOErrorEnum},
+  glib::{Bytes, Error, KeyFile, KeyFileFlags},
+};
+use libflatpak::{
+  prelude::{BundleRefExt, InstallationExt, RefExt, RemoteExt, RemoteRefExt},
+  BundleRef, Installation,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{all_installations, permissions::Permissions, RefKind};
+
+const FLATPAKREF_GROUP: &str = "Flatpak Ref";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefFileKind {
+  /// A `.flatpakref`, pointing to a ref in a repository.
+  Ref,
+  /// A `.flatpak` bundle, containing the ref itself.
+  Bundle,
+}
+
+/// What a file opened by the user would install, see [`read`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RefFile {
+  pub path: PathBuf,
+  pub kind: RefFileKind,
+  /// The id of the app or runtime, e.g. `org.gnome.Maps`.
+  pub id: String,
+  pub ref_kind: RefKind,
+  pub branch: Option<String>,
+  pub title: Option<String>,
+  /// The remote the ref is installed from, added when installing if needed.
+  pub remote: Option<String>,
+  /// The URL of the repository.
+  pub url: Option<String>,
+  /// The runtime, e.g. `org.gnome.Platform/x86_64/47`, when known.
+  pub runtime: Option<String>,
+  /// The `.flatpakrepo` of the repository providing the runtime.
+  pub runtime_repo: Option<String>,
+  /// In bytes, 0 when unknown.
+  pub installed_size: u64,
+  /// The permissions of the metadata, when known.
+  pub permissions: Option<Permissions>,
+}
+
+impl RefFile {
+  /// The title, or the id when there is none.
+  pub fn display_name(&self) -> &str {
+    self.title.as_deref().unwrap_or(&self.id)
+  }
+}
+
+/// Whether `path` is a file [`read`] understands, by its extension.
+pub fn is_ref_file(path: &Path) -> bool {
+  kind_of(path).is_some()
+}
+
+fn kind_of(path: &Path) -> Option<RefFileKind> {
+  match path.extension()?.to_str()? {
+    "flatpakref" => Some(RefFileKind::Ref),
+    "flatpak" => Some(RefFileKind::Bundle),
+    _ => None,
+  }
+}
+
+/// Describe what the `.flatpakref` or `.flatpak` file at `path` would
+/// install.
+///
+/// Bundles carry their metadata. A `.flatpakref` only points to a ref, whose
+/// metadata is only known if its repository is already a remote of one of
+/// the installations.
+pub fn read(path: &Path) -> Result<RefFile, Error> {
+  match kind_of(path) {
+    Some(RefFileKind::Ref) => read_flatpakref(path, &all_installations()?),
+    Some(RefFileKind::Bundle) => read_bundle(path),
+    None => Err(Error::new(
+      IOErrorEnum::NotSupported,
+      &format!("{} isn't a .flatpakref or .flatpak file", path.display()),
+    )),
+  }
+}
+
+fn read_flatpakref(path: &Path, installations: &[Installation]) -> Result<RefFile, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let (contents, _) = File::for_path(path).load_contents(cancellable)?;
+  let keyfile = KeyFile::new();
+  keyfile.load_from_bytes(&Bytes::from(&contents[..]), KeyFileFlags::NONE)?;
+  let string = |key: &str| {
+    keyfile
+      .string(FLATPAKREF_GROUP, key)
+      .ok()
+      .map(String::from)
+      .filter(|s| !s.is_empty())
+  };
+
+  let id = keyfile.string(FLATPAKREF_GROUP, "Name")?.to_string();
+  let is_runtime = keyfile
+    .boolean(FLATPAKREF_GROUP, "IsRuntime")
+    .unwrap_or(false);
+  let mut file = RefFile {
+    path: path.to_path_buf(),
+    kind: RefFileKind::Ref,
+    id,
+    ref_kind: if is_runtime {
+      RefKind::Runtime
+    } else {
+      RefKind::App
+    },
+    branch: string("Branch"),
+    title: string("Title"),
+    remote: string("SuggestRemoteName"),
+    url: string("Url"),
+    runtime: None,
+    runtime_repo: string("RuntimeRepo"),
+    installed_size: 0,
+    permissions: None,
+  };
+
+  // The metadata is only known to remotes already configured.
+  let Some(url) = file.url.as_deref().map(|url| url.trim_end_matches('/')) else {
+    return Ok(file);
+  };
+  let mut remotes = Vec::new();
+  for installation in installations {
+    for remote in installation.list_remotes(cancellable)? {
+      remotes.push((installation, remote));
+    }
+  }
+  let remote = remotes
+    .into_iter()
+    .find(|(_, remote)| remote.url().as_deref().map(|u| u.trim_end_matches('/')) == Some(url));
+  if let Some((installation, remote)) =
+    remote.and_then(|(installation, remote)| Some((installation, remote.name()?)))
+  {
+    let fetched = installation.fetch_remote_ref_sync(
+      &remote,
+      file.ref_kind.to_flatpak(),
+      &file.id,
+      None,
+      file.branch.as_deref(),
+      cancellable,
+    );
+    if let Ok(fetched) = fetched {
+      file.installed_size = fetched.installed_size();
+      if let Some(metadata) = fetched.metadata() {
+        describe_metadata(&mut file, &metadata)?;
+      }
+    }
+    file.remote = Some(remote.to_string());
+  }
+  Ok(file)
+}
+
+fn read_bundle(path: &Path) -> Result<RefFile, Error> {
+  let bundle = BundleRef::new(&File::for_path(path))?;
+  let mut file = RefFile {
+    path: path.to_path_buf(),
+    kind: RefFileKind::Bundle,
+    id: bundle.name().map(String::from).unwrap_or_default(),
+    ref_kind: RefKind::from_flatpak(bundle.kind()),
+    branch: bundle.branch().map(String::from),
+    title: None,
+    remote: bundle.origin().map(String::from),
+    url: None,
+    runtime: None,
+    runtime_repo: bundle.runtime_repo_url().map(String::from),
+    installed_size: bundle.installed_size(),
+    permissions: None,
+  };
+  if let Some(metadata) = bundle.metadata() {
+    describe_metadata(&mut file, &metadata)?;
+  }
+  Ok(file)
+}
+
+/// Fill `file` with the runtime and permissions of the `metadata` keyfile.
+fn describe_metadata(file: &mut RefFile, metadata: &Bytes) -> Result<(), Error> {
+  let keyfile = KeyFile::new();
+  keyfile.load_from_bytes(metadata, KeyFileFlags::NONE)?;
+
+  let group = match file.ref_kind {
+    RefKind::App => "Application",
+    RefKind::Runtime => "Runtime",
+  };
+  file.runtime = keyfile.string(group, "runtime").ok().map(String::from);
+  file.permissions = Some(Permissions::parse(metadata)?);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Write `contents` to a file named `name` in a directory of its own.
+  fn write(name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rouge-file-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+  }
+
+  #[test]
+  fn tells_ref_files_by_their_extension() {
+    assert!(is_ref_file(Path::new("/tmp/org.gnome.Maps.flatpakref")));
+    assert!(is_ref_file(Path::new("/tmp/org.gnome.Maps.flatpak")));
+    assert!(!is_ref_file(Path::new("/tmp/flathub.flatpakrepo")));
+    assert!(!is_ref_file(Path::new("/tmp/flatpak")));
+  }
+
+  #[test]
+  fn reads_flatpakrefs() {
+    let path = write(
+      "maps.flatpakref",
+      "[Flatpak Ref]\n\
+       Name=org.gnome.Maps\n\
+       Branch=stable\n\
+       Title=Maps\n\
+       Url=https://dl.flathub.org/repo/\n\
+       SuggestRemoteName=flathub\n\
+       RuntimeRepo=https://dl.flathub.org/repo/flathub.flatpakrepo\n\
+       IsRuntime=false\n",
+    );
+    let file = read_flatpakref(&path, &[]).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(file.kind, RefFileKind::Ref);
+    assert_eq!(file.id, "org.gnome.Maps");
+    assert_eq!(file.ref_kind, RefKind::App);
+    assert_eq!(file.branch.as_deref(), Some("stable"));
+    assert_eq!(file.display_name(), "Maps");
+    assert_eq!(file.remote.as_deref(), Some("flathub"));
+    assert_eq!(file.url.as_deref(), Some("https://dl.flathub.org/repo/"));
+    assert_eq!(
+      file.runtime_repo.as_deref(),
+      Some("https://dl.flathub.org/repo/flathub.flatpakrepo")
+    );
+    // Unknown without a remote for the repository.
+    assert_eq!(file.runtime, None);
+    assert_eq!(file.installed_size, 0);
+    assert_eq!(file.permissions, None);
+  }
+
+  #[test]
+  fn reads_flatpakrefs_of_runtimes() {
+    let path = write(
+      "platform.flatpakref",
+      "[Flatpak Ref]\nName=org.gnome.Platform\nBranch=47\nIsRuntime=true\n",
+    );
+    let file = read_flatpakref(&path, &[]).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(file.ref_kind, RefKind::Runtime);
+    assert_eq!(file.display_name(), "org.gnome.Platform");
+    assert_eq!(file.url, None);
+  }
+
+  #[test]
+  fn rejects_flatpakrefs_without_a_name() {
+    let path = write("nameless.flatpakref", "[Flatpak Ref]\nBranch=stable\n");
+    assert!(read_flatpakref(&path, &[]).is_err());
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn rejects_other_files() {
+    let error = read(Path::new("/tmp/flathub.flatpakrepo")).unwrap_err();
+    assert!(error.matches(IOErrorEnum::NotSupported));
+  }
+
+  #[test]
+  fn describes_runtimes_and_permissions() {
+    let path = write(
+      "described.flatpakref",
+      "[Flatpak Ref]\nName=org.gnome.Maps\n",
+    );
+    let mut file = read_flatpakref(&path, &[]).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let metadata = "[Application]\n\
+                    name=org.gnome.Maps\n\
+                    runtime=org.gnome.Platform/x86_64/47\n\
+                    \n\
+                    [Context]\n\
+                    shared=network;ipc;\n\
+                    sockets=x11;wayland;\n";
+    describe_metadata(&mut file, &Bytes::from(metadata.as_bytes())).unwrap();
+    assert_eq!(
+      file.runtime.as_deref(),
+      Some("org.gnome.Platform/x86_64/47")
+    );
+    let permissions = file.permissions.unwrap();
+    assert!(permissions.shared["network"]);
+    assert!(permissions.shared["ipc"]);
+    assert!(permissions.sockets["x11"]);
+    assert!(permissions.sockets["wayland"]);
+  }
+}
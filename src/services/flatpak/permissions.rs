@@ -0,0 +1,795 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt,
+};
+
+use adw::{
+  gio::{
+    prelude::{FileExt, FileExtManual},
+    Cancellable, File, FileCreateFlags, IOErrorEnum,
+  },
+  glib::{Bytes, Error, KeyFile, KeyFileFlags},
+};
+use libflatpak::prelude::{InstallationExt, InstalledRefExt};
+use serde::{Deserialize, Serialize};
+
+use super::{open, FpRef, InstallationId};
+
+const CONTEXT_GROUP: &str = "Context";
+const SESSION_BUS_GROUP: &str = "Session Bus Policy";
+const SYSTEM_BUS_GROUP: &str = "System Bus Policy";
+const ENVIRONMENT_GROUP: &str = "Environment";
+
+/// The values of `shared`, `sockets`, `devices` and `features` flatpak knows,
+/// offered even when an app doesn't ask for them.
+pub const SHARED: &[&str] = &["network", "ipc"];
+pub const SOCKETS: &[&str] = &[
+  "x11",
+  "wayland",
+  "fallback-x11",
+  "pulseaudio",
+  "session-bus",
+  "system-bus",
+  "ssh-auth",
+  "pcsc",
+  "cups",
+  "gpg-agent",
+];
+pub const DEVICES: &[&str] = &["dri", "input", "usb", "kvm", "shm", "all"];
+pub const FEATURES: &[&str] = &[
+  "devel",
+  "multiarch",
+  "bluetooth",
+  "canbus",
+  "per-app-dev-shm",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilesystemAccess {
+  ReadOnly,
+  ReadWrite,
+  /// Read-write, creating the directory if needed.
+  Create,
+  /// Denied by an override, `!` in the keyfile.
+  Denied,
+}
+
+/// What an app may do with a D-Bus name, from least to most.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BusPolicy {
+  /// Denied by an override.
+  None,
+  See,
+  Talk,
+  Own,
+}
+
+impl BusPolicy {
+  fn parse(value: &str) -> Option<Self> {
+    match value {
+      "none" => Some(BusPolicy::None),
+      "see" => Some(BusPolicy::See),
+      "talk" => Some(BusPolicy::Talk),
+      "own" => Some(BusPolicy::Own),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for BusPolicy {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      BusPolicy::None => write!(f, "none"),
+      BusPolicy::See => write!(f, "see"),
+      BusPolicy::Talk => write!(f, "talk"),
+      BusPolicy::Own => write!(f, "own"),
+    }
+  }
+}
+
+/// The sandbox permissions of the metadata of an app, or of an override of
+/// them. Toggles mapped to `false` are denied, `!` in the keyfile.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Permissions {
+  /// e.g. `network` → `true`.
+  pub shared: BTreeMap<String, bool>,
+  /// e.g. `wayland` → `true`.
+  pub sockets: BTreeMap<String, bool>,
+  /// e.g. `dri` → `true`.
+  pub devices: BTreeMap<String, bool>,
+  /// e.g. `bluetooth` → `true`.
+  pub features: BTreeMap<String, bool>,
+  /// e.g. `xdg-download` → read-only.
+  pub filesystems: BTreeMap<String, FilesystemAccess>,
+  /// Directories of the home kept in the data of the app instead.
+  pub persistent: BTreeSet<String>,
+  /// e.g. `org.freedesktop.Notifications` → talk.
+  pub session_bus: BTreeMap<String, BusPolicy>,
+  pub system_bus: BTreeMap<String, BusPolicy>,
+  pub environment: BTreeMap<String, String>,
+}
+
+/// One of the permissions of [`Permissions`] which can be granted or denied.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Permission {
+  Shared(String),
+  Socket(String),
+  Device(String),
+  Feature(String),
+  Filesystem(String),
+  SessionBus(String),
+  SystemBus(String),
+}
+
+impl fmt::Display for Permission {
+  /// As `flatpak override` takes it, e.g. `--socket=wayland`.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Permission::Shared(name) => write!(f, "--share={}", name),
+      Permission::Socket(name) => write!(f, "--socket={}", name),
+      Permission::Device(name) => write!(f, "--device={}", name),
+      Permission::Feature(name) => write!(f, "--allow={}", name),
+      Permission::Filesystem(name) => write!(f, "--filesystem={}", name),
+      Permission::SessionBus(name) => write!(f, "--talk-name={}", name),
+      Permission::SystemBus(name) => write!(f, "--system-talk-name={}", name),
+    }
+  }
+}
+
+impl Permissions {
+  /// Read the permissions of a `metadata` or override keyfile.
+  pub fn parse(bytes: &Bytes) -> Result<Self, Error> {
+    let keyfile = KeyFile::new();
+    keyfile.load_from_bytes(bytes, KeyFileFlags::NONE)?;
+
+    let list = |key: &str| -> Vec<String> {
+      keyfile
+        .string_list(CONTEXT_GROUP, key)
+        .map(|values| values.iter().map(|v| v.as_str().to_string()).collect())
+        .unwrap_or_default()
+    };
+    let toggles = |key: &str| -> BTreeMap<String, bool> {
+      list(key)
+        .into_iter()
+        .filter(|value| !value.is_empty())
+        .map(|value| match value.strip_prefix('!') {
+          Some(name) => (name.to_string(), false),
+          None => (value, true),
+        })
+        .collect()
+    };
+    let policies = |group: &str| -> BTreeMap<String, BusPolicy> {
+      let Ok(names) = keyfile.keys(group) else {
+        return BTreeMap::new();
+      };
+      names
+        .iter()
+        .filter_map(|name| {
+          let policy = keyfile.string(group, name.as_str()).ok()?;
+          Some((name.as_str().to_string(), BusPolicy::parse(&policy)?))
+        })
+        .collect()
+    };
+
+    let environment = match keyfile.keys(ENVIRONMENT_GROUP) {
+      Ok(names) => names
+        .iter()
+        .map(|name| {
+          let value = keyfile
+            .string(ENVIRONMENT_GROUP, name.as_str())
+            .map(String::from)
+            .unwrap_or_default();
+          (name.as_str().to_string(), value)
+        })
+        .collect(),
+      Err(_) => BTreeMap::new(),
+    };
+
+    Ok(Permissions {
+      shared: toggles("shared"),
+      sockets: toggles("sockets"),
+      devices: toggles("devices"),
+      features: toggles("features"),
+      filesystems: list("filesystems")
+        .iter()
+        .filter_map(|value| parse_filesystem(value))
+        .collect(),
+      persistent: list("persistent").into_iter().collect(),
+      session_bus: policies(SESSION_BUS_GROUP),
+      system_bus: policies(SYSTEM_BUS_GROUP),
+      environment,
+    })
+  }
+
+  /// Write the permissions as an override keyfile.
+  pub fn to_keyfile(&self) -> KeyFile {
+    let keyfile = KeyFile::new();
+    let set_list = |key: &str, values: Vec<String>| {
+      if !values.is_empty() {
+        // Lists end with a separator in keyfiles.
+        keyfile.set_string(CONTEXT_GROUP, key, &format!("{};", values.join(";")));
+      }
+    };
+    let toggles = |toggles: &BTreeMap<String, bool>| {
+      toggles
+        .iter()
+        .map(|(name, enabled)| format_toggle(name, *enabled))
+        .collect()
+    };
+
+    set_list("shared", toggles(&self.shared));
+    set_list("sockets", toggles(&self.sockets));
+    set_list("devices", toggles(&self.devices));
+    set_list("features", toggles(&self.features));
+    set_list(
+      "filesystems",
+      self
+        .filesystems
+        .iter()
+        .map(|(path, access)| format_filesystem(path, *access))
+        .collect(),
+    );
+    set_list("persistent", self.persistent.iter().cloned().collect());
+    for (name, policy) in &self.session_bus {
+      keyfile.set_string(SESSION_BUS_GROUP, name, &policy.to_string());
+    }
+    for (name, policy) in &self.system_bus {
+      keyfile.set_string(SYSTEM_BUS_GROUP, name, &policy.to_string());
+    }
+    for (name, value) in &self.environment {
+      keyfile.set_string(ENVIRONMENT_GROUP, name, value);
+    }
+    keyfile
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self == &Permissions::default()
+  }
+
+  /// Apply `overrides` on top of these permissions, as flatpak does when
+  /// running the app.
+  pub fn apply(&mut self, overrides: &Permissions) {
+    self.shared.extend(overrides.shared.clone());
+    self.sockets.extend(overrides.sockets.clone());
+    self.devices.extend(overrides.devices.clone());
+    self.features.extend(overrides.features.clone());
+    self.filesystems.extend(overrides.filesystems.clone());
+    self.persistent.extend(overrides.persistent.clone());
+    self.session_bus.extend(overrides.session_bus.clone());
+    self.system_bus.extend(overrides.system_bus.clone());
+    self.environment.extend(overrides.environment.clone());
+  }
+
+  /// Whether `permission` is granted.
+  pub fn allows(&self, permission: &Permission) -> bool {
+    let toggle =
+      |toggles: &BTreeMap<String, bool>, name: &String| toggles.get(name).copied().unwrap_or(false);
+    match permission {
+      Permission::Shared(name) => toggle(&self.shared, name),
+      Permission::Socket(name) => toggle(&self.sockets, name),
+      Permission::Device(name) => toggle(&self.devices, name),
+      Permission::Feature(name) => toggle(&self.features, name),
+      Permission::Filesystem(path) => self
+        .filesystems
+        .get(path)
+        .is_some_and(|access| *access != FilesystemAccess::Denied),
+      Permission::SessionBus(name) => self
+        .session_bus
+        .get(name)
+        .is_some_and(|policy| *policy != BusPolicy::None),
+      Permission::SystemBus(name) => self
+        .system_bus
+        .get(name)
+        .is_some_and(|policy| *policy != BusPolicy::None),
+    }
+  }
+
+  /// Grant or deny `permission`. Filesystems are granted read-write and bus
+  /// names talk.
+  pub fn set(&mut self, permission: &Permission, allowed: bool) {
+    match permission {
+      Permission::Shared(name) => {
+        self.shared.insert(name.clone(), allowed);
+      }
+      Permission::Socket(name) => {
+        self.sockets.insert(name.clone(), allowed);
+      }
+      Permission::Device(name) => {
+        self.devices.insert(name.clone(), allowed);
+      }
+      Permission::Feature(name) => {
+        self.features.insert(name.clone(), allowed);
+      }
+      Permission::Filesystem(path) => {
+        let access = if allowed {
+          FilesystemAccess::ReadWrite
+        } else {
+          FilesystemAccess::Denied
+        };
+        self.filesystems.insert(path.clone(), access);
+      }
+      Permission::SessionBus(name) => {
+        self.session_bus.insert(name.clone(), bus_policy(allowed));
+      }
+      Permission::SystemBus(name) => {
+        self.system_bus.insert(name.clone(), bus_policy(allowed));
+      }
+    }
+  }
+
+  /// Forget whatever these permissions say about `permission`.
+  pub fn unset(&mut self, permission: &Permission) {
+    match permission {
+      Permission::Shared(name) => {
+        self.shared.remove(name);
+      }
+      Permission::Socket(name) => {
+        self.sockets.remove(name);
+      }
+      Permission::Device(name) => {
+        self.devices.remove(name);
+      }
+      Permission::Feature(name) => {
+        self.features.remove(name);
+      }
+      Permission::Filesystem(path) => {
+        self.filesystems.remove(path);
+      }
+      Permission::SessionBus(name) => {
+        self.session_bus.remove(name);
+      }
+      Permission::SystemBus(name) => {
+        self.system_bus.remove(name);
+      }
+    }
+  }
+
+  /// What these permissions grant, as `flatpak run` options, e.g.
+  /// `--socket=wayland` or `--filesystem=xdg-download:ro`.
+  pub fn granted(&self) -> Vec<String> {
+    let mut granted: Vec<String> = self
+      .permissions()
+      .into_iter()
+      .filter(|permission| self.allows(permission))
+      .map(|permission| match &permission {
+        Permission::Filesystem(path) => {
+          format!(
+            "--filesystem={}",
+            format_filesystem(path, self.filesystems[path])
+          )
+        }
+        Permission::SessionBus(name) if self.session_bus[name] != BusPolicy::Talk => {
+          format!("--{}-name={}", self.session_bus[name], name)
+        }
+        Permission::SystemBus(name) if self.system_bus[name] != BusPolicy::Talk => {
+          format!("--system-{}-name={}", self.system_bus[name], name)
+        }
+        permission => permission.to_string(),
+      })
+      .collect();
+    granted.extend(
+      self
+        .persistent
+        .iter()
+        .map(|path| format!("--persist={}", path)),
+    );
+    granted.extend(
+      self
+        .environment
+        .iter()
+        .map(|(name, value)| format!("--env={}={}", name, value)),
+    );
+    granted
+  }
+
+  /// Every permission these permissions grant or deny.
+  fn permissions(&self) -> Vec<Permission> {
+    let names = |toggles: &BTreeMap<String, bool>| toggles.keys().cloned().collect::<Vec<_>>();
+    let mut permissions: Vec<Permission> = vec![];
+    permissions.extend(names(&self.shared).into_iter().map(Permission::Shared));
+    permissions.extend(names(&self.sockets).into_iter().map(Permission::Socket));
+    permissions.extend(names(&self.devices).into_iter().map(Permission::Device));
+    permissions.extend(names(&self.features).into_iter().map(Permission::Feature));
+    permissions.extend(self.filesystems.keys().cloned().map(Permission::Filesystem));
+    permissions.extend(self.session_bus.keys().cloned().map(Permission::SessionBus));
+    permissions.extend(self.system_bus.keys().cloned().map(Permission::SystemBus));
+    permissions
+  }
+}
+
+fn bus_policy(allowed: bool) -> BusPolicy {
+  if allowed {
+    BusPolicy::Talk
+  } else {
+    BusPolicy::None
+  }
+}
+
+fn format_toggle(name: &str, enabled: bool) -> String {
+  if enabled {
+    name.to_string()
+  } else {
+    format!("!{}", name)
+  }
+}
+
+/// Parse a `filesystems` value, e.g. `xdg-download:ro` or `!home`.
+fn parse_filesystem(value: &str) -> Option<(String, FilesystemAccess)> {
+  if value.is_empty() {
+    return None;
+  }
+  if let Some(path) = value.strip_prefix('!') {
+    // `!path:reset` also drops what overrides of lower precedence grant.
+    let path = path.strip_suffix(":reset").unwrap_or(path);
+    return Some((path.to_string(), FilesystemAccess::Denied));
+  }
+  let (path, access) = match value.rsplit_once(':') {
+    Some((path, "ro")) => (path, FilesystemAccess::ReadOnly),
+    Some((path, "rw")) => (path, FilesystemAccess::ReadWrite),
+    Some((path, "create")) => (path, FilesystemAccess::Create),
+    _ => (value, FilesystemAccess::ReadWrite),
+  };
+  Some((path.to_string(), access))
+}
+
+fn format_filesystem(path: &str, access: FilesystemAccess) -> String {
+  match access {
+    FilesystemAccess::ReadOnly => format!("{}:ro", path),
+    FilesystemAccess::ReadWrite => path.to_string(),
+    FilesystemAccess::Create => format!("{}:create", path),
+    FilesystemAccess::Denied => format!("!{}", path),
+  }
+}
+
+/// Where overrides are written: flatpak reads those of the default system
+/// installation, then those of the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverrideScope {
+  User,
+  System,
+}
+
+impl OverrideScope {
+  fn file(&self, app_id: &str) -> Result<File, Error> {
+    let installation = match self {
+      OverrideScope::User => open(&InstallationId::User)?,
+      OverrideScope::System => open(&InstallationId::default_system())?,
+    };
+    let path = installation
+      .path()
+      .ok_or_else(|| Error::new(IOErrorEnum::NotFound, "Installation has no path"))?;
+    Ok(path.child("overrides").child(app_id))
+  }
+}
+
+/// The permissions of an installed app, as declared by its metadata and
+/// overridden by the system and the user.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AppPermissions {
+  pub app_id: String,
+  pub metadata: Permissions,
+  pub system: Permissions,
+  pub user: Permissions,
+}
+
+impl AppPermissions {
+  /// The permissions the app runs with.
+  pub fn effective(&self) -> Permissions {
+    let mut permissions = self.defaults(OverrideScope::User);
+    permissions.apply(&self.user);
+    permissions
+  }
+
+  /// The permissions before the overrides of `scope` apply.
+  fn defaults(&self, scope: OverrideScope) -> Permissions {
+    let mut permissions = self.metadata.clone();
+    if scope == OverrideScope::User {
+      permissions.apply(&self.system);
+    }
+    permissions
+  }
+
+  pub fn overrides(&self, scope: OverrideScope) -> &Permissions {
+    match scope {
+      OverrideScope::User => &self.user,
+      OverrideScope::System => &self.system,
+    }
+  }
+
+  /// Whether the overrides of `scope` grant or deny `permission`.
+  pub fn is_overridden(&self, scope: OverrideScope, permission: &Permission) -> bool {
+    self.overrides(scope).permissions().contains(permission)
+  }
+
+  /// Grant or deny `permission` through the overrides of `scope`, or drop
+  /// its override when it's already what the app gets without it.
+  pub fn set(&mut self, scope: OverrideScope, permission: &Permission, allowed: bool) {
+    let default = self.defaults(scope).allows(permission);
+    let overrides = match scope {
+      OverrideScope::User => &mut self.user,
+      OverrideScope::System => &mut self.system,
+    };
+    if default == allowed {
+      overrides.unset(permission);
+    } else {
+      overrides.set(permission, allowed);
+    }
+  }
+
+  /// Every permission worth showing: those flatpak knows and those the app
+  /// or its overrides mention, sorted by kind.
+  pub fn permissions(&self) -> Vec<Permission> {
+    let known = (SHARED.iter().map(|s| Permission::Shared(s.to_string())))
+      .chain(SOCKETS.iter().map(|s| Permission::Socket(s.to_string())))
+      .chain(DEVICES.iter().map(|s| Permission::Device(s.to_string())))
+      .chain(FEATURES.iter().map(|s| Permission::Feature(s.to_string())));
+    let permissions: BTreeSet<Permission> = known
+      .chain(self.metadata.permissions())
+      .chain(self.system.permissions())
+      .chain(self.user.permissions())
+      .collect();
+    permissions.into_iter().collect()
+  }
+}
+
+/// Read the permissions of the installed app `app`, see [`AppPermissions`].
+pub fn read(app: &FpRef) -> Result<AppPermissions, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let metadata = open(&app.installation)?
+    .installed_ref(
+      app.kind.to_flatpak(),
+      &app.id,
+      Some(app.arch.as_str()),
+      Some(app.branch.as_str()),
+      cancellable,
+    )?
+    .load_metadata(cancellable)?;
+  Ok(AppPermissions {
+    app_id: app.id.clone(),
+    metadata: Permissions::parse(&metadata)?,
+    system: read_overrides(OverrideScope::System, &app.id)?,
+    user: read_overrides(OverrideScope::User, &app.id)?,
+  })
+}
+
+/// Read the overrides of `scope` for the app `app_id`, empty if there are
+/// none.
+pub fn read_overrides(scope: OverrideScope, app_id: &str) -> Result<Permissions, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  match scope.file(app_id)?.load_contents(cancellable) {
+    Ok((contents, _)) => Permissions::parse(&Bytes::from(&contents[..])),
+    Err(error) if error.matches(IOErrorEnum::NotFound) => Ok(Permissions::default()),
+    Err(error) => Err(error),
+  }
+}
+
+/// Replace the overrides of `scope` for the app `app_id`, or remove them when
+/// `overrides` is empty. The system overrides need root, the app picks them
+/// up the next time it starts.
+pub fn write_overrides(
+  scope: OverrideScope,
+  app_id: &str,
+  overrides: &Permissions,
+) -> Result<(), Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let file = scope.file(app_id)?;
+  if overrides.is_empty() {
+    return match file.delete(cancellable) {
+      Err(error) if !error.matches(IOErrorEnum::NotFound) => Err(error),
+      _ => Ok(()),
+    };
+  }
+  if let Some(dir) = file.parent()
+    && let Err(error) = dir.make_directory_with_parents(cancellable)
+    && !error.matches(IOErrorEnum::Exists)
+  {
+    return Err(error);
+  }
+  let data = overrides.to_keyfile().to_data();
+  file.replace_contents(
+    data.as_bytes(),
+    None,
+    false,
+    FileCreateFlags::REPLACE_DESTINATION,
+    cancellable,
+  )?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const OVERRIDES: &str = "\
+[Context]
+shared=network;!ipc;
+sockets=wayland;!x11;;
+filesystems=xdg-download:ro;!home;~/Music:create;!host:reset;/srv;
+persistent=.mozilla;
+
+[Session Bus Policy]
+org.freedesktop.Notifications=talk
+org.example.Owned=own
+org.example.Hidden=none
+org.example.Unknown=maybe
+
+[System Bus Policy]
+org.freedesktop.login1=see
+
+[Environment]
+GTK_THEME=Adwaita
+";
+
+  fn parse(keyfile: &str) -> Permissions {
+    Permissions::parse(&Bytes::from(keyfile.as_bytes())).unwrap()
+  }
+
+  fn round_trip(permissions: &Permissions) -> Permissions {
+    parse(&permissions.to_keyfile().to_data())
+  }
+
+  fn toggles(toggles: &[(&str, bool)]) -> BTreeMap<String, bool> {
+    toggles
+      .iter()
+      .map(|(name, enabled)| (name.to_string(), *enabled))
+      .collect()
+  }
+
+  #[test]
+  fn parses_overrides() {
+    let permissions = parse(OVERRIDES);
+    assert_eq!(
+      permissions.shared,
+      toggles(&[("network", true), ("ipc", false)])
+    );
+    assert_eq!(
+      permissions.sockets,
+      toggles(&[("wayland", true), ("x11", false)])
+    );
+    assert_eq!(
+      permissions.filesystems,
+      BTreeMap::from([
+        ("xdg-download".to_string(), FilesystemAccess::ReadOnly),
+        ("home".to_string(), FilesystemAccess::Denied),
+        ("~/Music".to_string(), FilesystemAccess::Create),
+        ("host".to_string(), FilesystemAccess::Denied),
+        ("/srv".to_string(), FilesystemAccess::ReadWrite),
+      ])
+    );
+    assert_eq!(
+      permissions.persistent,
+      BTreeSet::from([".mozilla".to_string()])
+    );
+    assert_eq!(
+      permissions.session_bus,
+      BTreeMap::from([
+        ("org.freedesktop.Notifications".to_string(), BusPolicy::Talk),
+        ("org.example.Owned".to_string(), BusPolicy::Own),
+        ("org.example.Hidden".to_string(), BusPolicy::None),
+      ])
+    );
+    assert_eq!(
+      permissions.system_bus,
+      BTreeMap::from([("org.freedesktop.login1".to_string(), BusPolicy::See)])
+    );
+    assert_eq!(
+      permissions.environment,
+      BTreeMap::from([("GTK_THEME".to_string(), "Adwaita".to_string())])
+    );
+    assert!(permissions.devices.is_empty());
+  }
+
+  #[test]
+  fn rejects_malformed_keyfiles() {
+    assert!(Permissions::parse(&Bytes::from(b"not a keyfile".as_slice())).is_err());
+    assert!(parse("").is_empty());
+  }
+
+  #[test]
+  fn writes_what_it_parses() {
+    let permissions = parse(OVERRIDES);
+    assert_eq!(round_trip(&permissions), permissions);
+
+    let keyfile = permissions.to_keyfile();
+    assert_eq!(
+      keyfile.string(CONTEXT_GROUP, "sockets").unwrap(),
+      "wayland;!x11;"
+    );
+    assert_eq!(
+      keyfile.string(CONTEXT_GROUP, "filesystems").unwrap(),
+      "/srv;!home;!host;xdg-download:ro;~/Music:create;"
+    );
+    assert!(!keyfile.has_key(CONTEXT_GROUP, "devices").unwrap());
+  }
+
+  #[test]
+  fn writes_nothing_without_overrides() {
+    let keyfile = Permissions::default().to_keyfile();
+    assert!(keyfile.groups().is_empty());
+    assert!(round_trip(&Permissions::default()).is_empty());
+  }
+
+  #[test]
+  fn applies_overrides() {
+    let mut permissions = parse(
+      "[Context]\nsockets=wayland;x11;\nfilesystems=home;\n\n\
+       [Session Bus Policy]\norg.example.Service=talk\n",
+    );
+    permissions.apply(&parse(OVERRIDES));
+
+    assert!(permissions.allows(&Permission::Socket("wayland".to_string())));
+    assert!(!permissions.allows(&Permission::Socket("x11".to_string())));
+    assert!(!permissions.allows(&Permission::Filesystem("home".to_string())));
+    assert!(permissions.allows(&Permission::SessionBus("org.example.Service".to_string())));
+    assert!(!permissions.allows(&Permission::SessionBus("org.example.Hidden".to_string())));
+    assert!(!permissions.allows(&Permission::Device("dri".to_string())));
+    assert_eq!(
+      permissions.granted(),
+      [
+        "--share=network",
+        "--socket=wayland",
+        "--filesystem=/srv",
+        "--filesystem=xdg-download:ro",
+        "--filesystem=~/Music:create",
+        "--own-name=org.example.Owned",
+        "--talk-name=org.example.Service",
+        "--talk-name=org.freedesktop.Notifications",
+        "--system-see-name=org.freedesktop.login1",
+        "--persist=.mozilla",
+        "--env=GTK_THEME=Adwaita",
+      ]
+    );
+  }
+
+  fn app() -> AppPermissions {
+    AppPermissions {
+      app_id: "org.example.App".to_string(),
+      metadata: parse("[Context]\nshared=network;\nsockets=x11;\n"),
+      system: Permissions::default(),
+      user: Permissions::default(),
+    }
+  }
+
+  #[test]
+  fn overrides_permissions() {
+    let mut app = app();
+    let network = Permission::Shared("network".to_string());
+    let wayland = Permission::Socket("wayland".to_string());
+
+    app.set(OverrideScope::User, &network, false);
+    app.set(OverrideScope::User, &wayland, true);
+    assert!(app.is_overridden(OverrideScope::User, &network));
+    assert!(!app.is_overridden(OverrideScope::System, &network));
+    assert!(!app.effective().allows(&network));
+    assert!(app.effective().allows(&wayland));
+
+    let written = round_trip(&app.user);
+    assert_eq!(written, app.user);
+    assert_eq!(
+      written.shared,
+      toggles(&[("network", false)]),
+      "denials are written negated"
+    );
+  }
+
+  #[test]
+  fn removes_overrides_matching_the_defaults() {
+    let mut app = app();
+    let network = Permission::Shared("network".to_string());
+
+    app.set(OverrideScope::User, &network, false);
+    app.set(OverrideScope::User, &network, true);
+    assert!(!app.is_overridden(OverrideScope::User, &network));
+    assert!(app.user.is_empty());
+    assert!(round_trip(&app.user).is_empty());
+
+    // The defaults of the user include the overrides of the system.
+    app.set(OverrideScope::System, &network, false);
+    app.set(OverrideScope::User, &network, true);
+    assert!(app.is_overridden(OverrideScope::User, &network));
+    assert!(app.effective().allows(&network));
+    app.set(OverrideScope::User, &network, false);
+    assert!(app.user.is_empty());
+    assert!(!app.effective().allows(&network));
+  }
+}
@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use adw::{
+  gio::{prelude::FileExtManual, Cancellable, File, IOErrorEnum},
+  glib::{Bytes, Error},
+};
+use libflatpak::{
+  prelude::{InstallationExt, RemoteExt},
+  Installation, Remote,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{list_all, open, InstallationId};
+
+/// A repository refs are installed from, e.g. Flathub.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RemoteInfo {
+  pub name: String,
+  pub installation: InstallationId,
+  pub title: Option<String>,
+  pub comment: Option<String>,
+  pub url: Option<String>,
+  pub homepage: Option<String>,
+  /// Remotes with a higher priority are preferred.
+  pub priority: i32,
+  pub collection_id: Option<String>,
+  pub gpg_verify: bool,
+  /// Path of the file listing which refs can be installed from the remote.
+  pub filter: Option<String>,
+  pub enabled: bool,
+}
+
+impl RemoteInfo {
+  fn of(installation: &InstallationId, remote: &Remote) -> Self {
+    let string = |s: Option<adw::glib::GString>| s.map(|s| s.to_string()).filter(|s| !s.is_empty());
+    RemoteInfo {
+      name: remote.name().map(String::from).unwrap_or_default(),
+      installation: installation.clone(),
+      title: string(remote.title()),
+      comment: string(remote.comment()),
+      url: string(remote.url()),
+      homepage: string(remote.homepage()),
+      priority: remote.prio(),
+      collection_id: string(remote.collection_id()),
+      gpg_verify: remote.is_gpg_verify(),
+      filter: string(remote.filter()),
+      enabled: !remote.is_disabled(),
+    }
+  }
+
+  /// The title, or the name when there is no title.
+  pub fn display_name(&self) -> &str {
+    self.title.as_deref().unwrap_or(&self.name)
+  }
+}
+
+/// Settings of a remote, see [`add`] and [`modify`]. Those left to `None`
+/// are unchanged, or default when adding a remote.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RemoteSettings {
+  pub title: Option<String>,
+  pub url: Option<String>,
+  pub priority: Option<i32>,
+  /// Empty to remove the collection id.
+  pub collection_id: Option<String>,
+  pub gpg_verify: Option<bool>,
+  /// The public key the remote is signed with, in binary form.
+  pub gpg_key: Option<Vec<u8>>,
+  /// Path of a filter file, empty to remove the filter.
+  pub filter: Option<String>,
+  pub enabled: Option<bool>,
+}
+
+impl RemoteSettings {
+  fn apply(&self, remote: &Remote) {
+    if let Some(title) = &self.title {
+      remote.set_title(title);
+    }
+    if let Some(url) = &self.url {
+      remote.set_url(url);
+    }
+    if let Some(priority) = self.priority {
+      remote.set_prio(priority);
+    }
+    if let Some(collection_id) = &self.collection_id {
+      remote.set_collection_id(Some(collection_id.as_str()).filter(|id| !id.is_empty()));
+    }
+    if let Some(gpg_verify) = self.gpg_verify {
+      remote.set_gpg_verify(gpg_verify);
+    }
+    if let Some(gpg_key) = &self.gpg_key {
+      remote.set_gpg_key(&Bytes::from(gpg_key.as_slice()));
+    }
+    if let Some(filter) = &self.filter {
+      remote.set_filter(filter);
+    }
+    if let Some(enabled) = self.enabled {
+      remote.set_disabled(!enabled);
+    }
+  }
+}
+
+/// List the remotes of every installation, see [`list_in`].
+pub fn list() -> Result<Vec<RemoteInfo>, Error> {
+  list_all(list_installation)
+}
+
+/// List the remotes of the installation `id`, including disabled ones.
+pub fn list_in(id: &InstallationId) -> Result<Vec<RemoteInfo>, Error> {
+  list_installation(&open(id)?)
+}
+
+fn list_installation(installation: &Installation) -> Result<Vec<RemoteInfo>, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let id = InstallationId::of(installation);
+  let remotes = installation.list_remotes(cancellable)?;
+
+  Ok(
+    remotes
+      .iter()
+      .map(|remote| RemoteInfo::of(&id, remote))
+      .collect(),
+  )
+}
+
+/// Add the remote `name` to the installation `id`. `settings` need a URL.
+pub fn add(
+  id: &InstallationId,
+  name: &str,
+  settings: &RemoteSettings,
+) -> Result<RemoteInfo, Error> {
+  if settings.url.is_none() {
+    return Err(Error::new(
+      IOErrorEnum::InvalidArgument,
+      &format!("Remote {} needs a URL", name),
+    ));
+  }
+  let remote = Remote::new(name);
+  settings.apply(&remote);
+  add_remote(&open(id)?, name, &remote)
+}
+
+/// Add the remote `name` to the installation `id`, configured by the
+/// `.flatpakrepo` file at `path`, which usually includes its key.
+pub fn import(id: &InstallationId, name: &str, path: &Path) -> Result<RemoteInfo, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let (contents, _) = File::for_path(path).load_contents(cancellable)?;
+  let remote = Remote::from_file(name, &Bytes::from(&contents[..]))?;
+  add_remote(&open(id)?, name, &remote)
+}
+
+fn add_remote(
+  installation: &Installation,
+  name: &str,
+  remote: &Remote,
+) -> Result<RemoteInfo, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  installation.add_remote(remote, false, cancellable)?;
+  // Read it back, with the defaults filled in.
+  Ok(RemoteInfo::of(
+    &InstallationId::of(installation),
+    &installation.remote_by_name(name, cancellable)?,
+  ))
+}
+
+/// Change the `settings` of the remote `name` which aren't `None`.
+pub fn modify(
+  id: &InstallationId,
+  name: &str,
+  settings: &RemoteSettings,
+) -> Result<RemoteInfo, Error> {
+  modify_remote(&open(id)?, name, settings)
+}
+
+fn modify_remote(
+  installation: &Installation,
+  name: &str,
+  settings: &RemoteSettings,
+) -> Result<RemoteInfo, Error> {
+  let cancellable: Option<&Cancellable> = None;
+  let remote = installation.remote_by_name(name, cancellable)?;
+  settings.apply(&remote);
+  installation.modify_remote(&remote, cancellable)?;
+  Ok(RemoteInfo::of(&InstallationId::of(installation), &remote))
+}
+
+/// Enable or disable the remote `name`. Refs of a disabled remote stay
+/// installed, but aren't updated.
+pub fn set_enabled(id: &InstallationId, name: &str, enabled: bool) -> Result<RemoteInfo, Error> {
+  modify(
+    id,
+    name,
+    &RemoteSettings {
+      enabled: Some(enabled),
+      ..Default::default()
+    },
+  )
+}
+
+/// Remove the remote `name`. This fails while refs installed from it are
+/// still installed.
+pub fn remove(id: &InstallationId, name: &str) -> Result<(), Error> {
+  let cancellable: Option<&Cancellable> = None;
+  open(id)?.remove_remote(name, cancellable)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::flatpak::tests::installation;
+
+  fn settings() -> RemoteSettings {
+    RemoteSettings {
+      title: Some("Test Repository".to_string()),
+      url: Some("file:///var/tmp/rouge-test-repo".to_string()),
+      priority: Some(5),
+      collection_id: Some("dev.rouge.Test".to_string()),
+      gpg_verify: Some(false),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn adds_remotes() {
+    let (installation, path) = installation("add-remote", true);
+    let remote = Remote::new("test");
+    settings().apply(&remote);
+    let added = add_remote(&installation, "test", &remote).unwrap();
+    assert_eq!(added.name, "test");
+    assert_eq!(added.installation, InstallationId::User);
+    assert_eq!(added.display_name(), "Test Repository");
+    assert_eq!(
+      added.url.as_deref(),
+      Some("file:///var/tmp/rouge-test-repo")
+    );
+    assert_eq!(added.priority, 5);
+    assert_eq!(added.collection_id.as_deref(), Some("dev.rouge.Test"));
+    assert!(!added.gpg_verify);
+    assert!(added.enabled);
+    assert_eq!(list_installation(&installation).unwrap(), vec![added]);
+    std::fs::remove_dir_all(path).unwrap();
+  }
+
+  #[test]
+  fn modifies_only_the_given_settings() {
+    let (installation, path) = installation("modify-remote", true);
+    let remote = Remote::new("test");
+    settings().apply(&remote);
+    let added = add_remote(&installation, "test", &remote).unwrap();
+
+    let modified = modify_remote(
+      &installation,
+      "test",
+      &RemoteSettings {
+        title: Some("Renamed".to_string()),
+        collection_id: Some(String::new()),
+        enabled: Some(false),
+        ..Default::default()
+      },
+    )
+    .unwrap();
+    assert_eq!(modified.display_name(), "Renamed");
+    assert_eq!(modified.collection_id, None);
+    assert!(!modified.enabled);
+    assert_eq!(modified.url, added.url);
+    assert_eq!(modified.priority, added.priority);
+    assert_eq!(list_installation(&installation).unwrap(), vec![modified]);
+    std::fs::remove_dir_all(path).unwrap();
+  }
+
+  #[test]
+  fn needs_a_url_to_add_remotes() {
+    let error = add(&InstallationId::User, "test", &RemoteSettings::default()).unwrap_err();
+    assert!(error.matches(IOErrorEnum::InvalidArgument));
+  }
+}
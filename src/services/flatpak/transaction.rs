@@ -0,0 +1,451 @@
+use std::{collections::BTreeMap, fs, rc::Rc};
+
+use adw::{
+  gio::{
+    prelude::{CancellableExtManual, FileExtManual},
+    Cancellable, File, IOErrorEnum,
+  },
+  glib::{translate::ToGlibPtr, Bytes, Error},
+};
+use libflatpak::{
+  prelude::{InstallationExt, RefExt, RelatedRefExt, TransactionExt, TransactionExtManual},
+  Installation, RelatedRef, Transaction, TransactionErrorDetails, TransactionOperation,
+  TransactionOperationType,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+  file::{RefFile, RefFileKind},
+  open, FpRef, InstallationId, PendingUpdate,
+};
+
+/// How often progress is reported, in milliseconds.
+const PROGRESS_INTERVAL: u32 = 250;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationKind {
+  Install,
+  InstallBundle,
+  Update,
+  Uninstall,
+  /// Deleting the data of an uninstalled app, see [`uninstall`].
+  RemoveData,
+}
+
+/// A step of a transaction: a ref to install, update or remove.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+  /// The full ref, e.g. `runtime/org.gnome.Platform/x86_64/47`.
+  pub ref_: String,
+  pub kind: OperationKind,
+  pub remote: Option<String>,
+  /// In bytes, 0 when unknown or nothing is downloaded.
+  pub download_size: u64,
+  /// In bytes.
+  pub installed_size: u64,
+}
+
+impl Operation {
+  fn from_flatpak(operation: &TransactionOperation) -> Self {
+    Operation {
+      ref_: operation.get_ref().map(String::from).unwrap_or_default(),
+      kind: match operation.operation_type() {
+        TransactionOperationType::Install => OperationKind::Install,
+        TransactionOperationType::InstallBundle => OperationKind::InstallBundle,
+        TransactionOperationType::Uninstall => OperationKind::Uninstall,
+        _ => OperationKind::Update,
+      },
+      remote: operation.remote().map(String::from),
+      download_size: operation.download_size(),
+      installed_size: operation.installed_size(),
+    }
+  }
+}
+
+/// What happens during a transaction, in order, see [`install`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TransactionEvent {
+  /// Dependencies are resolved and the operations are about to run.
+  Ready(Vec<Operation>),
+  /// An operation started.
+  Started(Operation),
+  /// Progress of the running operation.
+  Progress {
+    ref_: String,
+    status: Option<String>,
+    /// In percent.
+    progress: u32,
+    bytes_transferred: u64,
+  },
+  /// An operation succeeded.
+  Done { ref_: String, changed: bool },
+  /// An operation failed. The transaction stops, unless the error isn't
+  /// fatal.
+  Failed { ref_: String, message: String },
+}
+
+/// Install `ref_` from `remote` in the installation `id`, along with the
+/// runtimes and extensions it needs, reporting each step to `report`.
+///
+/// This blocks until the transaction is over, so it's meant to run in a
+/// `worker`, and stops as soon as possible once `cancellable` is triggered.
+pub fn install<F>(
+  id: &InstallationId,
+  remote: &str,
+  ref_: &str,
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  F: 'static + Fn(TransactionEvent),
+{
+  install_in(&open(id)?, remote, ref_, report, cancellable)
+}
+
+/// Same as [`install`], in `installation`, e.g. one created in a temporary
+/// directory with `Installation::new_for_path`.
+pub fn install_in<F>(
+  installation: &Installation,
+  remote: &str,
+  ref_: &str,
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  F: 'static + Fn(TransactionEvent),
+{
+  run(
+    installation,
+    |transaction| transaction.add_install(remote, ref_, &[]),
+    report,
+    cancellable,
+  )
+}
+
+/// Install what `file` describes in the installation `id`, see
+/// `file::read`. Remotes the file asks for, its own or the one providing its
+/// runtime, are added as needed.
+///
+/// Like [`install`], this blocks until the transaction is over.
+pub fn install_file<F>(
+  id: &InstallationId,
+  file: &RefFile,
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  F: 'static + Fn(TransactionEvent),
+{
+  run(
+    &open(id)?,
+    |transaction| {
+      transaction.connect_add_new_remote(|_, _, _, _, _| true);
+      match file.kind {
+        RefFileKind::Ref => {
+          let (contents, _) = File::for_path(&file.path).load_contents(Some(cancellable))?;
+          transaction.add_install_flatpakref(&Bytes::from(&contents[..]))
+        }
+        RefFileKind::Bundle => transaction.add_install_bundle(&File::for_path(&file.path), None),
+      }
+    },
+    report,
+    cancellable,
+  )
+}
+
+/// Uninstall `ref_` and its related refs flagged for deletion, such as its
+/// translations and debug info, reporting each step to `report`. With
+/// `remove_data`, the data of the app is deleted as a last step.
+///
+/// Like [`install`], this blocks until the transaction is over. Runtimes
+/// left unused afterwards can be found with `flatpak::unused_in`.
+pub fn uninstall<F>(
+  ref_: &FpRef,
+  remove_data: bool,
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  F: 'static + Fn(TransactionEvent),
+{
+  let installation = open(&ref_.installation)?;
+  let full_ref = ref_.format_ref();
+  let related: Vec<String> = match &ref_.origin {
+    Some(origin) => to_delete(&installation.list_installed_related_refs_sync(
+      origin,
+      &full_ref,
+      Some(cancellable),
+    )?),
+    None => vec![],
+  };
+  let data = ref_.data_dir().filter(|dir| remove_data && dir.exists());
+  let data_operation = data.as_ref().map(|_| Operation {
+    ref_: full_ref.clone(),
+    kind: OperationKind::RemoveData,
+    remote: None,
+    download_size: 0,
+    installed_size: 0,
+  });
+
+  let report = Rc::new(report);
+  {
+    let report = report.clone();
+    let data_operation = data_operation.clone();
+    run(
+      &installation,
+      |transaction| {
+        // Only the related refs listed above, which are flagged for deletion.
+        transaction.set_disable_related(true);
+        transaction.add_uninstall(&full_ref)?;
+        related
+          .iter()
+          .try_for_each(|related| transaction.add_uninstall(related))
+      },
+      move |event| match event {
+        TransactionEvent::Ready(mut operations) => {
+          operations.extend(data_operation.clone());
+          report(TransactionEvent::Ready(operations));
+        }
+        event => report(event),
+      },
+      cancellable,
+    )?;
+  }
+
+  let (Some(dir), Some(operation)) = (data, data_operation) else {
+    return Ok(());
+  };
+  cancellable.set_error_if_cancelled()?;
+  report(TransactionEvent::Started(operation));
+  match fs::remove_dir_all(&dir) {
+    Ok(()) => {
+      report(TransactionEvent::Done {
+        ref_: full_ref,
+        changed: true,
+      });
+      Ok(())
+    }
+    Err(error) => {
+      let message = format!("Unable to delete {}: {}", dir.display(), error);
+      report(TransactionEvent::Failed {
+        ref_: full_ref,
+        message: message.clone(),
+      });
+      Err(Error::new(IOErrorEnum::Failed, &message))
+    }
+  }
+}
+
+/// The related refs which go along with a ref when it's uninstalled.
+fn to_delete(related: &[RelatedRef]) -> Vec<String> {
+  related
+    .iter()
+    .filter(|related| related.should_delete())
+    .filter_map(|related| related.format_ref())
+    .map(String::from)
+    .collect()
+}
+
+/// Uninstall `refs` from the installation `id` in a single transaction,
+/// e.g. those listed by `flatpak::unused_in`.
+pub fn uninstall_refs<F>(
+  id: &InstallationId,
+  refs: &[String],
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  F: 'static + Fn(TransactionEvent),
+{
+  run(
+    &open(id)?,
+    |transaction| {
+      refs
+        .iter()
+        .try_for_each(|ref_| transaction.add_uninstall(ref_))
+    },
+    report,
+    cancellable,
+  )
+}
+
+/// Apply `updates`, along with new runtimes and extensions they need, in a
+/// single transaction per installation, reporting each step to `report`.
+///
+/// Like [`install`], this blocks until the transactions are over, and
+/// stops at the first one failing.
+pub fn update<F>(
+  updates: &[PendingUpdate],
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  F: 'static + Fn(TransactionEvent),
+{
+  let mut by_installation: BTreeMap<String, (InstallationId, Vec<String>)> = BTreeMap::new();
+  for update in updates {
+    let installation = &update.installed.installation;
+    by_installation
+      .entry(installation.to_string())
+      .or_insert_with(|| (installation.clone(), vec![]))
+      .1
+      .push(update.installed.format_ref());
+  }
+
+  let report = Rc::new(report);
+  for (id, refs) in by_installation.values() {
+    let report = report.clone();
+    run(
+      &open(id)?,
+      |transaction| {
+        refs
+          .iter()
+          .try_for_each(|ref_| add_update(transaction, ref_))
+      },
+      move |event| report(event),
+      cancellable,
+    )?;
+  }
+  Ok(())
+}
+
+/// Add an update of `ref_` to its latest commit, keeping the subpaths
+/// installed, e.g. the languages of a locale extension. The bindings can't
+/// express it, they pass an empty list which means all subpaths.
+fn add_update(transaction: &Transaction, ref_: &str) -> Result<(), Error> {
+  #[allow(unsafe_code)]
+  unsafe {
+    use libflatpak::glib::translate::from_glib_full;
+
+    let mut error = std::ptr::null_mut();
+    libflatpak::ffi::flatpak_transaction_add_update(
+      transaction.to_glib_none().0,
+      ref_.to_glib_none().0,
+      std::ptr::null_mut(),
+      std::ptr::null(),
+      &mut error,
+    );
+    if error.is_null() {
+      Ok(())
+    } else {
+      Err(from_glib_full(error))
+    }
+  }
+}
+
+/// Run a transaction on `installation` with the operations added by `add`.
+pub(crate) fn run<A, F>(
+  installation: &Installation,
+  add: A,
+  report: F,
+  cancellable: &Cancellable,
+) -> Result<(), Error>
+where
+  A: FnOnce(&Transaction) -> Result<(), Error>,
+  F: 'static + Fn(TransactionEvent),
+{
+  let transaction = Transaction::for_installation(installation, Some(cancellable))?;
+  // Look for dependencies in the other installations too, like the CLI.
+  transaction.add_default_dependency_sources();
+  transaction.set_no_interaction(true);
+  add(&transaction)?;
+
+  let report = Rc::new(report);
+  {
+    let report = report.clone();
+    transaction.connect_ready(move |transaction| {
+      let operations = transaction
+        .operations()
+        .iter()
+        .map(Operation::from_flatpak)
+        .collect();
+      report(TransactionEvent::Ready(operations));
+      true
+    });
+  }
+  {
+    let report = report.clone();
+    transaction.connect_new_operation(move |_, operation, progress| {
+      let operation = Operation::from_flatpak(operation);
+      let ref_ = operation.ref_.clone();
+      report(TransactionEvent::Started(operation));
+
+      progress.set_update_frequency(PROGRESS_INTERVAL);
+      let report = report.clone();
+      progress.connect_changed(move |progress| {
+        report(TransactionEvent::Progress {
+          ref_: ref_.clone(),
+          status: progress.status().map(String::from),
+          progress: progress.progress().clamp(0, 100) as u32,
+          bytes_transferred: progress.bytes_transferred(),
+        });
+      });
+    });
+  }
+  {
+    let report = report.clone();
+    transaction.connect_operation_done(move |_, operation, _, result| {
+      report(TransactionEvent::Done {
+        ref_: operation.get_ref().map(String::from).unwrap_or_default(),
+        // The bindings name the NO_CHANGE flag CHANGE.
+        changed: !result.contains(libflatpak::TransactionResult::CHANGE),
+      });
+    });
+  }
+  {
+    let report = report.clone();
+    transaction.connect_operation_error(move |_, operation, error, details| {
+      report(TransactionEvent::Failed {
+        ref_: operation.get_ref().map(String::from).unwrap_or_default(),
+        message: error.to_string(),
+      });
+      // The bindings name the NON_FATAL flag FATAL. Keep going past
+      // non-fatal errors only.
+      details.contains(TransactionErrorDetails::FATAL)
+    });
+  }
+
+  transaction.run(Some(cancellable))
+}
+
+#[cfg(test)]
+mod tests {
+  use adw::glib::Object;
+
+  use super::*;
+
+  fn related(name: &str, should_delete: bool) -> RelatedRef {
+    Object::builder()
+      .property("kind", libflatpak::RefKind::Runtime)
+      .property("name", name)
+      .property("arch", "x86_64")
+      .property("branch", "stable")
+      .property("should-delete", should_delete)
+      .build()
+  }
+
+  #[test]
+  fn deletes_related_refs_flagged_for_it() {
+    let related = [
+      related("org.gnome.Maps.Locale", true),
+      related("org.gnome.Platform", false),
+      related("org.gnome.Maps.Debug", true),
+    ];
+    assert_eq!(
+      to_delete(&related),
+      vec![
+        "runtime/org.gnome.Maps.Locale/x86_64/stable",
+        "runtime/org.gnome.Maps.Debug/x86_64/stable",
+      ]
+    );
+  }
+
+  #[test]
+  fn deletes_nothing_without_related_refs() {
+    assert_eq!(to_delete(&[]), Vec::<String>::new());
+    assert_eq!(
+      to_delete(&[related("org.gnome.Platform", false)]),
+      Vec::<String>::new()
+    );
+  }
+}
@@ -0,0 +1,549 @@
+use std::{
+  cmp::Ordering,
+  collections::{BTreeMap, HashMap},
+  ops::Bound,
+};
+
+use super::{catalog::Catalog, flatpak::FpRef};
+
+/// How much a match in each field counts.
+const NAME_WEIGHT: f32 = 8.0;
+const ID_WEIGHT: f32 = 5.0;
+const KEYWORD_WEIGHT: f32 = 4.0;
+const SUMMARY_WEIGHT: f32 = 2.0;
+const DESCRIPTION_WEIGHT: f32 = 1.0;
+
+/// How much each kind of match counts, relative to an exact one.
+const STEM_FACTOR: f32 = 0.9;
+const PREFIX_FACTOR: f32 = 0.6;
+const TYPO_FACTOR: f32 = 0.4;
+
+/// Words too common to tell documents apart.
+const STOP_WORDS: &[&str] = &[
+  "a",
+  "an",
+  "and",
+  "app",
+  "application",
+  "as",
+  "at",
+  "by",
+  "for",
+  "from",
+  "in",
+  "is",
+  "it",
+  "of",
+  "on",
+  "or",
+  "the",
+  "to",
+  "with",
+  "you",
+  "your",
+];
+
+/// Filters of a [`SearchIndex::search`], besides the text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+  pub text: String,
+  /// Only documents in this AppStream category, e.g. `Graphics`.
+  pub category: Option<String>,
+  /// Only installed documents, or only those which aren't.
+  pub installed: Option<bool>,
+  /// Only documents from this remote.
+  pub remote: Option<String>,
+}
+
+/// A document matching a query. It's a component of the catalog, an
+/// installed ref, or both when the component is installed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+  /// Index in the components of the catalog.
+  pub component: Option<usize>,
+  /// Index in the installed refs.
+  pub installed: Option<usize>,
+  /// Higher is better, 0 when the query has no text.
+  pub score: f32,
+}
+
+#[derive(Clone, Debug)]
+struct Document {
+  component: Option<usize>,
+  installed: Option<usize>,
+  /// To sort documents scoring the same.
+  name: String,
+  categories: Vec<String>,
+  remote: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Posting {
+  document: u32,
+  weight: f32,
+}
+
+/// An index of the apps of the catalog and the installed refs, built once
+/// and searched as the user types.
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+  documents: Vec<Document>,
+  /// Postings of every token, sorted by token for prefix lookups.
+  terms: BTreeMap<String, Vec<Posting>>,
+  /// Tokens by stem.
+  stems: HashMap<String, Vec<String>>,
+}
+
+impl SearchIndex {
+  /// Index the apps of `catalog` and the installed `refs`. Installed refs are
+  /// merged with the component installing them.
+  pub fn new(catalog: &Catalog, refs: &[FpRef]) -> Self {
+    let mut index = SearchIndex::default();
+    let installed: HashMap<String, usize> = refs
+      .iter()
+      .enumerate()
+      .map(|(i, r)| (r.format_ref(), i))
+      .collect();
+    let mut merged = vec![false; refs.len()];
+
+    for (i, component) in catalog.components.iter().enumerate() {
+      if !component.is_app() {
+        continue;
+      }
+      let installed = component
+        .bundle
+        .as_ref()
+        .and_then(|bundle| installed.get(bundle))
+        .copied();
+      if let Some(installed) = installed {
+        merged[installed] = true;
+      }
+      let keywords = component.keywords.join(" ");
+      let fields = [
+        (component.name.as_deref(), NAME_WEIGHT),
+        (Some(component.id.as_str()), ID_WEIGHT),
+        (Some(keywords.as_str()), KEYWORD_WEIGHT),
+        (component.summary.as_deref(), SUMMARY_WEIGHT),
+        (component.description.as_deref(), DESCRIPTION_WEIGHT),
+      ];
+      index.add(
+        Document {
+          component: Some(i),
+          installed,
+          name: component.display_name().to_lowercase(),
+          categories: component.categories.clone(),
+          remote: Some(component.remote.clone()),
+        },
+        &fields,
+      );
+    }
+
+    for (i, r) in refs.iter().enumerate() {
+      if merged[i] {
+        continue;
+      }
+      let fields = [
+        (r.name.as_deref(), NAME_WEIGHT),
+        (Some(r.id.as_str()), ID_WEIGHT),
+        (r.summary.as_deref(), SUMMARY_WEIGHT),
+      ];
+      index.add(
+        Document {
+          component: None,
+          installed: Some(i),
+          name: r.display_name().to_lowercase(),
+          categories: vec![],
+          remote: r.origin.clone(),
+        },
+        &fields,
+      );
+    }
+
+    for postings in index.terms.values_mut() {
+      postings.shrink_to_fit();
+    }
+    index
+  }
+
+  fn add(&mut self, document: Document, fields: &[(Option<&str>, f32)]) {
+    let id = self.documents.len() as u32;
+    self.documents.push(document);
+
+    // A token counts once per document, in its best field.
+    let mut weights: HashMap<String, f32> = HashMap::new();
+    for (text, weight) in fields {
+      for token in tokenize(text.unwrap_or_default()) {
+        let best = weights.entry(token).or_insert(0.0);
+        *best = best.max(*weight);
+      }
+    }
+    for (token, weight) in weights {
+      let postings = self.terms.entry(token.clone()).or_insert_with(|| {
+        let stem = stem(&token);
+        self.stems.entry(stem).or_default().push(token.clone());
+        vec![]
+      });
+      postings.push(Posting {
+        document: id,
+        weight,
+      });
+    }
+  }
+
+  /// Every document matching `query`: containing every word of its text,
+  /// exactly, through its stem, as a prefix or with a typo, and matching its
+  /// filters. The best matches come first. Without text, every document
+  /// matching the filters is returned, by name.
+  pub fn search(&self, query: &Query) -> Vec<Hit> {
+    let filter = |document: &Document| {
+      query
+        .category
+        .as_ref()
+        .is_none_or(|category| document.categories.contains(category))
+        && query
+          .installed
+          .is_none_or(|installed| document.installed.is_some() == installed)
+        && query
+          .remote
+          .as_ref()
+          .is_none_or(|remote| document.remote.as_ref() == Some(remote))
+    };
+
+    let tokens = tokenize(&query.text);
+    let mut scores: Vec<(u32, f32)> = if tokens.is_empty() {
+      (0..self.documents.len() as u32).map(|d| (d, 0.0)).collect()
+    } else {
+      let mut total: Option<HashMap<u32, f32>> = None;
+      for token in &tokens {
+        let scores = self.score_token(token);
+        total = Some(match total {
+          None => scores,
+          // Every word must match.
+          Some(total) => total
+            .into_iter()
+            .filter_map(|(d, score)| Some((d, score + scores.get(&d)?)))
+            .collect(),
+        });
+      }
+      total.unwrap_or_default().into_iter().collect()
+    };
+
+    scores.retain(|(d, _)| filter(&self.documents[*d as usize]));
+    scores.sort_by(|(a, a_score), (b, b_score)| {
+      b_score
+        .partial_cmp(a_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| {
+          self.documents[*a as usize]
+            .name
+            .cmp(&self.documents[*b as usize].name)
+        })
+    });
+    scores
+      .into_iter()
+      .map(|(d, score)| {
+        let document = &self.documents[d as usize];
+        Hit {
+          component: document.component,
+          installed: document.installed,
+          score,
+        }
+      })
+      .collect()
+  }
+
+  /// The score of every document matching `token`, by its best match.
+  fn score_token(&self, token: &str) -> HashMap<u32, f32> {
+    let mut factors: HashMap<&str, f32> = HashMap::new();
+
+    if let Some((term, _)) = self.terms.get_key_value(token) {
+      factors.insert(term, 1.0);
+    }
+    if let Some(terms) = self.stems.get(&stem(token)) {
+      for term in terms {
+        factors.entry(term).or_insert(STEM_FACTOR);
+      }
+    }
+    for (term, _) in self
+      .terms
+      .range::<str, _>((Bound::Included(token), Bound::Unbounded))
+      .take_while(|(term, _)| term.starts_with(token))
+    {
+      factors.entry(term).or_insert(PREFIX_FACTOR);
+    }
+    // Typos only when nothing else matched, they're the most expensive.
+    if factors.is_empty()
+      && let Some(max_distance) = max_typos(token)
+    {
+      let first = token.chars().next().unwrap_or_default();
+      let start = first.to_string();
+      for (term, _) in self
+        .terms
+        .range::<str, _>((Bound::Included(start.as_str()), Bound::Unbounded))
+        .take_while(|(term, _)| term.starts_with(first))
+      {
+        if term.len().abs_diff(token.len()) <= max_distance && distance(token, term) <= max_distance
+        {
+          factors.insert(term, TYPO_FACTOR);
+        }
+      }
+    }
+
+    let mut scores: HashMap<u32, f32> = HashMap::new();
+    for (term, factor) in factors {
+      for posting in &self.terms[term] {
+        let score = scores.entry(posting.document).or_insert(0.0);
+        *score = score.max(posting.weight * factor);
+      }
+    }
+    scores
+  }
+}
+
+/// The lowercase words of `text`, without stop words.
+fn tokenize(text: &str) -> Vec<String> {
+  text
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+    .collect()
+}
+
+/// Strip the common English suffixes of `token`, so that e.g. `edit`,
+/// `editing` and `edits` are found together.
+fn stem(token: &str) -> String {
+  const SUFFIXES: &[(&str, &str)] = &[
+    ("ational", "ate"),
+    ("ations", "ate"),
+    ("ation", "ate"),
+    ("ities", "ity"),
+    ("ings", ""),
+    ("ing", ""),
+    ("ies", "y"),
+    ("ers", ""),
+    ("er", ""),
+    ("ed", ""),
+    ("es", ""),
+    ("ly", ""),
+    ("s", ""),
+  ];
+  for (suffix, replacement) in SUFFIXES {
+    if let Some(stem) = token.strip_suffix(suffix) {
+      // Too short a stem would match unrelated words, and `ss` isn't a
+      // plural.
+      if stem.chars().count() >= 3 && !(suffix == &"s" && stem.ends_with('s')) {
+        return format!("{}{}", stem, replacement);
+      }
+    }
+  }
+  token.to_string()
+}
+
+/// How many typos are tolerated in `token`, if any: short words would
+/// match too many others.
+fn max_typos(token: &str) -> Option<usize> {
+  match token.chars().count() {
+    0..=3 => None,
+    4..=7 => Some(1),
+    _ => Some(2),
+  }
+}
+
+/// The Damerau-Levenshtein distance between `a` and `b`: how many
+/// insertions, deletions, substitutions and transpositions turn one into the
+/// other.
+fn distance(a: &str, b: &str) -> usize {
+  let a: Vec<char> = a.chars().collect();
+  let b: Vec<char> = b.chars().collect();
+  let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+  for (i, row) in rows.iter_mut().enumerate() {
+    row[0] = i;
+  }
+  for (j, cell) in rows[0].iter_mut().enumerate() {
+    *cell = j;
+  }
+  for i in 1..=a.len() {
+    for j in 1..=b.len() {
+      let cost = usize::from(a[i - 1] != b[j - 1]);
+      rows[i][j] = (rows[i - 1][j] + 1)
+        .min(rows[i][j - 1] + 1)
+        .min(rows[i - 1][j - 1] + cost);
+      if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+        rows[i][j] = rows[i][j].min(rows[i - 2][j - 2] + 1);
+      }
+    }
+  }
+  rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::services::catalog::Component;
+
+  fn app(id: &str, name: &str, summary: &str, category: &str) -> Component {
+    Component {
+      id: id.to_string(),
+      kind: "desktop-application".to_string(),
+      remote: "flathub".to_string(),
+      name: Some(name.to_string()),
+      summary: Some(summary.to_string()),
+      categories: vec![category.to_string()],
+      ..Component::default()
+    }
+  }
+
+  fn index() -> SearchIndex {
+    let catalog = Catalog {
+      components: vec![
+        app("org.example.Notes", "Notes", "A text editor", "Office"),
+        app(
+          "org.example.Editor",
+          "Editor",
+          "Edit anything",
+          "Development",
+        ),
+        app("org.gnome.Maps", "Maps", "Find places", "Utility"),
+        app("org.example.Atlas", "Atlas", "Maps of the world", "Utility"),
+        Component {
+          kind: "runtime".to_string(),
+          ..app("org.example.Platform", "Editor platform", "", "")
+        },
+      ],
+    };
+    SearchIndex::new(&catalog, &[])
+  }
+
+  /// The ids of the components matching `text`, best first.
+  fn search(index: &SearchIndex, query: Query) -> Vec<&'static str> {
+    const IDS: &[&str] = &[
+      "org.example.Notes",
+      "org.example.Editor",
+      "org.gnome.Maps",
+      "org.example.Atlas",
+    ];
+    index
+      .search(&query)
+      .into_iter()
+      .map(|hit| IDS[hit.component.unwrap()])
+      .collect()
+  }
+
+  fn text(text: &str) -> Query {
+    Query {
+      text: text.to_string(),
+      ..Query::default()
+    }
+  }
+
+  #[test]
+  fn tokenizes_words() {
+    assert_eq!(
+      tokenize("The GIMP, an Image-Editor!"),
+      ["gimp", "image", "editor"]
+    );
+    assert_eq!(tokenize("org.gnome.Maps"), ["org", "gnome", "maps"]);
+    assert!(tokenize(" - the app - ").is_empty());
+  }
+
+  #[test]
+  fn stems_words() {
+    assert_eq!(stem("editing"), "edit");
+    assert_eq!(stem("edits"), "edit");
+    assert_eq!(stem("edited"), "edit");
+    assert_eq!(stem("cities"), "city");
+    assert_eq!(stem("glass"), "glass");
+    assert_eq!(stem("bus"), "bus");
+  }
+
+  #[test]
+  fn measures_typos() {
+    assert_eq!(distance("editor", "editor"), 0);
+    assert_eq!(distance("edtior", "editor"), 1);
+    assert_eq!(distance("edior", "editor"), 1);
+    assert_eq!(distance("kitten", "sitting"), 3);
+    assert_eq!(max_typos("map"), None);
+    assert_eq!(max_typos("maps"), Some(1));
+    assert_eq!(max_typos("document"), Some(2));
+  }
+
+  #[test]
+  fn ranks_names_over_summaries() {
+    let index = index();
+    assert_eq!(
+      search(&index, text("editor")),
+      ["org.example.Editor", "org.example.Notes"]
+    );
+    assert_eq!(
+      search(&index, text("maps")),
+      ["org.gnome.Maps", "org.example.Atlas"]
+    );
+  }
+
+  #[test]
+  fn matches_prefixes() {
+    let index = index();
+    assert_eq!(
+      search(&index, text("edi")),
+      ["org.example.Editor", "org.example.Notes"]
+    );
+    let hits = index.search(&text("edi"));
+    let exact = index.search(&text("editor"));
+    assert!(hits[0].score < exact[0].score);
+    // Prefixes past the last term end the range.
+    assert!(search(&index, text("zz")).is_empty());
+  }
+
+  #[test]
+  fn matches_stems_and_typos() {
+    let index = index();
+    assert_eq!(
+      search(&index, text("map")),
+      ["org.gnome.Maps", "org.example.Atlas"]
+    );
+    assert_eq!(
+      search(&index, text("edtior")),
+      ["org.example.Editor", "org.example.Notes"]
+    );
+  }
+
+  #[test]
+  fn requires_every_word() {
+    let index = index();
+    assert_eq!(search(&index, text("text editor")), ["org.example.Notes"]);
+    assert!(search(&index, text("editor maps")).is_empty());
+  }
+
+  #[test]
+  fn filters_and_sorts_by_name_without_text() {
+    let index = index();
+    assert_eq!(
+      search(&index, Query::default()),
+      [
+        "org.example.Atlas",
+        "org.example.Editor",
+        "org.gnome.Maps",
+        "org.example.Notes"
+      ]
+    );
+    assert_eq!(
+      search(
+        &index,
+        Query {
+          category: Some("Utility".to_string()),
+          ..Query::default()
+        }
+      ),
+      ["org.example.Atlas", "org.gnome.Maps"]
+    );
+    assert!(search(
+      &index,
+      Query {
+        installed: Some(true),
+        ..Query::default()
+      }
+    )
+    .is_empty());
+  }
+}
@@ -0,0 +1,207 @@
+//! Runs transactions in a worker against a repository and an installation
+//! created in a temporary directory. Needs the `flatpak` command to build
+//! the repository, and is skipped without it.
+
+use std::{
+  env, fs,
+  path::{Path, PathBuf},
+  process::Command,
+};
+
+use adw::{
+  gio::{Cancellable, File},
+  glib::MainContext,
+};
+use futures::StreamExt;
+use libflatpak::{
+  prelude::{InstallationExt, RefExt, RemoteExt},
+  Installation, Remote,
+};
+use rouge_software::{
+  reactive::{
+    component::{Component, ViewContext},
+    vnode::VNode,
+    worker::Job,
+  },
+  services::flatpak::transaction::{self, OperationKind, TransactionEvent},
+};
+
+const REMOTE: &str = "test-repo";
+const RUNTIME: &str = "org.rouge.Test.Platform";
+
+#[derive(Clone, Debug)]
+enum Message {
+  Event(TransactionEvent),
+  Done(Result<(), String>),
+}
+
+/// Only there to give jobs a message type.
+#[derive(Clone, Debug, Default)]
+struct Installer;
+
+impl Component for Installer {
+  type Message = Message;
+  type Props = ();
+
+  fn view(&self, _: &ViewContext<Self>) -> VNode<'_, Self> {
+    unreachable!()
+  }
+}
+
+/// A directory removed once the test is over.
+struct TempDir(PathBuf);
+
+impl TempDir {
+  fn new(name: &str) -> Self {
+    let path = env::temp_dir().join(format!("rouge-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    TempDir(path)
+  }
+}
+
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.0);
+  }
+}
+
+fn flatpak(args: &[&str]) -> bool {
+  Command::new("flatpak")
+    .args(args)
+    .status()
+    .is_ok_and(|status| status.success())
+}
+
+/// Export a tiny runtime to a new repository in `dir`, returning its path.
+fn make_repo(dir: &Path) -> PathBuf {
+  let build = dir.join("build");
+  fs::create_dir_all(build.join("usr/share/rouge-test")).unwrap();
+  fs::write(build.join("usr/share/rouge-test/hello"), "hello\n").unwrap();
+  fs::write(
+    build.join("metadata"),
+    format!("[Runtime]\nname={}\n", RUNTIME),
+  )
+  .unwrap();
+
+  let repo = dir.join("repo");
+  assert!(
+    flatpak(&[
+      "build-export",
+      "--runtime",
+      "--disable-sandbox",
+      repo.to_str().unwrap(),
+      build.to_str().unwrap(),
+      "stable",
+    ]),
+    "flatpak build-export failed"
+  );
+  repo
+}
+
+/// A user installation in `dir`, with the repository at `repo` as remote.
+fn make_installation(dir: &Path, repo: &Path) -> Installation {
+  let cancellable: Option<&Cancellable> = None;
+  let installation =
+    Installation::for_path(&File::for_path(dir.join("installation")), true, cancellable).unwrap();
+  let remote = Remote::new(REMOTE);
+  remote.set_url(&format!("file://{}", repo.display()));
+  remote.set_gpg_verify(false);
+  installation
+    .add_remote(&remote, false, cancellable)
+    .unwrap();
+  installation
+}
+
+/// Install `ref_` in a worker, returning the messages it delivered.
+fn install(installation_dir: PathBuf, ref_: String) -> Vec<Message> {
+  let job = Job::<Installer>::blocking(move |worker| {
+    let cancellable: Option<&Cancellable> = None;
+    let result = Installation::for_path(&File::for_path(&installation_dir), true, cancellable)
+      .and_then(|installation| {
+        let progress = worker.clone();
+        transaction::install_in(
+          &installation,
+          REMOTE,
+          &ref_,
+          move |event| progress.progress(Message::Event(event)),
+          worker.cancellable(),
+        )
+      });
+    Message::Done(result.map_err(|error| error.to_string()))
+  });
+  MainContext::new().block_on(job.collect())
+}
+
+#[test]
+fn installs_from_a_local_repository() {
+  if !flatpak(&["--version"]) {
+    eprintln!("flatpak is not available, skipping.");
+    return;
+  }
+  let dir = TempDir::new("transaction");
+  let repo = make_repo(&dir.0);
+  let installation = make_installation(&dir.0, &repo);
+  let arch = libflatpak::functions::default_arch().unwrap();
+  let ref_ = format!("runtime/{}/{}/stable", RUNTIME, arch);
+
+  let messages = install(dir.0.join("installation"), ref_.clone());
+  let events: Vec<&TransactionEvent> = messages
+    .iter()
+    .filter_map(|message| match message {
+      Message::Event(event) => Some(event),
+      Message::Done(_) => None,
+    })
+    .collect();
+  assert!(
+    matches!(messages.last(), Some(Message::Done(Ok(())))),
+    "{:?}",
+    messages
+  );
+  match events.first() {
+    Some(TransactionEvent::Ready(operations)) => {
+      assert_eq!(operations.len(), 1);
+      assert_eq!(operations[0].ref_, ref_);
+      assert_eq!(operations[0].kind, OperationKind::Install);
+      assert_eq!(operations[0].remote.as_deref(), Some(REMOTE));
+    }
+    event => panic!("expected the operations first, got {:?}", event),
+  }
+  assert!(events
+    .iter()
+    .any(|event| matches!(event, TransactionEvent::Started(operation) if operation.ref_ == ref_)));
+  assert!(events.iter().any(|event| matches!(
+    event,
+    TransactionEvent::Done { ref_: done, changed: true } if *done == ref_
+  )));
+
+  let cancellable: Option<&Cancellable> = None;
+  let installed = installation.list_installed_refs(cancellable).unwrap();
+  let installed: Vec<String> = installed
+    .iter()
+    .filter_map(|installed| installed.format_ref())
+    .map(String::from)
+    .collect();
+  assert_eq!(installed, vec![ref_]);
+}
+
+#[test]
+fn reports_missing_refs() {
+  if !flatpak(&["--version"]) {
+    eprintln!("flatpak is not available, skipping.");
+    return;
+  }
+  let dir = TempDir::new("missing-ref");
+  let repo = make_repo(&dir.0);
+  make_installation(&dir.0, &repo);
+
+  let messages = install(
+    dir.0.join("installation"),
+    "app/org.rouge.Missing/x86_64/stable".to_string(),
+  );
+  assert!(
+    matches!(messages.last(), Some(Message::Done(Err(_)))),
+    "{:?}",
+    messages
+  );
+}